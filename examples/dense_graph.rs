@@ -0,0 +1,63 @@
+//! Comparing the sparse and dense graph representations on a synthetic
+//! program where most nodes end up related: a long chain of vars fed by a
+//! value at every link, so closing over each new edge touches everything
+//! upstream of it.
+//!
+//! Run with `cargo run --release --example dense_graph [links]`.
+
+use std::time::{Duration, Instant};
+use typical::{AbstractTypes, TypeChecker, Use, Value};
+
+#[derive(Debug)]
+struct Unit;
+
+impl AbstractTypes<(), ()> for Unit {
+    type Error = ();
+
+    fn meet(&self, _: &(), _: &()) -> Result<Vec<(Value, Use)>, Self::Error> {
+        Ok(vec![])
+    }
+}
+
+/// Builds a chain of `links` vars, flowing a fresh value into each one,
+/// returning the time taken and whether the first value reaches the end.
+fn chain(mut t: TypeChecker<(), (), Unit>, links: usize) -> (Duration, bool) {
+    let start = Instant::now();
+    let first = t.new_val(());
+    let (mut prev, head_use) = t.var();
+    t.flow(first, head_use).unwrap();
+
+    for _ in 1..links {
+        let (next_val, next_use) = t.var();
+        let value = t.new_val(());
+        t.flow(value, next_use).unwrap();
+        t.flow(prev, next_use).unwrap();
+        prev = next_val;
+    }
+    let sink = t.new_use(());
+    t.flow(prev, sink).unwrap();
+
+    (start.elapsed(), t.reaches(first, sink))
+}
+
+fn main() {
+    let links = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(500);
+
+    let (sparse, _) = chain(TypeChecker::new(Unit), links);
+    let (dense, _) = chain(TypeChecker::with_dense_graph(Unit), links);
+    println!("{} links: sparse {:?}, dense {:?}", links, sparse, dense);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_representations_should_close_the_chain() {
+        assert!(chain(TypeChecker::new(Unit), 100).1);
+        assert!(chain(TypeChecker::with_dense_graph(Unit), 100).1);
+    }
+}
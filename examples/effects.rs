@@ -0,0 +1,418 @@
+//! An exception tracking mini-language where every function carries a latent
+//! effect row of the exceptions it may raise, calls propagate that row into
+//! the caller and `try` discharges the exceptions it catches.
+//!
+//! Types and effects live in separate kinds sharing one `TypeChecker`: effect
+//! rows are simply the set of `Raises` value heads reaching an effect use, and
+//! any flow between the two kinds is reported as a kind mismatch.
+//!
+//! Run with `cargo run --example effects`.
+
+use std::collections::{BTreeSet, HashMap};
+use typical::{AbstractTypes, TypeChecker, Use, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Type,
+    Effect,
+}
+
+#[derive(Debug, Clone)]
+enum EffValue {
+    Int,
+    Bool,
+    Func {
+        arg: Use,
+        ret: Value,
+        effects: Value,
+    },
+    /// A single exception that may be raised. Because `meet` can only relate
+    /// existing nodes, each exception carries a `reraise` handle that already
+    /// flows into itself so that handlers can forward it further out.
+    Raises {
+        name: String,
+        reraise: Value,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum EffUse {
+    Int,
+    Bool,
+    Func {
+        arg: Value,
+        ret: Use,
+        effects: Use,
+    },
+    /// A `try` block, discharging `caught` and forwarding anything else to
+    /// the enclosing handler.
+    Catch {
+        caught: BTreeSet<String>,
+        rest: Use,
+    },
+    /// The outermost effect context, permitting only the listed exceptions.
+    Permits(BTreeSet<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum EffError {
+    Unhandled(String),
+    KindMismatch { expected: Kind, found: Kind },
+    Mismatch,
+}
+
+impl EffValue {
+    fn kind(&self) -> Kind {
+        match self {
+            EffValue::Raises { .. } => Kind::Effect,
+            _ => Kind::Type,
+        }
+    }
+}
+
+impl EffUse {
+    fn kind(&self) -> Kind {
+        match self {
+            EffUse::Catch { .. } | EffUse::Permits(_) => Kind::Effect,
+            _ => Kind::Type,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct EffectTypeSystem;
+
+impl AbstractTypes<EffValue, EffUse> for EffectTypeSystem {
+    type Error = EffError;
+
+    fn meet(&self, lhs: &EffValue, rhs: &EffUse) -> Result<Vec<(Value, Use)>, Self::Error> {
+        if lhs.kind() != rhs.kind() {
+            return Err(EffError::KindMismatch {
+                expected: rhs.kind(),
+                found: lhs.kind(),
+            });
+        }
+
+        match (lhs, rhs) {
+            (EffValue::Int, EffUse::Int) | (EffValue::Bool, EffUse::Bool) => Ok(vec![]),
+            (
+                EffValue::Func {
+                    arg: lhs_arg,
+                    ret: lhs_ret,
+                    effects: lhs_effects,
+                },
+                EffUse::Func {
+                    arg: rhs_arg,
+                    ret: rhs_ret,
+                    effects: rhs_effects,
+                },
+            ) => Ok(vec![
+                (*rhs_arg, *lhs_arg),
+                (*lhs_ret, *rhs_ret),
+                (*lhs_effects, *rhs_effects),
+            ]),
+            (EffValue::Raises { name, .. }, EffUse::Catch { caught, .. })
+                if caught.contains(name) =>
+            {
+                Ok(vec![])
+            }
+            (EffValue::Raises { reraise, .. }, EffUse::Catch { rest, .. }) => {
+                Ok(vec![(*reraise, *rest)])
+            }
+            (EffValue::Raises { name, .. }, EffUse::Permits(permitted)) => {
+                if permitted.contains(name) {
+                    Ok(vec![])
+                } else {
+                    Err(EffError::Unhandled(name.clone()))
+                }
+            }
+            _ => Err(EffError::Mismatch),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Int,
+    Bool,
+    Ident(String),
+    Add(Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    Lambda(String, Box<Expr>),
+    Call(Box<Expr>, Box<Expr>),
+    Let(String, Box<Expr>, Box<Expr>),
+    /// `raise Name`
+    Raise(String),
+    /// `try body catch (Name, ...)`
+    Try(Box<Expr>, Vec<String>),
+}
+
+type Checker = TypeChecker<EffValue, EffUse, EffectTypeSystem>;
+
+struct Context {
+    checker: Checker,
+    env: HashMap<String, Value>,
+}
+
+impl Context {
+    fn new() -> Self {
+        Self {
+            checker: TypeChecker::new(EffectTypeSystem),
+            env: HashMap::new(),
+        }
+    }
+
+    fn exception(&mut self, name: &str) -> Result<Value, EffError> {
+        let (reraise, reraise_use) = self.checker.var();
+        let raised = self.checker.new_val(EffValue::Raises {
+            name: name.to_string(),
+            reraise,
+        });
+        self.checker.flow(raised, reraise_use)?;
+        Ok(raised)
+    }
+
+    /// Checks `expr`, flowing every exception it may raise into `effects`.
+    fn check(&mut self, expr: &Expr, effects: Use) -> Result<Value, EffError> {
+        match expr {
+            Expr::Int => Ok(self.checker.new_val(EffValue::Int)),
+            Expr::Bool => Ok(self.checker.new_val(EffValue::Bool)),
+            Expr::Ident(name) => Ok(*self.env.get(name).expect("unbound identifier")),
+            Expr::Add(lhs, rhs) => {
+                for operand in [lhs, rhs].iter() {
+                    let operand = self.check(operand, effects)?;
+                    let int_use = self.checker.new_use(EffUse::Int);
+                    self.checker.flow(operand, int_use)?;
+                }
+                Ok(self.checker.new_val(EffValue::Int))
+            }
+            Expr::If(cond, then, otherwise) => {
+                let cond = self.check(cond, effects)?;
+                let bool_use = self.checker.new_use(EffUse::Bool);
+                self.checker.flow(cond, bool_use)?;
+
+                let (merged_value, merged_use) = self.checker.var();
+                for branch in [then, otherwise].iter() {
+                    let branch = self.check(branch, effects)?;
+                    self.checker.flow(branch, merged_use)?;
+                }
+                Ok(merged_value)
+            }
+            Expr::Lambda(param, body) => {
+                let (arg_value, arg_use) = self.checker.var();
+                let (latent_value, latent_use) = self.checker.var();
+
+                let shadowed = self.env.insert(param.clone(), arg_value);
+                let ret = self.check(body, latent_use);
+                match shadowed {
+                    Some(prev) => self.env.insert(param.clone(), prev),
+                    None => self.env.remove(param),
+                };
+
+                Ok(self.checker.new_val(EffValue::Func {
+                    arg: arg_use,
+                    ret: ret?,
+                    effects: latent_value,
+                }))
+            }
+            Expr::Call(func, arg) => {
+                let func = self.check(func, effects)?;
+                let arg = self.check(arg, effects)?;
+                let (ret_value, ret_use) = self.checker.var();
+                let call = self.checker.new_use(EffUse::Func {
+                    arg,
+                    ret: ret_use,
+                    effects,
+                });
+                self.checker.flow(func, call)?;
+                Ok(ret_value)
+            }
+            Expr::Let(name, bound, body) => {
+                let bound = self.check(bound, effects)?;
+                let shadowed = self.env.insert(name.clone(), bound);
+                let body = self.check(body, effects);
+                match shadowed {
+                    Some(prev) => self.env.insert(name.clone(), prev),
+                    None => self.env.remove(name),
+                };
+                body
+            }
+            Expr::Raise(name) => {
+                let raised = self.exception(name)?;
+                self.checker.flow(raised, effects)?;
+                // raising never returns, so the result is left unconstrained.
+                Ok(self.checker.var().0)
+            }
+            Expr::Try(body, caught) => {
+                let handler = self.checker.new_use(EffUse::Catch {
+                    caught: caught.iter().cloned().collect(),
+                    rest: effects,
+                });
+                self.check(body, handler)
+            }
+        }
+    }
+}
+
+/// Checks a whole program, permitting only the `permitted` exceptions to
+/// escape it.
+fn check_program(expr: &Expr, permitted: &[&str]) -> Result<(), EffError> {
+    let mut ctx = Context::new();
+    let top = ctx.checker.new_use(EffUse::Permits(
+        permitted.iter().map(|name| name.to_string()).collect(),
+    ));
+    ctx.check(expr, top).map(|_| ())
+}
+
+fn lambda(param: &str, body: Expr) -> Expr {
+    Expr::Lambda(param.to_string(), Box::new(body))
+}
+
+fn call(func: Expr, arg: Expr) -> Expr {
+    Expr::Call(Box::new(func), Box::new(arg))
+}
+
+fn ident(name: &str) -> Expr {
+    Expr::Ident(name.to_string())
+}
+
+fn let_in(name: &str, bound: Expr, body: Expr) -> Expr {
+    Expr::Let(name.to_string(), Box::new(bound), Box::new(body))
+}
+
+fn try_catch(body: Expr, caught: &[&str]) -> Expr {
+    Expr::Try(
+        Box::new(body),
+        caught.iter().map(|name| name.to_string()).collect(),
+    )
+}
+
+/// let parse = fun s -> raise ParseError in
+/// let read  = fun path -> raise IoError in
+/// fun x -> parse (read x)
+fn pipeline() -> Expr {
+    let_in(
+        "parse",
+        lambda("s", Expr::Raise("ParseError".to_string())),
+        let_in(
+            "read",
+            lambda("path", Expr::Raise("IoError".to_string())),
+            call(ident("parse"), call(ident("read"), Expr::Int)),
+        ),
+    )
+}
+
+fn main() {
+    println!("unhandled: {:?}", check_program(&pipeline(), &[]));
+    println!(
+        "catching ParseError: {:?}",
+        check_program(&try_catch(pipeline(), &["ParseError"]), &[])
+    );
+    println!(
+        "catching both: {:?}",
+        check_program(&try_catch(pipeline(), &["ParseError", "IoError"]), &[])
+    );
+    println!(
+        "catching ParseError, permitting IoError: {:?}",
+        check_program(&try_catch(pipeline(), &["ParseError"]), &["IoError"])
+    );
+    println!(
+        "pure program: {:?}",
+        check_program(&call(lambda("x", ident("x")), Expr::Bool), &[])
+    );
+    println!(
+        "conditional raise: {:?}",
+        check_program(
+            &Expr::If(
+                Box::new(Expr::Bool),
+                Box::new(Expr::Add(Box::new(Expr::Int), Box::new(Expr::Int))),
+                Box::new(Expr::Raise("Overflow".to_string())),
+            ),
+            &["Overflow"],
+        )
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_unhandled_exceptions_from_calls() {
+        assert!(matches!(
+            check_program(&pipeline(), &[]),
+            Err(EffError::Unhandled(_))
+        ));
+    }
+
+    #[test]
+    fn should_not_raise_latent_effects_without_a_call() {
+        let defined = lambda("x", Expr::Raise("Boom".to_string()));
+        assert_eq!(Ok(()), check_program(&defined, &[]));
+        assert_eq!(
+            Err(EffError::Unhandled("Boom".to_string())),
+            check_program(&call(defined, Expr::Int), &[])
+        );
+    }
+
+    #[test]
+    fn should_discharge_caught_exceptions_and_forward_the_rest() {
+        assert_eq!(
+            Err(EffError::Unhandled("IoError".to_string())),
+            check_program(&try_catch(pipeline(), &["ParseError"]), &[])
+        );
+        assert_eq!(
+            Ok(()),
+            check_program(&try_catch(pipeline(), &["ParseError"]), &["IoError"])
+        );
+        assert_eq!(
+            Ok(()),
+            check_program(&try_catch(pipeline(), &["ParseError", "IoError"]), &[])
+        );
+    }
+
+    #[test]
+    fn should_forward_through_nested_handlers() {
+        let nested = try_catch(try_catch(pipeline(), &["IoError"]), &["ParseError"]);
+        assert_eq!(Ok(()), check_program(&nested, &[]));
+    }
+
+    #[test]
+    fn should_raise_from_either_branch() {
+        let conditional = Expr::If(
+            Box::new(Expr::Bool),
+            Box::new(Expr::Raise("Left".to_string())),
+            Box::new(Expr::Add(Box::new(Expr::Int), Box::new(Expr::Int))),
+        );
+        assert_eq!(
+            Err(EffError::Unhandled("Left".to_string())),
+            check_program(&conditional, &[])
+        );
+        assert_eq!(Ok(()), check_program(&conditional, &["Left"]));
+    }
+
+    #[test]
+    fn should_reject_flows_between_kinds() {
+        let mut ctx = Context::new();
+        let effects = ctx.checker.new_use(EffUse::Permits(BTreeSet::new()));
+        let func = ctx.check(&lambda("x", ident("x")), effects).unwrap();
+
+        assert_eq!(
+            Err(EffError::KindMismatch {
+                expected: Kind::Effect,
+                found: Kind::Type
+            }),
+            ctx.checker.flow(func, effects)
+        );
+
+        let raised = ctx.exception("Boom").unwrap();
+        let int_use = ctx.checker.new_use(EffUse::Int);
+        assert_eq!(
+            Err(EffError::KindMismatch {
+                expected: Kind::Type,
+                found: Kind::Effect
+            }),
+            ctx.checker.flow(raised, int_use)
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Incrementally re-checking a program after an edit.
+//!
+//! The program is a sequence of top-level definitions, each of which may refer
+//! to the definitions before it. The driver checks definitions one at a time
+//! and keeps a checkpoint of the checker taken before each of them. When a
+//! definition is edited it is marked dirty, the checker is restored to the
+//! checkpoint preceding the first dirty definition and only the definitions
+//! from that point onwards are checked again. The checker is `Clone`, so
+//! cloning is used as the checkpoint to retract everything the edited region
+//! contributed to the constraint graph.
+//!
+//! Run with `cargo run --example incremental`.
+
+use std::collections::HashMap;
+use typical::{AbstractTypes, TypeChecker, Use, Value};
+
+#[derive(Debug, Clone)]
+enum Ty {
+    Int,
+    Bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CheckError {
+    Mismatch,
+}
+
+#[derive(Debug, Clone)]
+struct PrimitiveTypeSystem;
+
+impl AbstractTypes<Ty, Ty> for PrimitiveTypeSystem {
+    type Error = CheckError;
+
+    fn meet(&self, lhs: &Ty, rhs: &Ty) -> Result<Vec<(Value, Use)>, Self::Error> {
+        match (lhs, rhs) {
+            (Ty::Int, Ty::Int) | (Ty::Bool, Ty::Bool) => Ok(vec![]),
+            _ => Err(CheckError::Mismatch),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Int,
+    Bool,
+    Ident(String),
+    Add(Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Definition {
+    name: String,
+    body: Expr,
+}
+
+type Checker = TypeChecker<Ty, Ty, PrimitiveTypeSystem>;
+
+/// The state a definition may depend on: the checker and the values bound by
+/// earlier definitions.
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    checker: Checker,
+    env: HashMap<String, Value>,
+}
+
+fn check_expr(
+    checker: &mut Checker,
+    env: &HashMap<String, Value>,
+    expr: &Expr,
+) -> Result<Value, CheckError> {
+    match expr {
+        Expr::Int => Ok(checker.new_val(Ty::Int)),
+        Expr::Bool => Ok(checker.new_val(Ty::Bool)),
+        Expr::Ident(name) => Ok(*env.get(name).expect("unbound identifier")),
+        Expr::Add(lhs, rhs) => {
+            for operand in [lhs, rhs].iter() {
+                let operand = check_expr(checker, env, operand)?;
+                let int_use = checker.new_use(Ty::Int);
+                checker.flow(operand, int_use)?;
+            }
+            Ok(checker.new_val(Ty::Int))
+        }
+        Expr::If(cond, then, otherwise) => {
+            let cond = check_expr(checker, env, cond)?;
+            let bool_use = checker.new_use(Ty::Bool);
+            checker.flow(cond, bool_use)?;
+
+            let (merged_value, merged_use) = checker.var();
+            for branch in [then, otherwise].iter() {
+                let branch = check_expr(checker, env, branch)?;
+                checker.flow(branch, merged_use)?;
+            }
+            Ok(merged_value)
+        }
+    }
+}
+
+/// Drives checking of a program, remembering enough state to re-check only
+/// the definitions an edit could have affected.
+struct Session {
+    program: Vec<Definition>,
+    /// `checkpoints[i]` is the state before definition `i` was checked.
+    checkpoints: Vec<Checkpoint>,
+    diagnostics: Vec<Result<(), CheckError>>,
+    /// index of the first definition edited since the last check.
+    first_dirty: Option<usize>,
+    /// number of definitions checked by the most recent call to `check`.
+    last_checked: usize,
+}
+
+impl Session {
+    fn new(program: Vec<Definition>) -> Self {
+        Self {
+            first_dirty: Some(0),
+            program,
+            checkpoints: vec![Checkpoint {
+                checker: TypeChecker::new(PrimitiveTypeSystem),
+                env: HashMap::new(),
+            }],
+            diagnostics: Vec::new(),
+            last_checked: 0,
+        }
+    }
+
+    /// Replaces the body of definition `idx`, marking it dirty.
+    fn edit(&mut self, idx: usize, body: Expr) {
+        self.program[idx].body = body;
+        self.first_dirty = Some(self.first_dirty.map_or(idx, |dirty| dirty.min(idx)));
+    }
+
+    /// Re-checks every definition from the first dirty one onwards, leaving
+    /// earlier results untouched.
+    fn check(&mut self) -> &[Result<(), CheckError>] {
+        let start = match self.first_dirty.take() {
+            Some(start) => start,
+            None => {
+                self.last_checked = 0;
+                return &self.diagnostics;
+            }
+        };
+
+        self.checkpoints.truncate(start + 1);
+        self.diagnostics.truncate(start);
+        let mut state = self.checkpoints[start].clone();
+
+        for def in &self.program[start..] {
+            let result = check_expr(&mut state.checker, &state.env, &def.body);
+            // bind failed definitions to a fresh variable so later
+            // definitions can still be checked.
+            let bound = match &result {
+                Ok(value) => *value,
+                Err(_) => state.checker.var().0,
+            };
+            state.env.insert(def.name.clone(), bound);
+            self.diagnostics.push(result.map(|_| ()));
+            self.checkpoints.push(state.clone());
+        }
+
+        self.last_checked = self.program.len() - start;
+        &self.diagnostics
+    }
+}
+
+fn def(name: &str, body: Expr) -> Definition {
+    Definition {
+        name: name.to_string(),
+        body,
+    }
+}
+
+fn ident(name: &str) -> Box<Expr> {
+    Box::new(Expr::Ident(name.to_string()))
+}
+
+fn program() -> Vec<Definition> {
+    vec![
+        def("one", Expr::Int),
+        def("yes", Expr::Bool),
+        def("two", Expr::Add(ident("one"), ident("one"))),
+        def("pick", Expr::If(ident("yes"), ident("two"), ident("one"))),
+        def("three", Expr::Add(ident("pick"), ident("one"))),
+    ]
+}
+
+fn main() {
+    let mut session = Session::new(program());
+    println!("initial check: {:?}", session.check());
+
+    // two = one + yes
+    session.edit(2, Expr::Add(ident("one"), ident("yes")));
+    let diagnostics = session.check().to_vec();
+    println!(
+        "after edit: {:?} ({} of {} definitions re-checked)",
+        diagnostics,
+        session.last_checked,
+        session.program.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_scratch(program: Vec<Definition>) -> Vec<Result<(), CheckError>> {
+        Session::new(program).check().to_vec()
+    }
+
+    #[test]
+    fn should_check_unedited_program_once() {
+        let mut session = Session::new(program());
+        assert!(session.check().iter().all(Result::is_ok));
+        assert_eq!(5, session.last_checked);
+
+        session.check();
+        assert_eq!(0, session.last_checked);
+    }
+
+    #[test]
+    fn incremental_check_should_match_from_scratch_check() {
+        let edits = vec![
+            (2, Expr::Add(ident("one"), ident("yes"))),
+            (4, Expr::If(ident("yes"), ident("one"), ident("yes"))),
+            (2, Expr::Add(ident("one"), ident("one"))),
+            (0, Expr::Bool),
+        ];
+
+        let mut session = Session::new(program());
+        session.check();
+        for (idx, body) in edits {
+            session.edit(idx, body);
+            let incremental = session.check().to_vec();
+
+            assert_eq!(session.program.len() - idx, session.last_checked);
+            assert_eq!(from_scratch(session.program.clone()), incremental);
+        }
+    }
+
+    #[test]
+    fn should_recheck_from_earliest_of_several_edits() {
+        let mut session = Session::new(program());
+        session.check();
+
+        session.edit(3, Expr::If(ident("yes"), ident("one"), ident("two")));
+        session.edit(1, Expr::Int);
+        let incremental = session.check().to_vec();
+
+        assert_eq!(4, session.last_checked);
+        assert_eq!(Err(CheckError::Mismatch), incremental[3]);
+        assert_eq!(from_scratch(session.program.clone()), incremental);
+    }
+}
@@ -0,0 +1,393 @@
+//! A tiny JavaScript-like expression language demonstrating records with
+//! optional fields, nullable flows and a gradual `Dynamic` type on top of the
+//! flow-based `TypeChecker`.
+//!
+//! Run with `cargo run --example js_like`. The tests at the bottom of the
+//! file double as integration tests for these constructs.
+
+use std::collections::{BTreeMap, HashMap};
+use typical::{AbstractTypes, TypeChecker, Use, Value};
+
+/// Value heads: the shapes a JS value can be observed to have.
+#[derive(Debug, Clone)]
+enum JsValue {
+    Null,
+    Bool,
+    Number,
+    Str,
+    Object(BTreeMap<String, Value>),
+    /// Gradual escape hatch, satisfies every use.
+    Dynamic,
+}
+
+/// Field requirement made by an object use.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Presence {
+    Required,
+    Optional,
+}
+
+/// Use heads: the ways a JS value can be consumed.
+#[derive(Debug, Clone)]
+enum JsUse {
+    Bool,
+    Number,
+    Str,
+    Object {
+        fields: BTreeMap<String, (Use, Presence)>,
+        /// Whether `null` is an acceptable input, as with `?.` access.
+        nullable: bool,
+    },
+    /// Accepts any value, e.g. an argument to `console.log`.
+    Dynamic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum JsTypeError {
+    NullDereference,
+    MissingField(String),
+    Mismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl JsValue {
+    fn name(&self) -> &'static str {
+        match self {
+            JsValue::Null => "null",
+            JsValue::Bool => "boolean",
+            JsValue::Number => "number",
+            JsValue::Str => "string",
+            JsValue::Object(_) => "object",
+            JsValue::Dynamic => "any",
+        }
+    }
+}
+
+impl JsUse {
+    fn name(&self) -> &'static str {
+        match self {
+            JsUse::Bool => "boolean",
+            JsUse::Number => "number",
+            JsUse::Str => "string",
+            JsUse::Object { .. } => "object",
+            JsUse::Dynamic => "any",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct JsTypeSystem;
+
+impl AbstractTypes<JsValue, JsUse> for JsTypeSystem {
+    type Error = JsTypeError;
+
+    fn meet(&self, lhs: &JsValue, rhs: &JsUse) -> Result<Vec<(Value, Use)>, Self::Error> {
+        match (lhs, rhs) {
+            (JsValue::Dynamic, _) | (_, JsUse::Dynamic) => Ok(vec![]),
+            (JsValue::Bool, JsUse::Bool)
+            | (JsValue::Number, JsUse::Number)
+            | (JsValue::Str, JsUse::Str) => Ok(vec![]),
+            (JsValue::Null, JsUse::Object { nullable: true, .. }) => Ok(vec![]),
+            (
+                JsValue::Null,
+                JsUse::Object {
+                    nullable: false, ..
+                },
+            ) => Err(JsTypeError::NullDereference),
+            (JsValue::Object(provided), JsUse::Object { fields, .. }) => {
+                let mut out = vec![];
+                for (name, &(field_use, presence)) in fields {
+                    match (provided.get(name), presence) {
+                        (Some(&field_value), _) => out.push((field_value, field_use)),
+                        (None, Presence::Optional) => (),
+                        (None, Presence::Required) => {
+                            return Err(JsTypeError::MissingField(name.clone()))
+                        }
+                    }
+                }
+                Ok(out)
+            }
+            (lhs, rhs) => Err(JsTypeError::Mismatch {
+                expected: rhs.name(),
+                found: lhs.name(),
+            }),
+        }
+    }
+}
+
+/// The surface syntax of the example language.
+#[derive(Debug, Clone)]
+enum Expr {
+    Null,
+    // literal values are irrelevant to checking so only their kind is kept.
+    Bool,
+    Number,
+    Str,
+    Object(Vec<(String, Expr)>),
+    Ident(String),
+    Let(String, Box<Expr>, Box<Expr>),
+    /// `cond ? then : else`
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// `obj.field`
+    Field(Box<Expr>, String),
+    /// `obj?.field`, yielding `null` when `obj` is `null`.
+    OptionalField(Box<Expr>, String),
+    /// `obj.field ?? default`, tolerating objects without `field`.
+    FieldOr(Box<Expr>, String, Box<Expr>),
+    /// `expr as any`
+    AsDynamic(Box<Expr>),
+    /// `console.log(expr)`, consuming any value.
+    Log(Box<Expr>),
+}
+
+type Checker = TypeChecker<JsValue, JsUse, JsTypeSystem>;
+
+fn object_use(
+    checker: &mut Checker,
+    name: &str,
+    presence: Presence,
+    nullable: bool,
+) -> (Value, Use) {
+    let (field_value, field_use) = checker.var();
+    let fields = vec![(name.to_string(), (field_use, presence))]
+        .into_iter()
+        .collect();
+    let object = checker.new_use(JsUse::Object { fields, nullable });
+    (field_value, object)
+}
+
+fn check_expr(
+    checker: &mut Checker,
+    env: &mut HashMap<String, Value>,
+    expr: &Expr,
+) -> Result<Value, JsTypeError> {
+    match expr {
+        Expr::Null => Ok(checker.new_val(JsValue::Null)),
+        Expr::Bool => Ok(checker.new_val(JsValue::Bool)),
+        Expr::Number => Ok(checker.new_val(JsValue::Number)),
+        Expr::Str => Ok(checker.new_val(JsValue::Str)),
+        Expr::Object(fields) => {
+            let mut checked = BTreeMap::new();
+            for (name, field) in fields {
+                checked.insert(name.clone(), check_expr(checker, env, field)?);
+            }
+            Ok(checker.new_val(JsValue::Object(checked)))
+        }
+        Expr::Ident(name) => Ok(*env.get(name).expect("unbound identifier")),
+        Expr::Let(name, bound, body) => {
+            let bound = check_expr(checker, env, bound)?;
+            let shadowed = env.insert(name.clone(), bound);
+            let body = check_expr(checker, env, body);
+            match shadowed {
+                Some(prev) => env.insert(name.clone(), prev),
+                None => env.remove(name),
+            };
+            body
+        }
+        Expr::If(cond, then, otherwise) => {
+            let cond = check_expr(checker, env, cond)?;
+            let bool_use = checker.new_use(JsUse::Bool);
+            checker.flow(cond, bool_use)?;
+
+            let (merged_value, merged_use) = checker.var();
+            let then = check_expr(checker, env, then)?;
+            checker.flow(then, merged_use)?;
+            let otherwise = check_expr(checker, env, otherwise)?;
+            checker.flow(otherwise, merged_use)?;
+            Ok(merged_value)
+        }
+        Expr::Field(object, name) => {
+            let object = check_expr(checker, env, object)?;
+            let (field, object_use) = object_use(checker, name, Presence::Required, false);
+            checker.flow(object, object_use)?;
+            Ok(field)
+        }
+        Expr::OptionalField(object, name) => {
+            let object = check_expr(checker, env, object)?;
+            let (field, object_use) = object_use(checker, name, Presence::Required, true);
+            checker.flow(object, object_use)?;
+
+            let (result_value, result_use) = checker.var();
+            let null = checker.new_val(JsValue::Null);
+            checker.flow(field, result_use)?;
+            checker.flow(null, result_use)?;
+            Ok(result_value)
+        }
+        Expr::FieldOr(object, name, default) => {
+            let object = check_expr(checker, env, object)?;
+            let (field, object_use) = object_use(checker, name, Presence::Optional, false);
+            checker.flow(object, object_use)?;
+
+            let (result_value, result_use) = checker.var();
+            let default = check_expr(checker, env, default)?;
+            checker.flow(field, result_use)?;
+            checker.flow(default, result_use)?;
+            Ok(result_value)
+        }
+        Expr::AsDynamic(inner) => {
+            check_expr(checker, env, inner)?;
+            Ok(checker.new_val(JsValue::Dynamic))
+        }
+        Expr::Log(inner) => {
+            let inner = check_expr(checker, env, inner)?;
+            let any = checker.new_use(JsUse::Dynamic);
+            checker.flow(inner, any)?;
+            Ok(checker.new_val(JsValue::Null))
+        }
+    }
+}
+
+/// Type checks an expression, requiring its result to be usable as `expected`.
+fn check_program(expr: &Expr, expected: JsUse) -> Result<(), JsTypeError> {
+    let mut checker = TypeChecker::new(JsTypeSystem);
+    let result = check_expr(&mut checker, &mut HashMap::new(), expr)?;
+    let expected = checker.new_use(expected);
+    checker.flow(result, expected)
+}
+
+fn object(fields: Vec<(&str, Expr)>) -> Expr {
+    Expr::Object(
+        fields
+            .into_iter()
+            .map(|(name, expr)| (name.to_string(), expr))
+            .collect(),
+    )
+}
+
+fn ident(name: &str) -> Box<Expr> {
+    Box::new(Expr::Ident(name.to_string()))
+}
+
+fn main() {
+    // let user = { name: "ada", age: 36 };
+    // let maybe = true ? user : null;
+    // console.log(maybe?.name);
+    // user.nickname ?? user.name
+    let user = object(vec![("name", Expr::Str), ("age", Expr::Number)]);
+    let program = Expr::Let(
+        "user".to_string(),
+        Box::new(user),
+        Box::new(Expr::Let(
+            "maybe".to_string(),
+            Box::new(Expr::If(
+                Box::new(Expr::Bool),
+                ident("user"),
+                Box::new(Expr::Null),
+            )),
+            Box::new(Expr::Let(
+                "_".to_string(),
+                Box::new(Expr::Log(Box::new(Expr::OptionalField(
+                    ident("maybe"),
+                    "name".to_string(),
+                )))),
+                Box::new(Expr::FieldOr(
+                    ident("user"),
+                    "nickname".to_string(),
+                    Box::new(Expr::Field(ident("user"), "name".to_string())),
+                )),
+            )),
+        )),
+    );
+    println!("program checks: {:?}", check_program(&program, JsUse::Str));
+
+    // (true ? user : null).name
+    let unsafe_access = Expr::Field(
+        Box::new(Expr::If(
+            Box::new(Expr::Bool),
+            Box::new(object(vec![("name", Expr::Str)])),
+            Box::new(Expr::Null),
+        )),
+        "name".to_string(),
+    );
+    println!(
+        "unsafe access checks: {:?}",
+        check_program(&unsafe_access, JsUse::Str)
+    );
+
+    // (36 as any).age
+    let gradual = Expr::Field(
+        Box::new(Expr::AsDynamic(Box::new(Expr::Number))),
+        "age".to_string(),
+    );
+    println!(
+        "gradual access checks: {:?}",
+        check_program(&gradual, JsUse::Number)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ada() -> Expr {
+        object(vec![("name", Expr::Str), ("age", Expr::Number)])
+    }
+
+    fn nullable(expr: Expr) -> Expr {
+        Expr::If(Box::new(Expr::Bool), Box::new(expr), Box::new(Expr::Null))
+    }
+
+    #[test]
+    fn should_check_field_access_against_field_type() {
+        let access = Expr::Field(Box::new(ada()), "age".to_string());
+        assert!(check_program(&access, JsUse::Number).is_ok());
+        assert_eq!(
+            Err(JsTypeError::Mismatch {
+                expected: "string",
+                found: "number"
+            }),
+            check_program(&access, JsUse::Str)
+        );
+    }
+
+    #[test]
+    fn should_reject_missing_required_field() {
+        let access = Expr::Field(Box::new(ada()), "email".to_string());
+        assert_eq!(
+            Err(JsTypeError::MissingField("email".to_string())),
+            check_program(&access, JsUse::Dynamic)
+        );
+    }
+
+    #[test]
+    fn should_accept_missing_optional_field_with_default() {
+        let access = Expr::FieldOr(Box::new(ada()), "email".to_string(), Box::new(Expr::Str));
+        assert!(check_program(&access, JsUse::Str).is_ok());
+    }
+
+    #[test]
+    fn should_reject_field_access_on_nullable_flow() {
+        let access = Expr::Field(Box::new(nullable(ada())), "name".to_string());
+        assert_eq!(
+            Err(JsTypeError::NullDereference),
+            check_program(&access, JsUse::Str)
+        );
+    }
+
+    #[test]
+    fn should_propagate_null_through_optional_chaining() {
+        let access = Expr::OptionalField(Box::new(nullable(ada())), "name".to_string());
+        assert!(check_program(&access, JsUse::Dynamic).is_ok());
+        assert_eq!(
+            Err(JsTypeError::Mismatch {
+                expected: "string",
+                found: "null"
+            }),
+            check_program(&access, JsUse::Str)
+        );
+    }
+
+    #[test]
+    fn should_let_dynamic_satisfy_any_use() {
+        let access = Expr::Field(
+            Box::new(Expr::AsDynamic(Box::new(Expr::Number))),
+            "anything".to_string(),
+        );
+        assert!(check_program(&access, JsUse::Str).is_ok());
+
+        let bad = Expr::Field(Box::new(Expr::Number), "anything".to_string());
+        assert!(check_program(&bad, JsUse::Str).is_err());
+    }
+}
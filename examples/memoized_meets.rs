@@ -0,0 +1,97 @@
+//! Comparing plain and memoized meets on a synthetic program with a deep
+//! class hierarchy, where checking that one class is a subclass of another
+//! walks the hierarchy. Many values of the same leaf class flow into many
+//! uses of the root class, so the same pair of heads is met over and over.
+//!
+//! Run with `cargo run --release --example memoized_meets [values]`.
+
+use std::time::{Duration, Instant};
+use typical::{AbstractTypes, Memoized, TypeChecker, Use, Value};
+
+/// A class hierarchy, given as the parent of each class. Class 0 is the
+/// root.
+#[derive(Debug)]
+struct Classes {
+    parents: Vec<usize>,
+}
+
+impl Classes {
+    /// A single line of descent `depth` classes deep.
+    fn chain(depth: usize) -> Self {
+        Self {
+            parents: (0..depth).map(|class| class.saturating_sub(1)).collect(),
+        }
+    }
+}
+
+impl AbstractTypes<usize, usize> for Classes {
+    type Error = ();
+
+    fn meet(&self, lhs: &usize, rhs: &usize) -> Result<Vec<(Value, Use)>, Self::Error> {
+        let mut class = *lhs;
+        loop {
+            if class == *rhs {
+                return Ok(vec![]);
+            } else if class == 0 {
+                return Err(());
+            }
+            class = self.parents[class];
+        }
+    }
+}
+
+/// Flows `values` instances of the deepest class through a var into as
+/// many uses of the root class, returning the time taken.
+fn check<AT>(mut t: TypeChecker<usize, usize, AT>, values: usize, depth: usize) -> Duration
+where
+    AT: AbstractTypes<usize, usize>,
+    AT::Error: std::fmt::Debug,
+{
+    let start = Instant::now();
+    let (x_val, x_use) = t.var();
+    for _ in 0..values {
+        let value = t.new_val(depth - 1);
+        t.flow(value, x_use).unwrap();
+        let sink = t.new_use(0);
+        t.flow(x_val, sink).unwrap();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let values = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(300);
+    let depth = 10_000;
+
+    let plain = check(TypeChecker::new(Classes::chain(depth)), values, depth);
+    let memoized = check(
+        TypeChecker::new(Memoized::new(Classes::chain(depth))),
+        values,
+        depth,
+    );
+    println!(
+        "{} values, {} classes deep: plain {:?}, memoized {:?}",
+        values, depth, plain, memoized
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memoized_meets_should_agree_with_plain_ones() {
+        let mut t = TypeChecker::new(Memoized::new(Classes::chain(5)));
+        let leaf = t.new_val(4);
+        let (x_val, x_use) = t.var();
+        let sink = t.new_use(2);
+        assert_eq!(Ok(()), t.flow(leaf, x_use));
+        assert_eq!(Ok(()), t.flow(x_val, sink));
+
+        let root = t.new_val(0);
+        assert_eq!(Err(()), t.flow(root, x_use));
+        check(TypeChecker::new(Memoized::new(Classes::chain(5))), 10, 5);
+    }
+}
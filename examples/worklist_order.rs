@@ -0,0 +1,176 @@
+//! Comparing the worklist orders, with and without deduplicating queued
+//! work, on synthetic programs of three shapes: a wide one, where a single
+//! value fans out into many independent uses, a deep one, where a value is
+//! passed down a long chain of vars into a function whose result is passed
+//! back up another, and a shared one, where many copies of a function meet
+//! the same call and each queues the same flows. The graph only hands back
+//! pairs of nodes it newly connected, so on these shapes each pair of heads
+//! is met once in every configuration; what deduplication saves is linking
+//! flows queued more than once, which only the shared shape does.
+//!
+//! Closing over a chain takes time cubic in its length, so the deep chains
+//! are a quarter of the size.
+//!
+//! Run with `cargo run --release --example worklist_order [size]`.
+
+use std::time::{Duration, Instant};
+use typical::{AbstractTypes, GraphConfig, TypeChecker, Use, Value, WorklistOrder};
+
+#[derive(Debug, Clone)]
+enum Head {
+    Unit,
+    /// a function whose argument flows into its result.
+    Func {
+        arg: Use,
+        ret: Value,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Call {
+    Unit,
+    Call { arg: Value, ret: Use },
+}
+
+#[derive(Debug)]
+struct Funcs;
+
+impl AbstractTypes<Head, Call> for Funcs {
+    type Error = ();
+
+    fn meet(&self, lhs: &Head, rhs: &Call) -> Result<Vec<(Value, Use)>, Self::Error> {
+        match (lhs, rhs) {
+            (Head::Unit, Call::Unit) => Ok(vec![]),
+            (
+                Head::Func { arg, ret },
+                Call::Call {
+                    arg: given,
+                    ret: out,
+                },
+            ) => Ok(vec![(*given, *arg), (*ret, *out)]),
+            _ => Err(()),
+        }
+    }
+}
+
+fn checker(order: WorklistOrder, queue_duplicates: bool) -> TypeChecker<Head, Call, Funcs> {
+    let config = GraphConfig {
+        worklist_order: order,
+        queue_duplicates,
+        ..GraphConfig::default()
+    };
+    TypeChecker::new_with_config(Funcs, config)
+}
+
+/// Flows a single value into `size` uses through one var, returning the
+/// time taken and the number of meets.
+fn wide(mut t: TypeChecker<Head, Call, Funcs>, size: usize) -> (Duration, usize) {
+    let start = Instant::now();
+    let (x_val, x_use) = t.var();
+    for _ in 0..size {
+        let sink = t.new_use(Call::Unit);
+        t.flow(x_val, sink).unwrap();
+    }
+    let unit = t.new_val(Head::Unit);
+    t.flow(unit, x_use).unwrap();
+    (start.elapsed(), t.stats().meets)
+}
+
+/// Passes a value down a chain of `size` vars into the identity function,
+/// and its result back up a chain of the same length, returning the time
+/// taken and the number of meets.
+fn deep(mut t: TypeChecker<Head, Call, Funcs>, size: usize) -> (Duration, usize) {
+    let start = Instant::now();
+    let (x_val, x_use) = t.var();
+    let identity = t.new_val(Head::Func {
+        arg: x_use,
+        ret: x_val,
+    });
+
+    let unit = t.new_val(Head::Unit);
+    let (mut arg, first) = t.var();
+    t.flow(unit, first).unwrap();
+    for _ in 0..size {
+        let (next_val, next_use) = t.var();
+        t.flow(arg, next_use).unwrap();
+        arg = next_val;
+    }
+    let (mut ret, ret_use) = t.var();
+    for _ in 0..size {
+        let (next_val, next_use) = t.var();
+        t.flow(ret, next_use).unwrap();
+        ret = next_val;
+    }
+    let sink = t.new_use(Call::Unit);
+    t.flow(ret, sink).unwrap();
+
+    let call = t.new_use(Call::Call { arg, ret: ret_use });
+    t.flow(identity, call).unwrap();
+    (start.elapsed(), t.stats().meets)
+}
+
+/// Meets `size` copies of the identity function with one call through a
+/// var, returning the time taken and the number of meets. Each copy queues
+/// the same two flows into and out of the function.
+fn shared(mut t: TypeChecker<Head, Call, Funcs>, size: usize) -> (Duration, usize) {
+    let start = Instant::now();
+    let (arg_val, arg_use) = t.var();
+    let (f_val, f_use) = t.var();
+    for _ in 0..size {
+        let identity = t.new_val(Head::Func {
+            arg: arg_use,
+            ret: arg_val,
+        });
+        t.flow(identity, f_use).unwrap();
+    }
+    let unit = t.new_val(Head::Unit);
+    let sink = t.new_use(Call::Unit);
+    let call = t.new_use(Call::Call {
+        arg: unit,
+        ret: sink,
+    });
+    t.flow(f_val, call).unwrap();
+    (start.elapsed(), t.stats().meets)
+}
+
+fn main() {
+    let size = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(200);
+
+    for &order in &[WorklistOrder::Lifo, WorklistOrder::Fifo] {
+        for &(queue_duplicates, label) in &[(true, "duplicates"), (false, "deduplicated")] {
+            let (wide_time, wide_meets) = wide(checker(order, queue_duplicates), size);
+            let (deep_time, deep_meets) = deep(checker(order, queue_duplicates), size / 4);
+            let (shared_time, shared_meets) = shared(checker(order, queue_duplicates), size);
+            println!(
+                "{:?}, {}: wide {:?} ({} meets), deep {:?} ({} meets), shared {:?} ({} meets)",
+                order,
+                label,
+                wide_time,
+                wide_meets,
+                deep_time,
+                deep_meets,
+                shared_time,
+                shared_meets
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_configuration_should_meet_each_pair_once() {
+        for &order in &[WorklistOrder::Lifo, WorklistOrder::Fifo] {
+            for &queue_duplicates in &[true, false] {
+                assert_eq!(100, wide(checker(order, queue_duplicates), 100).1);
+                assert_eq!(2, deep(checker(order, queue_duplicates), 25).1);
+                assert_eq!(101, shared(checker(order, queue_duplicates), 100).1);
+            }
+        }
+    }
+}
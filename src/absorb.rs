@@ -0,0 +1,161 @@
+use crate::scheme::Instantiate;
+use crate::{
+    AbstractTypes, EntityId, FlowWarning, NodeIndex, RecordedFlow, Remap, TypeChecker, TypeNode,
+};
+use crate::{Use, Value, Violation};
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Instantiate<I>,
+    U: Instantiate<I>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Appends the nodes, edges and bookkeeping of a checker built on its
+    /// own, such as for a separately compiled module, returning where each
+    /// of its nodes now is. Every handle created by the other checker must
+    /// be passed through the returned Remap before it is used with this
+    /// one. The other checker's graph is already closed, so nothing is
+    /// re-checked: flows linking the two afterwards only close over the
+    /// edges they introduce.
+    ///
+    /// Names the other checker gave its vars with `var_named` are dropped
+    /// where this checker already uses them, though its vars keep their
+    /// display names. Its constraint groups and any work left over from a
+    /// cancelled check are dropped.
+    ///
+    /// Panics if either checker has a checkpoint or a binding level open.
+    pub fn absorb(&mut self, other: TypeChecker<V, U, AT, I>) -> Remap {
+        assert!(
+            self.levels.is_empty() && other.levels.is_empty(),
+            "can't absorb inside a level"
+        );
+        let base = self.types.len();
+        let remap = Remap {
+            ids: (0..other.types.len())
+                .map(|old| Some(EntityId::at(base + old)))
+                .collect(),
+        };
+        let id = |old: EntityId<I>| EntityId::at(base + usize::from(old));
+        let values = |value: Value<I>| Value(id(value.0));
+        let uses = |rhs: Use<I>| Use(id(rhs.0));
+        let remap_head = |node: TypeNode<V, U>| match node {
+            TypeNode::Var => TypeNode::Var,
+            TypeNode::Value(head) => TypeNode::Value(head.instantiate(&values, &uses)),
+            TypeNode::Use(head) => TypeNode::Use(head.instantiate(&values, &uses)),
+        };
+
+        self.r.append_mut(&other.r);
+        for _ in 0..other.types.len() {
+            self.aliases.make_set();
+        }
+        for old in (0..other.types.len()).map(EntityId::at) {
+            // every node is merged into its root once, so the root keeps
+            // the higher rank and stays the representative.
+            let root = other.aliases.root(old);
+            if root != old {
+                self.aliases.union(id(root), id(old));
+            }
+        }
+        self.types.extend(other.types.into_iter().map(remap_head));
+        for (old, heads) in other.extra_heads {
+            let heads = heads.into_iter().map(remap_head).collect();
+            self.extra_heads.insert(id(old), heads);
+        }
+        self.added_heads
+            .extend(other.added_heads.into_iter().map(id));
+        for (old, head) in other.unrefined {
+            self.unrefined
+                .insert(id(old), head.instantiate(&values, &uses));
+        }
+        self.meet_nodes.extend(other.meet_nodes.into_iter().map(id));
+
+        self.holes.extend(other.holes.into_iter().map(uses));
+        for (old, label) in other.labels.into_inner() {
+            self.labels.insert(id(old), label);
+        }
+        for (old, name) in other.var_names.into_inner() {
+            self.var_names.insert(id(old), name);
+        }
+        for (name, old) in other.named_vars.into_inner() {
+            self.named_vars.entry(name).or_insert_with(|| id(old));
+        }
+
+        self.flows
+            .extend(other.flows.iter().map(|flow| RecordedFlow {
+                lhs: values(flow.lhs),
+                rhs: uses(flow.rhs),
+                group: None,
+            }));
+        self.sync_recorded();
+        self.direct
+            .extend(other.direct.iter().map(|&(lhs, rhs)| (id(lhs), id(rhs))));
+        self.forbidden.extend(
+            other
+                .forbidden
+                .into_iter()
+                .map(|(lhs, rhs)| (values(lhs), uses(rhs))),
+        );
+        self.violations
+            .extend(other.violations.into_iter().map(|violation| Violation {
+                lhs: values(violation.lhs),
+                rhs: uses(violation.rhs),
+                path: violation.path.into_iter().map(id).collect(),
+            }));
+        // no checkpoint is open, so where a guarded flow fired no longer
+        // matters once the flows are renumbered.
+        self.guarded
+            .extend(other.guarded.into_iter().map(|mut guarded| {
+                guarded.guard = values(guarded.guard);
+                guarded.lhs = values(guarded.lhs);
+                guarded.rhs = uses(guarded.rhs);
+                guarded.fired = guarded.fired.map(|_| 0);
+                guarded
+            }));
+        self.warnings
+            .extend(other.warnings.into_iter().map(|warning| match warning {
+                FlowWarning::HighOutDegree { entity, degree } => FlowWarning::HighOutDegree {
+                    entity: id(entity),
+                    degree,
+                },
+            }));
+        self.meets += other.meets;
+        remap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Head, HeadTypeSystem};
+    use crate::*;
+
+    #[test]
+    fn absorbed_modules_should_link_through_remapped_handles() {
+        let mut main = TypeChecker::new(HeadTypeSystem);
+        let (arg, arg_use) = main.var_named("arg");
+        let int_use = main.new_use(Head::Int);
+        assert!(main.flow(arg, int_use).is_ok());
+
+        let mut lib = TypeChecker::new(HeadTypeSystem);
+        let int = lib.new_val(Head::Int);
+        let (elem, elem_use) = lib.var_named("elem");
+        assert!(lib.flow(int, elem_use).is_ok());
+        let list = lib.new_val(Head::List(elem));
+
+        let remap = main.absorb(lib);
+        let (int, elem, list) = (
+            remap.map_value(int).unwrap(),
+            remap.map_value(elem).unwrap(),
+            remap.map_value(list).unwrap(),
+        );
+        assert!(main.reaches(int, Use(elem.0)));
+        assert_eq!(Some("elem"), main.name_of(elem));
+        assert!(main.validate_heads().is_empty());
+
+        // linking the modules only closes over the new edge.
+        let sink = main.new_use(Head::List(Value(arg_use.0)));
+        assert!(main.flow(list, sink).is_ok());
+        assert!(main.reaches(int, int_use));
+        assert_eq!(6, main.types.len());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{AbstractTypes, Flow, NodeIndex, TypeChecker, Use, Value};
+
+/// AnnotationError reports which direction of an annotation failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationError<E> {
+    /// The expression's value doesn't satisfy the annotation.
+    ExprIntoAnnotation(E),
+    /// The annotation's value isn't accepted where the expression is used.
+    AnnotationIntoExpr(E),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for AnnotationError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ExprIntoAnnotation(e) => write!(f, "expression doesn't match annotation: {}", e),
+            Self::AnnotationIntoExpr(e) => write!(f, "annotation doesn't match expression: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error> std::error::Error for AnnotationError<E> {}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Relates an expression to a type annotation in both directions: the
+    /// expression's value flows into the annotation's use, and the
+    /// annotation's value flows into the expression's use. Returns the nodes
+    /// created for the annotation.
+    pub fn annotate(
+        &mut self,
+        expr: (Value<I>, Use<I>),
+        ann_value: V,
+        ann_use: U,
+    ) -> Result<Flow<I>, AnnotationError<AT::Error>> {
+        let (value, rhs) = expr;
+        let (ann_value, ann_use) = (self.new_val(ann_value), self.new_use(ann_use));
+
+        self.flow(value, ann_use)
+            .map_err(AnnotationError::ExprIntoAnnotation)?;
+        self.flow(ann_value, rhs)
+            .map_err(AnnotationError::AnnotationIntoExpr)?;
+        Ok((ann_value, ann_use))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn annotate_should_report_the_failing_direction() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        assert!(t.annotate((x_val, x_use), Prim::Int, Prim::Int).is_ok());
+
+        let literal = t.new_val(Prim::Bool);
+        let (y_val, y_use) = t.var();
+        assert!(t.flow(literal, y_use).is_ok());
+        assert_eq!(
+            Err(AnnotationError::ExprIntoAnnotation(TypeError::Converge)),
+            t.annotate((y_val, y_use), Prim::Int, Prim::Int)
+        );
+
+        let (z_val, z_use) = t.var();
+        let sink = t.new_use(Prim::Bool);
+        assert!(t.flow(z_val, sink).is_ok());
+        assert_eq!(
+            Err(AnnotationError::AnnotationIntoExpr(TypeError::Converge)),
+            t.annotate((z_val, z_use), Prim::Int, Prim::Int)
+        );
+    }
+}
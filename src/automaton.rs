@@ -0,0 +1,387 @@
+//! Simplification of described types. A TypeDescription is read as the
+//! automaton it denotes, with each recursive type becoming a cycle, which is
+//! then minimized by partition refinement and read back out. Types that
+//! unroll to the same infinite tree therefore simplify to the same
+//! description.
+
+use crate::collections::{HashMap, HashSet, Key};
+use crate::describe::{nth_name, Polarity, TypeDescription};
+use crate::prelude::*;
+
+/// State is a single node of the automaton, referring to its successors by
+/// index.
+#[derive(Debug, Clone)]
+enum State {
+    Var(String),
+    Head(String, Vec<Polarity>, Vec<usize>),
+    Union(Vec<usize>),
+    Intersection(Vec<usize>),
+}
+
+impl State {
+    fn successors(&self) -> &[usize] {
+        match self {
+            State::Var(_) => &[],
+            State::Head(_, _, next) | State::Union(next) | State::Intersection(next) => next,
+        }
+    }
+}
+
+/// Numbers each key in order of first appearance, returning the number given
+/// to every key along with the count of distinct keys.
+fn number<K: Key>(keys: impl Iterator<Item = K>) -> (Vec<usize>, usize) {
+    let mut seen = HashMap::new();
+    let numbers = keys
+        .map(|key| {
+            let next = seen.len();
+            *seen.entry(key).or_insert(next)
+        })
+        .collect();
+    (numbers, seen.len())
+}
+
+/// Collects the members of a union or intersection, splicing in the members
+/// of any directly nested one of the same kind.
+fn flatten<'d>(
+    members: &'d [TypeDescription],
+    nested: fn(&'d TypeDescription) -> Option<&'d [TypeDescription]>,
+    out: &mut Vec<&'d TypeDescription>,
+) {
+    for member in members {
+        match nested(member) {
+            Some(inner) => flatten(inner, nested, out),
+            None => out.push(member),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Automaton {
+    states: Vec<State>,
+}
+
+impl Automaton {
+    fn push(&mut self, state: State) -> usize {
+        self.states.push(state);
+        self.states.len() - 1
+    }
+
+    fn build(&mut self, ty: &TypeDescription, bound: &mut Vec<(String, usize)>) -> usize {
+        match ty {
+            TypeDescription::Var(name) => match bound.iter().rev().find(|(n, _)| n == name) {
+                Some(&(_, state)) => state,
+                None => self.push(State::Var(name.clone())),
+            },
+            TypeDescription::Head { label, children } => {
+                let polarities = children.iter().map(|&(p, _)| p).collect();
+                let next = children.iter().map(|(_, c)| self.build(c, bound)).collect();
+                self.push(State::Head(label.clone(), polarities, next))
+            }
+            TypeDescription::Union(members) => {
+                let mut flat = Vec::new();
+                flatten(
+                    members,
+                    |ty| match ty {
+                        TypeDescription::Union(inner) => Some(inner),
+                        _ => None,
+                    },
+                    &mut flat,
+                );
+                let next = flat.into_iter().map(|m| self.build(m, bound)).collect();
+                self.push(State::Union(next))
+            }
+            TypeDescription::Intersection(members) => {
+                let mut flat = Vec::new();
+                flatten(
+                    members,
+                    |ty| match ty {
+                        TypeDescription::Intersection(inner) => Some(inner),
+                        _ => None,
+                    },
+                    &mut flat,
+                );
+                let next = flat.into_iter().map(|m| self.build(m, bound)).collect();
+                self.push(State::Intersection(next))
+            }
+            TypeDescription::Recursive { name, body } => {
+                // reserve the state so the body can refer back to it, then
+                // make it a copy of whatever the body built.
+                let state = self.push(State::Union(Vec::new()));
+                bound.push((name.clone(), state));
+                let body = self.build(body, bound);
+                bound.pop();
+                if body != state {
+                    self.states[state] = self.states[body].clone();
+                }
+                state
+            }
+        }
+    }
+
+    /// Partitions the states into classes of equivalent states, starting
+    /// from their shapes and splitting classes until every state in a class
+    /// has successors in the same classes.
+    fn minimize(&self) -> Vec<usize> {
+        let (mut classes, mut count) = number(self.states.iter().map(|state| match state {
+            State::Var(name) => (0, name.as_str(), &[][..]),
+            State::Head(label, polarities, _) => (1, label.as_str(), &polarities[..]),
+            State::Union(_) => (2, "", &[][..]),
+            State::Intersection(_) => (3, "", &[][..]),
+        }));
+
+        loop {
+            let signatures = self.states.iter().enumerate().map(|(idx, state)| {
+                let mut next: Vec<usize> = state.successors().iter().map(|&s| classes[s]).collect();
+                if !matches!(state, State::Head(..)) {
+                    next.sort_unstable();
+                    next.dedup();
+                }
+                (classes[idx], next)
+            });
+
+            let (refined, refined_count) = number(signatures);
+            if refined_count == count {
+                return refined;
+            }
+            classes = refined;
+            count = refined_count;
+        }
+    }
+}
+
+/// Reader turns the minimized automaton back into a description, folding
+/// every cycle into a recursive type.
+struct Reader<'a> {
+    automaton: &'a Automaton,
+    classes: Vec<usize>,
+    representatives: HashMap<usize, usize>,
+    stack: Vec<usize>,
+    recursive: HashMap<usize, String>,
+    binders: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(automaton: &'a Automaton) -> Self {
+        let classes = automaton.minimize();
+        let mut representatives = HashMap::new();
+        for (state, &class) in classes.iter().enumerate() {
+            representatives.entry(class).or_insert(state);
+        }
+
+        Self {
+            automaton,
+            classes,
+            representatives,
+            stack: Vec::new(),
+            recursive: HashMap::new(),
+            binders: 0,
+        }
+    }
+
+    fn read_members(&mut self, next: &[usize]) -> Vec<TypeDescription> {
+        let mut seen = Vec::new();
+        for &state in next {
+            if !seen.contains(&self.classes[state]) {
+                seen.push(self.classes[state]);
+            }
+        }
+        seen.into_iter().map(|class| self.read(class)).collect()
+    }
+
+    fn read(&mut self, class: usize) -> TypeDescription {
+        if self.stack.contains(&class) {
+            // binders are renamed once the whole type is read, so any
+            // placeholder that can't clash with a var name will do.
+            if !self.recursive.contains_key(&class) {
+                self.recursive.insert(class, format!("#{}", self.binders));
+                self.binders += 1;
+            }
+            return TypeDescription::Var(self.recursive[&class].clone());
+        }
+
+        let automaton = self.automaton;
+        self.stack.push(class);
+        let description = match &automaton.states[self.representatives[&class]] {
+            State::Var(name) => TypeDescription::Var(name.clone()),
+            State::Head(label, polarities, next) => TypeDescription::Head {
+                label: label.clone(),
+                children: polarities
+                    .iter()
+                    .zip(next)
+                    .map(|(&polarity, &state)| (polarity, self.read(self.classes[state])))
+                    .collect(),
+            },
+            State::Union(next) => {
+                let mut members = self.read_members(next);
+                match members.len() {
+                    1 => members.pop().unwrap(),
+                    _ => TypeDescription::Union(members),
+                }
+            }
+            State::Intersection(next) => {
+                let mut members = self.read_members(next);
+                match members.len() {
+                    1 => members.pop().unwrap(),
+                    _ => TypeDescription::Intersection(members),
+                }
+            }
+        };
+        self.stack.pop();
+
+        match self.recursive.remove(&class) {
+            Some(name) => TypeDescription::Recursive {
+                name,
+                body: Box::new(description),
+            },
+            None => description,
+        }
+    }
+}
+
+/// Collects the names of every var that isn't a placeholder for a binder.
+fn free_vars(ty: &TypeDescription, names: &mut HashSet<String>) {
+    match ty {
+        TypeDescription::Var(name) if !name.starts_with('#') => {
+            names.insert(name.clone());
+        }
+        TypeDescription::Var(_) => (),
+        TypeDescription::Head { children, .. } => {
+            children.iter().for_each(|(_, c)| free_vars(c, names))
+        }
+        TypeDescription::Union(members) | TypeDescription::Intersection(members) => {
+            members.iter().for_each(|m| free_vars(m, names))
+        }
+        TypeDescription::Recursive { body, .. } => free_vars(body, names),
+    }
+}
+
+/// Binders names each binder placeholder in order of appearance, skipping
+/// any name already taken by a free var.
+struct Binders {
+    taken: HashSet<String>,
+    names: HashMap<String, String>,
+    next: usize,
+}
+
+impl Binders {
+    fn name(&mut self, name: &str) -> String {
+        if !name.starts_with('#') {
+            return name.to_string();
+        }
+        if let Some(bound) = self.names.get(name) {
+            return bound.clone();
+        }
+
+        let mut bound = nth_name(self.next);
+        while self.taken.contains(&bound) {
+            self.next += 1;
+            bound = nth_name(self.next);
+        }
+        self.next += 1;
+        self.names.insert(name.to_string(), bound.clone());
+        bound
+    }
+
+    fn rename(&mut self, ty: &TypeDescription) -> TypeDescription {
+        match ty {
+            TypeDescription::Var(name) => TypeDescription::Var(self.name(name)),
+            TypeDescription::Head { label, children } => TypeDescription::Head {
+                label: label.clone(),
+                children: children
+                    .iter()
+                    .map(|(polarity, c)| (*polarity, self.rename(c)))
+                    .collect(),
+            },
+            TypeDescription::Union(members) => {
+                TypeDescription::Union(members.iter().map(|m| self.rename(m)).collect())
+            }
+            TypeDescription::Intersection(members) => {
+                TypeDescription::Intersection(members.iter().map(|m| self.rename(m)).collect())
+            }
+            TypeDescription::Recursive { name, body } => TypeDescription::Recursive {
+                name: self.name(name),
+                body: Box::new(self.rename(body)),
+            },
+        }
+    }
+}
+
+impl TypeDescription {
+    /// Returns the smallest description of the same type. Nested unions and
+    /// intersections are flattened, duplicate members are dropped,
+    /// and recursive types are folded at their shortest period, so equal
+    /// types simplify to equal descriptions up to the names of their vars.
+    /// Vars keep their names, while binders are renamed in order of
+    /// appearance.
+    pub fn simplify(&self) -> TypeDescription {
+        let mut automaton = Automaton::default();
+        let root = automaton.build(self, &mut Vec::new());
+
+        let mut reader = Reader::new(&automaton);
+        let class = reader.classes[root];
+        let simplified = reader.read(class);
+        let mut taken = HashSet::new();
+        free_vars(&simplified, &mut taken);
+        Binders {
+            taken,
+            names: HashMap::new(),
+            next: 0,
+        }
+        .rename(&simplified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> TypeDescription {
+        TypeDescription::Var(name.to_string())
+    }
+
+    fn head(label: &str, children: Vec<TypeDescription>) -> TypeDescription {
+        TypeDescription::Head {
+            label: label.to_string(),
+            children: children
+                .into_iter()
+                .map(|child| (Polarity::Positive, child))
+                .collect(),
+        }
+    }
+
+    fn rec(name: &str, body: TypeDescription) -> TypeDescription {
+        TypeDescription::Recursive {
+            name: name.to_string(),
+            body: Box::new(body),
+        }
+    }
+
+    #[test]
+    fn simplify_should_flatten_and_deduplicate_joins() {
+        let ty = TypeDescription::Union(vec![
+            head("int", vec![]),
+            TypeDescription::Union(vec![head("bool", vec![]), head("int", vec![])]),
+            var("q"),
+        ]);
+        assert_eq!("int | bool | q", ty.simplify().to_string());
+
+        let ty = head("fn", vec![var("x"), TypeDescription::Union(vec![var("x")])]);
+        assert_eq!("fn(x, x)", ty.simplify().to_string());
+    }
+
+    #[test]
+    fn simplify_should_fold_recursion_at_its_shortest_period() {
+        let unrolled = head("list", vec![rec("b", head("list", vec![var("b")]))]);
+        let folded = rec("a", head("list", vec![var("a")]));
+        assert_eq!(folded, unrolled.simplify());
+
+        let doubled = rec("z", head("list", vec![head("list", vec![var("z")])]));
+        assert_eq!(folded, doubled.simplify());
+
+        // a binder that is never referred to is dropped.
+        assert_eq!(
+            head("int", vec![]),
+            rec("a", head("int", vec![])).simplify()
+        );
+    }
+}
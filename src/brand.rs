@@ -0,0 +1,148 @@
+use crate::{AbstractTypes, TypeChecker, Use, Value};
+use core::marker::PhantomData;
+
+/// Id is an invariant lifetime unique to a single call to `branded`, so
+/// that no two checkers ever share one.
+type Id<'id> = PhantomData<fn(&'id ()) -> &'id ()>;
+
+/// Branded wraps a handle with the unique brand of the checker that created
+/// it, so that handles can't be passed to any other checker, even one over
+/// the same type system.
+///
+/// ```compile_fail
+/// # use typical::*;
+/// # #[derive(Debug)] struct A;
+/// # impl AbstractTypes<(), ()> for A {
+/// #     type Error = TypeError;
+/// #     fn meet(&self, _: &(), _: &()) -> Result<Vec<(Value, Use)>, TypeError> { Ok(vec![]) }
+/// # }
+/// branded(A, |mut a| {
+///     branded(A, |mut b| {
+///         let value = a.new_val(());
+///         let sink = b.new_use(());
+///         b.flow(value, sink)
+///     })
+/// });
+/// ```
+pub struct Branded<'id, H> {
+    handle: H,
+    id: Id<'id>,
+}
+
+impl<'id, H> Branded<'id, H> {
+    fn new(handle: H) -> Self {
+        Self {
+            handle,
+            id: PhantomData,
+        }
+    }
+
+    /// Discards the brand, returning the underlying handle.
+    pub fn into_inner(self) -> H {
+        self.handle
+    }
+}
+
+impl<'id, H: Copy> Copy for Branded<'id, H> {}
+
+impl<'id, H: Copy> Clone for Branded<'id, H> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'id, H: PartialEq> PartialEq for Branded<'id, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl<'id, H: Eq> Eq for Branded<'id, H> {}
+
+impl<'id, H: core::hash::Hash> core::hash::Hash for Branded<'id, H> {
+    fn hash<S: core::hash::Hasher>(&self, state: &mut S) {
+        self.handle.hash(state)
+    }
+}
+
+impl<'id, H: core::fmt::Debug> core::fmt::Debug for Branded<'id, H> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Branded({:?})", self.handle)
+    }
+}
+
+/// BrandedChecker wraps a TypeChecker, handing out handles branded with a
+/// lifetime unique to it. It can only be created through `branded`.
+#[derive(Debug)]
+pub struct BrandedChecker<'id, V, U, AT>
+where
+    AT: AbstractTypes<V, U>,
+{
+    checker: TypeChecker<V, U, AT>,
+    id: Id<'id>,
+}
+
+/// Creates a checker whose handles can't be used with any other checker,
+/// and passes it to `f`. The checker and its handles can't escape `f`.
+pub fn branded<V, U, AT, R>(
+    abstract_type_mapper: AT,
+    f: impl for<'id> FnOnce(BrandedChecker<'id, V, U, AT>) -> R,
+) -> R
+where
+    AT: AbstractTypes<V, U>,
+{
+    f(BrandedChecker {
+        checker: TypeChecker::new(abstract_type_mapper),
+        id: PhantomData,
+    })
+}
+
+impl<'id, V, U, AT> BrandedChecker<'id, V, U, AT>
+where
+    AT: AbstractTypes<V, U>,
+{
+    /// Returns the wrapped checker.
+    pub fn checker(&self) -> &TypeChecker<V, U, AT> {
+        &self.checker
+    }
+
+    pub fn new_val(&mut self, val_type: V) -> Branded<'id, Value> {
+        Branded::new(self.checker.new_val(val_type))
+    }
+
+    pub fn new_use(&mut self, constraint: U) -> Branded<'id, Use> {
+        Branded::new(self.checker.new_use(constraint))
+    }
+
+    pub fn var(&mut self) -> (Branded<'id, Value>, Branded<'id, Use>) {
+        let (value, var_use) = self.checker.var();
+        (Branded::new(value), Branded::new(var_use))
+    }
+
+    pub fn flow(
+        &mut self,
+        lhs: Branded<'id, Value>,
+        rhs: Branded<'id, Use>,
+    ) -> Result<(), AT::Error> {
+        self.checker.flow(lhs.handle, rhs.handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::Strict;
+    use crate::TypeError;
+
+    #[test]
+    fn branded_handles_should_flow_within_their_checker() {
+        let result = branded(Strict, |mut t| {
+            let (var_val, var_use) = t.var();
+            let truthy = t.new_val(true);
+            let falsy = t.new_use(false);
+            assert!(t.flow(truthy, var_use).is_ok());
+            t.flow(var_val, falsy)
+        });
+        assert_eq!(Err(TypeError::Converge), result);
+    }
+}
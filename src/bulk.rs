@@ -0,0 +1,84 @@
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, GraphConfig, NodeIndex, TypeChecker, TypeNode, Use, Value};
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Creates a checker with room for `nodes` nodes before it needs to
+    /// reallocate.
+    pub fn with_capacity(abstract_type_mapper: AT, nodes: usize) -> Self {
+        let mut checker = Self::new_with_index(abstract_type_mapper, GraphConfig::default());
+        checker.reserve(nodes);
+        checker
+    }
+
+    /// Reserves room for at least `additional` more nodes in the node table,
+    /// the graph and the union-find alike.
+    pub fn reserve(&mut self, additional: usize) {
+        self.types.reserve(additional);
+        self.r.reserve(additional);
+        self.aliases.reserve(additional);
+    }
+
+    /// Creates a value for each head, reserving room for all of them up
+    /// front. The handles are returned in order and are numbered
+    /// contiguously.
+    pub fn new_vals(&mut self, val_types: impl IntoIterator<Item = V>) -> Vec<Value<I>> {
+        self.add_nodes(val_types.into_iter().map(TypeNode::Value))
+            .into_iter()
+            .map(Value)
+            .collect()
+    }
+
+    /// Creates a use for each head, reserving room for all of them up
+    /// front. The handles are returned in order and are numbered
+    /// contiguously.
+    pub fn new_uses(&mut self, constraints: impl IntoIterator<Item = U>) -> Vec<Use<I>> {
+        self.add_nodes(constraints.into_iter().map(TypeNode::Use))
+            .into_iter()
+            .map(Use)
+            .collect()
+    }
+
+    /// Creates `count` vars, reserving room for all of them up front. The
+    /// handles are returned in order and are numbered contiguously.
+    pub fn vars(&mut self, count: usize) -> Vec<(Value<I>, Use<I>)> {
+        self.add_nodes((0..count).map(|_| TypeNode::Var))
+            .into_iter()
+            .map(|i| (Value(i), Use(i)))
+            .collect()
+    }
+
+    fn add_nodes(&mut self, nodes: impl Iterator<Item = TypeNode<V, U>>) -> Vec<EntityId<I>> {
+        self.reserve(nodes.size_hint().0);
+        nodes.map(|node| self.add_node(node)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::Permissive;
+    use crate::*;
+
+    #[test]
+    fn bulk_nodes_should_be_numbered_contiguously() {
+        let mut t = TypeChecker::with_capacity(Permissive, 8);
+        assert!(t.types.capacity() >= 8);
+        let values = t.new_vals(vec![(); 3]);
+        let vars = t.vars(2);
+        let uses = t.new_uses(vec![(); 3]);
+
+        let ids: Vec<usize> = values
+            .iter()
+            .map(|value| value.index())
+            .chain(vars.iter().map(|(value, _)| value.index()))
+            .chain(uses.iter().map(|rhs| rhs.index()))
+            .collect();
+        assert_eq!((0..8).collect::<Vec<_>>(), ids);
+        assert!(t.flow(vars[0].0, uses[0]).is_ok());
+        assert!(t.flow(values[2], vars[0].1).is_ok());
+        assert!(t.reaches(values[2], uses[0]));
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{AbstractTypes, NodeIndex, TypeChecker, Use, Value};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// CancellationToken lets a host abandon a check from elsewhere, such as an
+/// editor discarding a check made stale by further typing.
+#[derive(Debug, Default)]
+pub struct CancellationToken(AtomicBool);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests that any check polling this token stop at its next step.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+
+    /// Returns true if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Budget bounds a single check by the number of steps of the worklist it
+/// may take, where each step adds one edge or checks one pair of heads, and
+/// by a flag that can be set from another thread to stop it early.
+#[derive(Debug, Default)]
+pub struct Budget {
+    pub max_steps: Option<usize>,
+    token: CancellationToken,
+}
+
+impl Budget {
+    pub fn new(max_steps: Option<usize>) -> Self {
+        Self {
+            max_steps,
+            token: CancellationToken::new(),
+        }
+    }
+
+    /// Requests that any check under this budget stop at its next step.
+    pub fn cancel(&self) {
+        self.token.cancel()
+    }
+
+    /// Returns true if the budget has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+/// Cancellable is the error of a check that can be cancelled, which either
+/// failed with a type error or was stopped before it finished.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cancellable<E> {
+    Cancelled,
+    /// The check used up the steps of its budget.
+    Interrupted,
+    Failed(E),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for Cancellable<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "check was cancelled."),
+            Self::Interrupted => write!(f, "check ran out of steps."),
+            Self::Failed(e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error> std::error::Error for Cancellable<E> {}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Adds every flow in turn, stopping at the first that fails.
+    pub fn flow_many(
+        &mut self,
+        pairs: impl IntoIterator<Item = (Value<I>, Use<I>)>,
+    ) -> Result<(), AT::Error> {
+        pairs
+            .into_iter()
+            .try_for_each(|(lhs, rhs)| self.flow(lhs, rhs))
+    }
+
+    /// Adds every flow, polling the token between steps of the worklist. If
+    /// the token is cancelled the remaining work is kept, leaving the
+    /// checker consistent: the work is finished by `resume` or by the next
+    /// flow added. The flows of a check that fails aren't recorded, as with
+    /// `flow`.
+    pub fn flow_cancellable(
+        &mut self,
+        pairs: impl IntoIterator<Item = (Value<I>, Use<I>)>,
+        token: &CancellationToken,
+    ) -> Result<(), Cancellable<AT::Error>> {
+        let recorded = self.enqueue_all(pairs);
+        let result = self.solve(Some(token), None);
+        if matches!(result, Err(Cancellable::Failed(_))) {
+            self.forget_flows(&recorded);
+        }
+        result
+    }
+
+    /// Continues a cancelled check with a new token.
+    pub fn resume(&mut self, token: &CancellationToken) -> Result<(), Cancellable<AT::Error>> {
+        self.solve(Some(token), None)
+    }
+
+    /// Adds a flow like `flow`, but stops once the budget's steps run out or
+    /// it is cancelled. A stopped check keeps its remaining work, leaving
+    /// the checker consistent: the work is finished by
+    /// `resume_with_budget` or by the next flow added. A flow whose check
+    /// fails isn't recorded, as with `flow`.
+    pub fn flow_with_budget(
+        &mut self,
+        lhs: Value<I>,
+        rhs: Use<I>,
+        budget: &Budget,
+    ) -> Result<(), Cancellable<AT::Error>> {
+        let idx = self.flows.len();
+        let recorded = self.enqueue(lhs, rhs);
+        let result = self.solve(Some(&budget.token), budget.max_steps);
+        if recorded && matches!(result, Err(Cancellable::Failed(_))) {
+            self.forget_flow(idx);
+        }
+        result
+    }
+
+    /// Continues a stopped check under a new budget.
+    pub fn resume_with_budget(&mut self, budget: &Budget) -> Result<(), Cancellable<AT::Error>> {
+        self.solve(Some(&budget.token), budget.max_steps)
+    }
+
+    /// Returns true if no work is left over from a cancelled check.
+    pub fn is_settled(&self) -> bool {
+        self.worklist.edges.is_empty() && self.worklist.pairs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn cancelled_checks_should_resume_where_they_stopped() {
+        let mut t = TypeChecker::new(Strict);
+        let (var_val, var_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        let boolean = t.new_use(Prim::Bool);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        assert_eq!(
+            Err(Cancellable::Cancelled),
+            t.flow_cancellable(vec![(int, var_use), (var_val, boolean)], &token)
+        );
+        assert!(!t.is_settled());
+
+        assert_eq!(
+            Err(Cancellable::Failed(TypeError::Converge)),
+            t.resume(&CancellationToken::new())
+        );
+        assert!(t.is_settled());
+    }
+
+    #[test]
+    fn budgeted_flows_should_stop_when_their_steps_run_out() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let (x_val, x_use) = t.var();
+        let sink = t.new_use(Prim::Int);
+        assert!(t.flow(x_val, sink).is_ok());
+
+        let budget = Budget::new(Some(1));
+        assert_eq!(
+            Err(Cancellable::Interrupted),
+            t.flow_with_budget(int, x_use, &budget)
+        );
+        assert!(!t.is_settled());
+        while t.resume_with_budget(&budget) == Err(Cancellable::Interrupted) {}
+        assert!(t.reaches(int, sink));
+
+        let budget = Budget::new(None);
+        budget.cancel();
+        assert_eq!(
+            Err(Cancellable::Cancelled),
+            t.flow_with_budget(int, sink, &budget)
+        );
+        let boolean = t.new_use(Prim::Bool);
+        assert_eq!(
+            Err(Cancellable::Failed(TypeError::Converge)),
+            t.flow_with_budget(x_val, boolean, &Budget::new(Some(100)))
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use crate::collections::Shared;
+use crate::prelude::*;
+use crate::{
+    graph, union_find, AbstractTypes, EntityId, NodeIndex, RecordedFlow, TypeChecker, TypeNode,
+    Use, Value, Violation, Worklist,
+};
+
+/// Checkpoint marks a point in time that a TypeChecker can be restored to,
+/// for trying out flows speculatively. Checkpoints must be restored or
+/// committed in the reverse of the order they were saved in.
+#[derive(Debug)]
+pub struct Checkpoint<I = u32> {
+    graph: graph::Snapshot,
+    aliases: union_find::Snapshot,
+    nodes: usize,
+    holes: usize,
+    flows: usize,
+    direct: usize,
+    forbidden: usize,
+    violations: usize,
+    guarded: usize,
+    warnings: usize,
+    overwritten: usize,
+    heads: usize,
+    meets: usize,
+    failed: Option<(EntityId<I>, EntityId<I>)>,
+    tags: usize,
+    open_group: Option<usize>,
+    levels: Vec<usize>,
+}
+
+/// Overwritten is a change to a node's head made in place while a checkpoint
+/// was open, holding what restoring the checkpoint needs to undo it.
+#[derive(Debug, Clone)]
+pub(crate) enum Overwritten<V, U, I> {
+    /// the node was replaced, and this is what it was.
+    Node(EntityId<I>, TypeNode<V, U>),
+    /// the value was refined for the first time, and the head it had is kept
+    /// in `unrefined`.
+    Refined(EntityId<I>),
+    /// the value's original head was put back, and this is the refined head
+    /// it replaced.
+    Unrefined(EntityId<I>, V),
+    /// the value's original head was forgotten, and this is what it was.
+    Forgotten(EntityId<I>, V),
+    /// recorded flows were dropped from these positions, in ascending order,
+    /// leaving `len` flows.
+    Flows {
+        removed: Vec<(usize, RecordedFlow<I>)>,
+        len: usize,
+    },
+    /// a region was detached for rechecking, and these are the direct edges
+    /// and violations it had before.
+    Detached {
+        direct: Shared<Vec<(EntityId<I>, EntityId<I>)>>,
+        violations: Vec<Violation<I>>,
+    },
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Saves the state of the checker so that everything added after it can
+    /// be undone by `restore`.
+    pub fn save(&mut self) -> Checkpoint<I> {
+        Checkpoint {
+            graph: self.r.snapshot(),
+            aliases: self.aliases.snapshot(),
+            nodes: self.types.len(),
+            holes: self.holes.len(),
+            flows: self.flows.len(),
+            direct: self.direct.len(),
+            forbidden: self.forbidden.len(),
+            violations: self.violations.len(),
+            guarded: self.guarded.len(),
+            warnings: self.warnings.len(),
+            overwritten: self.overwritten.len(),
+            heads: self.added_heads.len(),
+            meets: self.meets,
+            failed: self.failed,
+            tags: self.groups.tags.len(),
+            open_group: self.groups.open,
+            levels: self.levels.clone(),
+        }
+    }
+
+    /// Rolls the checker back to a checkpoint, removing every node, head and
+    /// edge added since it was saved, including the edges derived from them.
+    /// Handles created since the checkpoint shouldn't be used again. Heads
+    /// replaced in place since, by refinement or `replace_value_head`, are
+    /// put back, as are flows retracted since. The count of meets, the last
+    /// failure, the constraint groups and the open binding levels are rolled
+    /// back with them.
+    pub fn restore(&mut self, checkpoint: Checkpoint<I>) {
+        self.r.rollback_to(checkpoint.graph);
+        self.aliases.rollback_to(checkpoint.aliases);
+
+        let nodes = checkpoint.nodes;
+        while self.overwritten.len() > checkpoint.overwritten {
+            match self.overwritten.pop().unwrap() {
+                Overwritten::Node(id, node) => self.types[usize::from(id)] = node,
+                Overwritten::Refined(id) => {
+                    if let Some(original) = self.unrefined.remove(&id) {
+                        self.types[usize::from(id)] = TypeNode::Value(original);
+                    }
+                }
+                Overwritten::Unrefined(id, refined) => {
+                    let node = &mut self.types[usize::from(id)];
+                    if let TypeNode::Value(original) =
+                        core::mem::replace(node, TypeNode::Value(refined))
+                    {
+                        self.unrefined.insert(id, original);
+                    }
+                }
+                Overwritten::Forgotten(id, original) => {
+                    self.unrefined.insert(id, original);
+                }
+                Overwritten::Flows { removed, len } => {
+                    // flows are only ever recorded at the end, so those
+                    // recorded since the drop are truncated first.
+                    self.flows.truncate(len);
+                    for (idx, flow) in removed {
+                        self.flows.insert(idx, flow);
+                        for fired in self.guarded.iter_mut().filter_map(|g| g.fired.as_mut()) {
+                            if *fired >= idx {
+                                *fired += 1;
+                            }
+                        }
+                    }
+                }
+                Overwritten::Detached { direct, violations } => {
+                    self.direct = direct;
+                    self.violations = violations;
+                }
+            }
+        }
+        while self.added_heads.len() > checkpoint.heads {
+            self.pop_head();
+        }
+        self.types.truncate(nodes);
+        self.holes.truncate(checkpoint.holes);
+        let flows = checkpoint.flows;
+        self.flows.truncate(flows);
+        self.sync_recorded();
+        self.direct.truncate(checkpoint.direct);
+        self.forbidden.truncate(checkpoint.forbidden);
+        self.violations.truncate(checkpoint.violations);
+        self.guarded.truncate(checkpoint.guarded);
+        self.warnings.truncate(checkpoint.warnings);
+        self.meets = checkpoint.meets;
+        self.failed = checkpoint.failed;
+        self.groups.tags.truncate(checkpoint.tags);
+        self.groups.open = checkpoint.open_group;
+        self.levels = checkpoint.levels;
+        for guarded in &mut self.guarded {
+            if guarded.fired.is_some_and(|fired| fired >= flows) {
+                guarded.fired = None;
+            }
+        }
+
+        let created = |id: &EntityId<I>| usize::from(*id) >= nodes;
+        self.labels.retain(|id, _| !created(id));
+        self.var_names.retain(|id, _| !created(id));
+        self.named_vars.retain(|_, id| !created(id));
+        self.groups.nodes.retain(|id, _| !created(id));
+        self.unrefined.retain(|id, _| !created(id));
+        self.meet_nodes.retain(|id| !created(id));
+        self.worklist = Worklist::default();
+    }
+
+    /// Keeps everything added since a checkpoint, discarding the ability to
+    /// restore it.
+    pub fn commit(&mut self, checkpoint: Checkpoint<I>) {
+        self.r.commit(checkpoint.graph);
+        self.aliases.commit(checkpoint.aliases);
+        if !self.r.is_snapshotting() {
+            self.overwritten.clear();
+        }
+    }
+
+    /// Adds every flow, or none of them. If any head check fails, the
+    /// checker is restored to exactly the state it was in before the call.
+    pub fn flow_batch(
+        &mut self,
+        pairs: impl IntoIterator<Item = (Value<I>, Use<I>)>,
+    ) -> Result<(), AT::Error> {
+        let checkpoint = self.save();
+        for (lhs, rhs) in pairs {
+            self.enqueue(lhs, rhs);
+        }
+        match self.settle() {
+            Ok(()) => {
+                self.commit(checkpoint);
+                Ok(())
+            }
+            Err(e) => {
+                self.restore(checkpoint);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Range, RangeTypeSystem, Strict};
+    use crate::*;
+
+    #[test]
+    fn restore_should_undo_speculative_flows() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        let int_use = t.new_use(Prim::Int);
+        assert!(t.flow(x_val, int_use).is_ok());
+
+        let checkpoint = t.save();
+        let literal = t.new_val(Prim::Bool);
+        assert_eq!(Err(TypeError::Converge), t.flow(literal, x_use));
+        t.restore(checkpoint);
+
+        assert_eq!(2, t.types.len());
+        assert_eq!(vec![(x_val.0, int_use.0)], t.r.edges().collect::<Vec<_>>());
+
+        let checkpoint = t.save();
+        let literal = t.new_val(Prim::Int);
+        assert!(t.flow(literal, x_use).is_ok());
+        t.commit(checkpoint);
+        assert!(t.reaches(literal, int_use));
+    }
+
+    #[test]
+    fn restore_should_roll_back_bookkeeping_and_retractions() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        let int_use = t.new_use(Prim::Int);
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, int_use).is_ok());
+        let meets = t.stats().meets;
+
+        let checkpoint = t.save();
+        t.push_level();
+        t.begin_group("speculative");
+        let literal = t.new_val(Prim::Bool);
+        assert_eq!(Err(TypeError::Converge), t.flow(literal, x_use));
+        assert!(t.retract(int, x_use).is_ok());
+        assert!(!t.reaches(int, int_use));
+        t.restore(checkpoint);
+
+        assert_eq!(meets, t.stats().meets);
+        assert_eq!(None, t.failed);
+        assert_eq!(None, t.groups.open);
+        assert!(t.groups.tags.is_empty());
+        assert_eq!(0, t.level());
+        assert!(t.reaches(int, int_use));
+        let recorded: Vec<(Value, Use)> = t.flows.iter().map(|f| (f.lhs, f.rhs)).collect();
+        assert_eq!(vec![(int, x_use), (x_val, int_use)], recorded);
+
+        // the flow put back can be retracted again.
+        assert!(t.retract(int, x_use).is_ok());
+        assert!(!t.reaches(int, int_use));
+    }
+
+    #[test]
+    fn failed_batches_should_leave_no_trace() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        let int_use = t.new_use(Prim::Int);
+        let bool_use = t.new_use(Prim::Bool);
+
+        assert_eq!(
+            Err(TypeError::Converge),
+            t.flow_batch(vec![(int, x_use), (x_val, int_use), (x_val, bool_use)])
+        );
+        assert_eq!(0, t.r.edges().count());
+        assert!(t.flows.is_empty());
+
+        assert!(t.flow_batch(vec![(int, x_use), (x_val, int_use)]).is_ok());
+        assert!(t.reaches(int, int_use));
+    }
+
+    #[test]
+    fn restore_should_undo_refined_heads() {
+        let mut t = TypeChecker::new(RangeTypeSystem);
+        let number = t.new_val(Range(0, 10));
+        let small = t.new_use(Range(0, 3));
+        let big = t.new_use(Range(5, 20));
+        assert_eq!(
+            Err(TypeError::Converge),
+            t.flow_batch(vec![(number, small), (number, big)])
+        );
+        assert_eq!(Some(&Range(0, 10)), t.head_of_value(number));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::prelude::*;
+use crate::{AbstractTypes, NodeIndex, TypeChecker, Use, Value};
+use alloc::sync::Arc;
+use core::any::Any;
+
+/// Coercions is implemented by type systems that accept some pairs of heads
+/// only once the value is converted, such as an integer passed where a
+/// float is expected. The meet accepts such pairs as it would any other,
+/// and `coercion` tags the ones needing a conversion so that a compiler can
+/// insert the matching cast in its IR.
+pub trait Coercions<V, U, I = u32>: AbstractTypes<V, U, I> {
+    type Tag;
+
+    /// Returns the conversion a value head needs to satisfy a use head that
+    /// the meet accepted, or None if it satisfies it as it is.
+    fn coercion(&self, lhs: &V, rhs: &U) -> Option<Self::Tag>;
+}
+
+/// CoercionRequired is a pair of nodes whose heads only agree once the
+/// value is converted as the tag describes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoercionRequired<C, I = u32> {
+    pub at: (Value<I>, Use<I>),
+    pub tag: C,
+}
+
+/// Coerces returns the conversion a pair of heads needs, if any.
+pub(crate) type Coerces<V, U, AT> = fn(&AT, &V, &U) -> Option<Tag>;
+
+/// Tag is the tag of a conversion with its type erased, so that a checker
+/// can hold the tags of whichever type system it has while a flow runs.
+pub(crate) type Tag = Arc<dyn Any + Send + Sync>;
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: Coercions<V, U, I>,
+    AT::Tag: Send + Sync + 'static,
+{
+    /// Adds a flow like `flow`, returning every pair of nodes it connected
+    /// whose heads only agree through a conversion. Pairs are reported once,
+    /// by the call that first connects them, and only their first heads are
+    /// checked for a conversion, when they are met. A coerced pair isn't
+    /// refined, as it is the converted value that satisfies the use.
+    pub fn flow_coercing(
+        &mut self,
+        lhs: Value<I>,
+        rhs: Use<I>,
+    ) -> Result<Vec<CoercionRequired<AT::Tag, I>>, AT::Error> {
+        self.coerces = Some(|types: &AT, lhs: &V, rhs: &U| {
+            types.coercion(lhs, rhs).map(|tag| Arc::new(tag) as Tag)
+        });
+        let result = self.flow(lhs, rhs);
+        self.coerces = None;
+        let coerced = core::mem::take(&mut self.coerced);
+        result?;
+
+        let required = coerced
+            .into_iter()
+            .map(|(lhs, rhs, tag)| {
+                let tag = Arc::downcast::<AT::Tag>(tag)
+                    .ok()
+                    .and_then(|tag| Arc::try_unwrap(tag).ok());
+                CoercionRequired {
+                    at: (lhs, rhs),
+                    tag: tag.expect("tags are only taken by the flow that erased them"),
+                }
+            })
+            .collect();
+        Ok(required)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Num {
+        Int,
+        Float,
+        Bool,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Cast {
+        IntToFloat,
+    }
+
+    /// Ints convert to floats, and every other head only satisfies itself.
+    #[derive(Debug)]
+    struct Widening;
+
+    impl AbstractTypes<Num, Num> for Widening {
+        type Error = TypeError;
+
+        fn meet(&self, lhs: &Num, rhs: &Num) -> Result<Vec<(Value, Use)>, Self::Error> {
+            match (lhs, rhs) {
+                (Num::Int, Num::Float) => Ok(vec![]),
+                _ if lhs == rhs => Ok(vec![]),
+                _ => Err(TypeError::Converge),
+            }
+        }
+    }
+
+    impl Coercions<Num, Num> for Widening {
+        type Tag = Cast;
+
+        fn coercion(&self, lhs: &Num, rhs: &Num) -> Option<Cast> {
+            match (lhs, rhs) {
+                (Num::Int, Num::Float) => Some(Cast::IntToFloat),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn coerced_pairs_should_be_returned_to_the_caller() {
+        let mut t = TypeChecker::new(Widening);
+        let int = t.new_val(Num::Int);
+        let (x_val, x_use) = t.var();
+        let float = t.new_use(Num::Float);
+        let other_int = t.new_use(Num::Int);
+        assert_eq!(Ok(vec![]), t.flow_coercing(int, x_use));
+        assert_eq!(Ok(vec![]), t.flow_coercing(x_val, other_int));
+
+        assert_eq!(
+            Ok(vec![CoercionRequired {
+                at: (int, float),
+                tag: Cast::IntToFloat,
+            }]),
+            t.flow_coercing(x_val, float)
+        );
+        // the pair was already connected, so it isn't reported again.
+        assert_eq!(Ok(vec![]), t.flow_coercing(int, float));
+        let boolean = t.new_val(Num::Bool);
+        assert_eq!(Err(TypeError::Converge), t.flow_coercing(boolean, float));
+    }
+}
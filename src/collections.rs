@@ -0,0 +1,82 @@
+//! The maps and sets the checker keeps its bookkeeping in. With the `std`
+//! feature these are the hashed collections of `std`, and without it the
+//! B-tree collections of `alloc`, which need no source of randomness. The
+//! checker never iterates them in an order that reaches its output, so the
+//! two behave the same.
+
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::collections::{BTreeMap as HashMap, BTreeSet as HashSet};
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{HashMap, HashSet};
+
+/// Key is what the checker needs of the keys of its maps and sets. The
+/// hashed collections only use the hash and the B-tree ones only the order,
+/// but both are required under every feature set, so that turning on `std`
+/// anywhere in a build never stops a key type from qualifying.
+pub trait Key: Ord + core::hash::Hash {}
+impl<T: Ord + core::hash::Hash> Key for T {}
+
+/// Shared holds bookkeeping that clones of a checker share until one of
+/// them writes to it, at which point that clone takes a copy of its own.
+#[derive(Default, Clone, PartialEq)]
+pub(crate) struct Shared<T>(alloc::sync::Arc<T>);
+
+impl<T> Shared<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self(alloc::sync::Arc::new(value))
+    }
+
+    /// Takes the value out, copying it if it is still shared.
+    pub(crate) fn into_inner(self) -> T
+    where
+        T: Clone,
+    {
+        alloc::sync::Arc::try_unwrap(self.0).unwrap_or_else(|shared| (*shared).clone())
+    }
+
+    /// Returns true if both hold the same copy.
+    #[cfg(test)]
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        alloc::sync::Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> core::ops::Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Clone> core::ops::DerefMut for Shared<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        alloc::sync::Arc::make_mut(&mut self.0)
+    }
+}
+
+impl<T: core::iter::FromIterator<A>, A> core::iter::FromIterator<A> for Shared<T> {
+    fn from_iter<I: IntoIterator<Item = A>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Shared<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Shared<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self::new)
+    }
+}
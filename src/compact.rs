@@ -0,0 +1,256 @@
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::scheme::Instantiate;
+use crate::{
+    union_find, AbstractTypes, EntityId, FlowWarning, NodeIndex, RecordedFlow, TypeChecker,
+    TypeNode, Use, Value, Violation, Worklist,
+};
+
+/// Remap records where `compact` or `absorb` moved each node, so that
+/// handles held outside of the checker can be updated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remap<I = u32> {
+    pub(crate) ids: Vec<Option<EntityId<I>>>,
+}
+
+impl<I: NodeIndex> Remap<I> {
+    fn id(&self, id: EntityId<I>) -> Option<EntityId<I>> {
+        self.ids.get(usize::from(id)).copied().flatten()
+    }
+
+    /// Returns the new handle for a value, or None if its node was dropped.
+    pub fn map_value(&self, value: Value<I>) -> Option<Value<I>> {
+        self.id(value.0).map(Value)
+    }
+
+    /// Returns the new handle for a use, or None if its node was dropped.
+    pub fn map_use(&self, rhs: Use<I>) -> Option<Use<I>> {
+        self.id(rhs.0).map(Use)
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Instantiate<I>,
+    U: Instantiate<I>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Drops every node that can't be reached from the roots, following
+    /// edges in either direction and the handles nested inside heads, and
+    /// renumbers the nodes that remain. Every handle created before the
+    /// call must be passed through the returned Remap before it is used
+    /// again, and any pending work from a cancelled check is discarded.
+    ///
+    /// Panics if a checkpoint or a binding level is open.
+    pub fn compact(&mut self, values: &[Value<I>], uses: &[Use<I>]) -> Remap<I> {
+        assert!(self.levels.is_empty(), "can't compact inside a level");
+        let mut kept = vec![false; self.types.len()];
+        let mut work: Vec<EntityId<I>> = values
+            .iter()
+            .map(|value| value.0)
+            .chain(uses.iter().map(|rhs| rhs.0))
+            .collect();
+        while let Some(node) = work.pop() {
+            if core::mem::replace(&mut kept[usize::from(node)], true) {
+                continue;
+            }
+            let root = self.aliases.root(node);
+            work.push(root);
+            work.extend(self.r.upstream(root));
+            work.extend(self.r.downstream(root));
+            work.extend(self.nested(node));
+        }
+
+        let mut next = 0;
+        let remap = Remap {
+            ids: kept
+                .iter()
+                .map(|&kept| {
+                    kept.then(|| {
+                        next += 1;
+                        EntityId::at(next - 1)
+                    })
+                })
+                .collect(),
+        };
+        self.apply(&remap);
+        remap
+    }
+
+    /// Moves every node and piece of bookkeeping to its new index.
+    fn apply(&mut self, remap: &Remap<I>) {
+        let id = |id: EntityId<I>| remap.id(id);
+        self.r.compact_mut(&remap.ids);
+
+        let mut aliases = union_find::UnionFind::default();
+        for _ in remap.ids.iter().flatten() {
+            aliases.make_set();
+        }
+        for old in (0..remap.ids.len()).map(EntityId::at) {
+            if let (Some(node), Some(root)) = (id(old), id(self.aliases.root(old))) {
+                // every node is merged into its root once, so the root
+                // keeps the higher rank and stays the representative.
+                if node != root {
+                    aliases.union(root, node);
+                }
+            }
+        }
+        self.aliases = aliases;
+
+        let values = |value: Value<I>| remap.map_value(value).unwrap_or(value);
+        let uses = |rhs: Use<I>| remap.map_use(rhs).unwrap_or(rhs);
+        let types = core::mem::take(&mut self.types);
+        self.types = types
+            .into_iter()
+            .enumerate()
+            .filter(|&(old, _)| remap.ids[old].is_some())
+            .map(|(_, node)| match node {
+                TypeNode::Var => TypeNode::Var,
+                TypeNode::Value(head) => TypeNode::Value(head.instantiate(&values, &uses)),
+                TypeNode::Use(head) => TypeNode::Use(head.instantiate(&values, &uses)),
+            })
+            .collect();
+
+        let remap_head = |node: TypeNode<V, U>| match node {
+            TypeNode::Var => TypeNode::Var,
+            TypeNode::Value(head) => TypeNode::Value(head.instantiate(&values, &uses)),
+            TypeNode::Use(head) => TypeNode::Use(head.instantiate(&values, &uses)),
+        };
+        self.extra_heads = core::mem::take(&mut self.extra_heads)
+            .into_iter()
+            .filter_map(|(old, heads)| {
+                Some((id(old)?, heads.into_iter().map(remap_head).collect()))
+            })
+            .collect();
+        self.added_heads = self.added_heads.iter().filter_map(|&old| id(old)).collect();
+        self.unrefined = core::mem::take(&mut self.unrefined)
+            .into_iter()
+            .filter_map(|(old, head)| Some((id(old)?, head.instantiate(&values, &uses))))
+            .collect();
+        self.meet_nodes = self.meet_nodes.iter().filter_map(|&old| id(old)).collect();
+
+        self.holes = self
+            .holes
+            .iter()
+            .filter_map(|&h| remap.map_use(h))
+            .collect();
+        let rekey = |map: &mut HashMap<EntityId<I>, _>| {
+            *map = core::mem::take(map)
+                .into_iter()
+                .filter_map(|(old, v)| id(old).map(|new| (new, v)))
+                .collect();
+        };
+        rekey(&mut self.labels);
+        rekey(&mut self.var_names);
+        self.named_vars = core::mem::take(&mut self.named_vars)
+            .into_inner()
+            .into_iter()
+            .filter_map(|(name, old)| id(old).map(|new| (name, new)))
+            .collect();
+        self.groups.nodes = core::mem::take(&mut self.groups.nodes)
+            .into_iter()
+            .filter_map(|(old, group)| id(old).map(|new| (new, group)))
+            .collect();
+
+        self.flows = self
+            .flows
+            .iter()
+            .filter_map(|flow| {
+                Some(RecordedFlow {
+                    lhs: remap.map_value(flow.lhs)?,
+                    rhs: remap.map_use(flow.rhs)?,
+                    group: flow.group,
+                })
+            })
+            .collect();
+        self.sync_recorded();
+        self.direct = self
+            .direct
+            .iter()
+            .filter_map(|&(lhs, rhs)| Some((id(lhs)?, id(rhs)?)))
+            .collect();
+        self.forbidden = self
+            .forbidden
+            .iter()
+            .filter_map(|&(lhs, rhs)| Some((remap.map_value(lhs)?, remap.map_use(rhs)?)))
+            .collect();
+        self.violations = self
+            .violations
+            .iter()
+            .filter_map(|violation| {
+                Some(Violation {
+                    lhs: remap.map_value(violation.lhs)?,
+                    rhs: remap.map_use(violation.rhs)?,
+                    path: violation
+                        .path
+                        .iter()
+                        .map(|&node| id(node))
+                        .collect::<Option<_>>()?,
+                })
+            })
+            .collect();
+        // no checkpoint is open, so where a guarded flow fired no longer
+        // matters once the flows are renumbered.
+        self.guarded = core::mem::take(&mut self.guarded)
+            .into_iter()
+            .filter_map(|mut guarded| {
+                guarded.guard = remap.map_value(guarded.guard)?;
+                guarded.lhs = remap.map_value(guarded.lhs)?;
+                guarded.rhs = remap.map_use(guarded.rhs)?;
+                guarded.fired = guarded.fired.map(|_| 0);
+                Some(guarded)
+            })
+            .collect();
+        self.warnings = core::mem::take(&mut self.warnings)
+            .into_iter()
+            .filter_map(|warning| match warning {
+                FlowWarning::HighOutDegree { entity, degree } => Some(FlowWarning::HighOutDegree {
+                    entity: id(entity)?,
+                    degree,
+                }),
+            })
+            .collect();
+        self.failed = self
+            .failed
+            .and_then(|(lhs, rhs)| Some((id(lhs)?, id(rhs)?)));
+        self.worklist = Worklist::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::{Head, HeadTypeSystem};
+
+    #[test]
+    fn compact_should_drop_unreachable_nodes_and_remap_the_rest() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let (temp_val, temp_use) = t.var();
+        let temp = t.new_val(Head::Int);
+        assert!(t.flow(temp, temp_use).is_ok());
+
+        let int = t.new_val(Head::Int);
+        let list = t.new_val(Head::List(int));
+        let (x_val, x_use) = t.var();
+        assert!(t.flow(list, x_use).is_ok());
+
+        let remap = t.compact(&[x_val], &[]);
+        assert_eq!(None, remap.map_value(temp_val));
+        assert_eq!(3, t.types.len());
+
+        let (int, x_val, x_use) = (
+            remap.map_value(int).unwrap(),
+            remap.map_value(x_val).unwrap(),
+            remap.map_use(x_use).unwrap(),
+        );
+        let list = remap.map_value(list).unwrap();
+        assert!(t.reaches(list, x_use));
+        assert!(matches!(t.types[list.index()], TypeNode::Value(Head::List(elem)) if elem == int));
+
+        let (_, elem_use) = t.var();
+        let list_use = t.new_use(Head::List(Value(elem_use.0)));
+        assert!(t.flow(x_val, list_use).is_ok());
+        assert!(t.reaches(int, elem_use));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::prelude::*;
+use crate::{AbstractTypes, NodeIndex, TypeChecker, Use, Value};
+
+/// Fields exposes the named fields of a record-like value head.
+pub trait Fields<I = u32> {
+    /// Returns the name and value of each field, in display order. Heads
+    /// that aren't records have no fields.
+    fn fields(&self) -> Vec<(String, Value<I>)> {
+        Vec::new()
+    }
+}
+
+/// Completion is a field name offered at a use, along with the value of
+/// that field on every head that has it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion<I = u32> {
+    pub name: String,
+    pub values: Vec<Value<I>>,
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Fields<I>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns every field available on any value head currently reaching a
+    /// use, such as the receiver of a field access, sorted by name. The
+    /// values of each field are ordered by the heads they belong to.
+    pub fn completions(&self, rhs: Use<I>) -> Vec<Completion<I>> {
+        let id = self.aliases.root(rhs.0);
+        let mut completions: Vec<Completion<I>> = Vec::new();
+
+        let mut sources: Vec<_> = self.r.upstream(id).collect();
+        sources.sort_unstable();
+        let heads = sources.into_iter().flat_map(|lhs| self.value_heads(lhs));
+        for (name, value) in heads.flat_map(Fields::fields) {
+            match completions.iter_mut().find(|c| c.name == name) {
+                Some(completion) => completion.values.push(value),
+                None => completions.push(Completion {
+                    name,
+                    values: vec![value],
+                }),
+            }
+        }
+        completions.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+        completions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Debug, Clone)]
+    enum Head {
+        Int,
+        Record(Vec<(String, Value)>),
+    }
+
+    impl Fields for Head {
+        fn fields(&self) -> Vec<(String, Value)> {
+            match self {
+                Head::Record(fields) => fields.clone(),
+                Head::Int => vec![],
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct OpenTypeSystem;
+
+    impl AbstractTypes<Head, ()> for OpenTypeSystem {
+        type Error = TypeError;
+
+        fn meet(&self, _: &Head, _: &()) -> Result<Vec<(Value, Use)>, Self::Error> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn completions_should_union_fields_of_reaching_records() {
+        let mut t = TypeChecker::new(OpenTypeSystem);
+        let (x, y) = (t.new_val(Head::Int), t.new_val(Head::Int));
+        let point = t.new_val(Head::Record(vec![("x".to_string(), x)]));
+        let point3 = t.new_val(Head::Record(vec![
+            ("y".to_string(), y),
+            ("x".to_string(), y),
+        ]));
+
+        let (receiver_val, receiver_use) = t.var();
+        let access = t.new_use(());
+        assert!(t.flow(point, receiver_use).is_ok());
+        assert!(t.flow(point3, receiver_use).is_ok());
+        assert!(t.flow(receiver_val, access).is_ok());
+
+        assert_eq!(
+            vec![
+                Completion {
+                    name: "x".to_string(),
+                    values: vec![x, y],
+                },
+                Completion {
+                    name: "y".to_string(),
+                    values: vec![y],
+                },
+            ],
+            t.completions(access)
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Co-occurrence analysis in the style of Simple-sub. In a polar type a var
+//! only matters through the other members of the unions and intersections
+//! it appears in, so a var that is always accompanied by the same var, or
+//! by the same type in both polarities, can be merged into it or dropped.
+//! This is much cheaper than minimizing the whole automaton while catching
+//! most of the vars that make inferred types hard to read.
+
+use crate::collections::HashMap;
+use crate::describe::{Describe, Polarity, TypeDescription};
+use crate::prelude::*;
+use crate::{AbstractTypes, NodeIndex, TypeChecker, Value};
+
+/// Occurrences tracks, for every free var and polarity, the members that
+/// accompanied the var in each of its occurrences.
+#[derive(Debug, Default)]
+struct Occurrences {
+    order: Vec<String>,
+    cooccurring: HashMap<(String, Polarity), Vec<TypeDescription>>,
+}
+
+impl Occurrences {
+    fn record(&mut self, name: &str, polarity: Polarity, others: Vec<TypeDescription>) {
+        if !self.order.iter().any(|seen| seen == name) {
+            self.order.push(name.to_string());
+        }
+
+        let key = (name.to_string(), polarity);
+        match self.cooccurring.get_mut(&key) {
+            Some(cooccurring) => cooccurring.retain(|member| others.contains(member)),
+            None => {
+                self.cooccurring.insert(key, others);
+            }
+        }
+    }
+
+    fn collect(&mut self, ty: &TypeDescription, polarity: Polarity, bound: &mut Vec<String>) {
+        match ty {
+            TypeDescription::Var(name) if !bound.contains(name) => {
+                self.record(name, polarity, Vec::new())
+            }
+            TypeDescription::Var(_) => (),
+            TypeDescription::Head { children, .. } => {
+                for (polarity, child) in children {
+                    self.collect(child, *polarity, bound);
+                }
+            }
+            TypeDescription::Union(members) | TypeDescription::Intersection(members) => {
+                for (idx, member) in members.iter().enumerate() {
+                    match member {
+                        TypeDescription::Var(name) if !bound.contains(name) => {
+                            let others = members
+                                .iter()
+                                .enumerate()
+                                .filter(|&(other, _)| other != idx)
+                                .map(|(_, other)| other.clone())
+                                .collect();
+                            self.record(name, polarity, others);
+                        }
+                        _ => self.collect(member, polarity, bound),
+                    }
+                }
+            }
+            TypeDescription::Recursive { name, body } => {
+                bound.push(name.clone());
+                self.collect(body, polarity, bound);
+                bound.pop();
+            }
+        }
+    }
+}
+
+/// Plan records the vars to substitute and to drop from joins and meets.
+#[derive(Debug, Default)]
+struct Plan {
+    substitutions: HashMap<String, String>,
+    removed: Vec<String>,
+}
+
+impl Plan {
+    fn new(occurrences: &Occurrences) -> Self {
+        let mut plan = Plan::default();
+        for name in &occurrences.order {
+            let positive = occurrences
+                .cooccurring
+                .get(&(name.clone(), Polarity::Positive));
+            let negative = occurrences
+                .cooccurring
+                .get(&(name.clone(), Polarity::Negative));
+
+            let (positive, negative) = match (positive, negative) {
+                (Some(positive), Some(negative)) => (positive, negative),
+                // a var of a single polarity can always be instantiated to
+                // the identity of the join or meet it appears in.
+                _ => {
+                    plan.removed.push(name.clone());
+                    continue;
+                }
+            };
+
+            let mut sandwiched = false;
+            for member in positive.iter().filter(|member| negative.contains(member)) {
+                match member {
+                    TypeDescription::Var(other)
+                        if other != name && !plan.substitutions.contains_key(other) =>
+                    {
+                        plan.substitutions.insert(name.clone(), other.clone());
+                        break;
+                    }
+                    TypeDescription::Var(_) => (),
+                    _ => sandwiched = true,
+                }
+            }
+
+            if sandwiched && !plan.substitutions.contains_key(name) {
+                plan.removed.push(name.clone());
+            }
+        }
+        plan
+    }
+
+    fn is_removed(&self, ty: &TypeDescription, bound: &[String]) -> bool {
+        match ty {
+            TypeDescription::Var(name) => !bound.contains(name) && self.removed.contains(name),
+            _ => false,
+        }
+    }
+
+    fn rewrite_members(
+        &self,
+        members: &[TypeDescription],
+        bound: &mut Vec<String>,
+    ) -> Vec<TypeDescription> {
+        let kept: Vec<&TypeDescription> = members
+            .iter()
+            .filter(|member| !self.is_removed(member, bound))
+            .collect();
+        // a var standing alone has nothing to be absorbed into.
+        let kept = if kept.is_empty() {
+            members.iter().collect()
+        } else {
+            kept
+        };
+
+        let mut rewritten: Vec<TypeDescription> = Vec::new();
+        for member in kept {
+            let member = self.rewrite(member, bound);
+            if !rewritten.contains(&member) {
+                rewritten.push(member);
+            }
+        }
+        rewritten
+    }
+
+    fn rewrite(&self, ty: &TypeDescription, bound: &mut Vec<String>) -> TypeDescription {
+        match ty {
+            TypeDescription::Var(name) if !bound.contains(name) => {
+                let mut name = name;
+                while let Some(other) = self.substitutions.get(name) {
+                    name = other;
+                }
+                TypeDescription::Var(name.clone())
+            }
+            TypeDescription::Var(name) => TypeDescription::Var(name.clone()),
+            TypeDescription::Head { label, children } => TypeDescription::Head {
+                label: label.clone(),
+                children: children
+                    .iter()
+                    .map(|(polarity, child)| (*polarity, self.rewrite(child, bound)))
+                    .collect(),
+            },
+            TypeDescription::Union(members) => {
+                let mut members = self.rewrite_members(members, bound);
+                match members.len() {
+                    1 => members.pop().unwrap(),
+                    _ => TypeDescription::Union(members),
+                }
+            }
+            TypeDescription::Intersection(members) => {
+                let mut members = self.rewrite_members(members, bound);
+                match members.len() {
+                    1 => members.pop().unwrap(),
+                    _ => TypeDescription::Intersection(members),
+                }
+            }
+            TypeDescription::Recursive { name, body } => {
+                bound.push(name.clone());
+                let body = self.rewrite(body, bound);
+                bound.pop();
+                TypeDescription::Recursive {
+                    name: name.clone(),
+                    body: Box::new(body),
+                }
+            }
+        }
+    }
+}
+
+impl TypeDescription {
+    /// Removes vars that are redundant given the members they co-occur
+    /// with, reading the description in the given polarity. A var that only
+    /// occurs in one polarity is dropped from the joins and meets it
+    /// appears in, a var always accompanied by the same other var in both
+    /// polarities is replaced by it, and a var always accompanied by the
+    /// same type in both polarities is dropped.
+    pub fn simplify_cooccurrences(&self, polarity: Polarity) -> TypeDescription {
+        let mut occurrences = Occurrences::default();
+        occurrences.collect(self, polarity, &mut Vec::new());
+        Plan::new(&occurrences).rewrite(self, &mut Vec::new())
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Describe<I>,
+    U: Describe<I>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Describes a value for display, removing redundant vars before
+    /// minimizing the result.
+    pub fn describe_simplified(&self, value: Value<I>) -> TypeDescription {
+        self.describe(value)
+            .simplify_cooccurrences(Polarity::Positive)
+            .simplify()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> TypeDescription {
+        TypeDescription::Var(name.to_string())
+    }
+
+    fn int() -> TypeDescription {
+        TypeDescription::Head {
+            label: "int".to_string(),
+            children: vec![],
+        }
+    }
+
+    fn func(arg: TypeDescription, ret: TypeDescription) -> TypeDescription {
+        TypeDescription::Head {
+            label: "fn".to_string(),
+            children: vec![(Polarity::Negative, arg), (Polarity::Positive, ret)],
+        }
+    }
+
+    #[test]
+    fn cooccurring_vars_should_be_merged_or_dropped() {
+        use TypeDescription::{Intersection, Union};
+
+        let sandwiched = func(
+            Intersection(vec![var("a"), int()]),
+            Union(vec![var("a"), int()]),
+        );
+        assert_eq!(
+            "fn(int, int)",
+            sandwiched
+                .simplify_cooccurrences(Polarity::Positive)
+                .to_string()
+        );
+
+        let paired = func(
+            Intersection(vec![var("a"), var("b")]),
+            Union(vec![var("b"), var("a")]),
+        );
+        assert_eq!(
+            "fn(b, b)",
+            paired
+                .simplify_cooccurrences(Polarity::Positive)
+                .simplify()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn polar_vars_should_be_dropped_from_joins() {
+        let ty = func(var("a"), TypeDescription::Union(vec![var("b"), int()]));
+        assert_eq!(
+            "fn(a, int)",
+            ty.simplify_cooccurrences(Polarity::Positive).to_string()
+        );
+    }
+}
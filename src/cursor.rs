@@ -0,0 +1,232 @@
+use crate::prelude::*;
+use crate::{AbstractTypes, NodeIndex, TypeChecker, Use, Value, WorklistOrder};
+use alloc::collections::VecDeque;
+
+/// StepResult reports where a FlowCursor stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepResult<E> {
+    /// Every flow has been fully checked.
+    Done,
+    /// The budget ran out with work still pending.
+    Pending,
+    /// A head check failed, discarding the remaining work.
+    Failed(E),
+}
+
+/// FlowCursor drives a check in bounded steps, letting a host interleave
+/// solving with other work on the same thread. Dropping a cursor with work
+/// pending leaves that work on the checker, to be finished by the next flow.
+/// The flows of a check that fails aren't recorded, as with `flow`.
+#[derive(Debug)]
+pub struct FlowCursor<'a, V, U, AT, I = u32>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    checker: &'a mut TypeChecker<V, U, AT, I>,
+    /// the positions of the flows recorded since the work was last done.
+    recorded: Vec<usize>,
+}
+
+impl<'a, V, U, AT, I> FlowCursor<'a, V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Queues another flow to be checked by later steps.
+    pub fn push(&mut self, lhs: Value<I>, rhs: Use<I>) {
+        let recorded = self.checker.enqueue_all(core::iter::once((lhs, rhs)));
+        self.recorded.extend(recorded);
+    }
+
+    /// Performs at most `budget` steps of the worklist, where each step adds
+    /// a single edge or checks a single pair of heads.
+    pub fn step(&mut self, budget: usize) -> StepResult<AT::Error> {
+        let result = self.advance(budget);
+        match result {
+            StepResult::Done => self.recorded.clear(),
+            StepResult::Failed(_) => self.forget(),
+            StepResult::Pending => (),
+        }
+        result
+    }
+
+    fn advance(&mut self, budget: usize) -> StepResult<AT::Error> {
+        for _ in 0..budget {
+            match self.checker.step() {
+                Ok(true) => (),
+                Ok(false) => return StepResult::Done,
+                Err(e) => return StepResult::Failed(e),
+            }
+        }
+
+        if self.checker.is_settled() {
+            StepResult::Done
+        } else {
+            StepResult::Pending
+        }
+    }
+
+    /// Checks all of the remaining work.
+    fn finish(mut self) -> Result<(), AT::Error> {
+        let result = self.checker.settle();
+        if result.is_err() {
+            self.forget();
+        }
+        result
+    }
+
+    fn forget(&mut self) {
+        let recorded = core::mem::take(&mut self.recorded);
+        self.checker.forget_flows(&recorded);
+    }
+}
+
+/// FlowSession is a check in progress that a host drives step by step, as
+/// with a FlowCursor, while inspecting the frontier of work still pending
+/// between steps, such as to show it in an interactive debugger.
+#[derive(Debug)]
+pub struct FlowSession<'a, V, U, AT, I = u32>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    cursor: FlowCursor<'a, V, U, AT, I>,
+}
+
+impl<'a, V, U, AT, I> FlowSession<'a, V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Queues another flow to be checked by later steps.
+    pub fn push(&mut self, lhs: Value<I>, rhs: Use<I>) {
+        self.cursor.push(lhs, rhs);
+    }
+
+    /// Performs at most `n` steps, as `FlowCursor::step` does.
+    pub fn step(&mut self, n: usize) -> StepResult<AT::Error> {
+        self.cursor.step(n)
+    }
+
+    /// Checks all of the remaining work.
+    pub fn finish(self) -> Result<(), AT::Error> {
+        self.cursor.finish()
+    }
+
+    /// Returns the flows still to be added to the graph, in the order they
+    /// will be taken unless the worklist is shuffled.
+    pub fn pending_flows(&self) -> impl Iterator<Item = (Value<I>, Use<I>)> + '_ {
+        in_order(self.order(), &self.cursor.checker.worklist.edges)
+    }
+
+    /// Returns the pairs of nodes newly connected whose heads are still to
+    /// be met, in the order they will be taken unless the worklist is
+    /// shuffled. They are all met before the next flow is added.
+    pub fn pending_meets(&self) -> impl Iterator<Item = (Value<I>, Use<I>)> + '_ {
+        in_order(self.order(), &self.cursor.checker.worklist.pairs)
+            .map(|(lhs, rhs)| (Value(lhs), Use(rhs)))
+    }
+
+    fn order(&self) -> WorklistOrder {
+        self.cursor.checker.graph_config.worklist_order
+    }
+}
+
+/// Lists queued work in the order it will be taken.
+fn in_order<T: Copy>(order: WorklistOrder, work: &VecDeque<T>) -> impl Iterator<Item = T> + '_ {
+    let len = work.len();
+    (0..len).map(move |i| match order {
+        WorklistOrder::Lifo => work[len - 1 - i],
+        WorklistOrder::Fifo => work[i],
+    })
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Queues flows without checking them, returning a session to check
+    /// them step by step and inspect the work pending in between.
+    pub fn begin_flow(
+        &mut self,
+        pairs: impl IntoIterator<Item = (Value<I>, Use<I>)>,
+    ) -> FlowSession<'_, V, U, AT, I> {
+        FlowSession {
+            cursor: self.cursor(pairs),
+        }
+    }
+
+    /// Queues flows without checking them, returning a cursor to check them
+    /// step by step.
+    pub fn cursor(
+        &mut self,
+        pairs: impl IntoIterator<Item = (Value<I>, Use<I>)>,
+    ) -> FlowCursor<'_, V, U, AT, I> {
+        let recorded = self.enqueue_all(pairs);
+        FlowCursor {
+            checker: self,
+            recorded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn cursor_should_check_flows_within_its_budget() {
+        let mut t = TypeChecker::new(Strict);
+        let (var_val, var_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        let int_use = t.new_use(Prim::Int);
+        let bool_use = t.new_use(Prim::Bool);
+
+        let mut cursor = t.cursor(vec![(int, var_use), (var_val, int_use)]);
+        assert_eq!(StepResult::Pending, cursor.step(1));
+        assert_eq!(StepResult::Done, cursor.step(100));
+
+        cursor.push(var_val, bool_use);
+        let mut result = cursor.step(1);
+        while result == StepResult::Pending {
+            result = cursor.step(1);
+        }
+        assert_eq!(StepResult::Failed(TypeError::Converge), result);
+        let recorded: Vec<(Value, Use)> = t.flows.iter().map(|f| (f.lhs, f.rhs)).collect();
+        assert_eq!(vec![(int, var_use), (var_val, int_use)], recorded);
+
+        let bool_use = t.new_use(Prim::Bool);
+        let session = t.begin_flow(vec![(var_val, bool_use)]);
+        assert_eq!(Err(TypeError::Converge), session.finish());
+        assert_eq!(2, t.flows.len());
+    }
+
+    #[test]
+    fn sessions_should_expose_their_pending_work() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        let sink = t.new_use(Prim::Int);
+
+        let mut session = t.begin_flow(vec![(int, x_use), (x_val, sink)]);
+        assert_eq!(
+            vec![(x_val, sink), (int, x_use)],
+            session.pending_flows().collect::<Vec<_>>()
+        );
+        // adding each flow connects its ends, and the first also connects
+        // the int to the sink through the var.
+        assert_eq!(StepResult::Pending, session.step(3));
+        assert_eq!(0, session.pending_flows().count());
+        assert_eq!(
+            vec![(int, sink), (int, x_use)],
+            session.pending_meets().collect::<Vec<_>>()
+        );
+
+        session.push(int, sink);
+        assert!(session.finish().is_ok());
+        assert!(t.is_settled());
+    }
+}
@@ -0,0 +1,145 @@
+//! Cycles in the flow graph. Flowing a var into itself, directly or through
+//! other nodes, is always safe: every edge is added to the graph at most
+//! once and each pair of heads is only met when the edge between them is
+//! new, so checking terminates on cyclic graphs just as it does on acyclic
+//! ones. Types built from such cycles are read back by `describe` as
+//! recursive types rather than being unrolled.
+//!
+//! Since the graph is transitively closed, every member of a cycle has the
+//! same edges as every other, so `condense_cycles` can merge each cycle
+//! into a single var without losing anything.
+
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, TypeNode, Value};
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns every node on a cycle of flows through a value, which is
+    /// every node both reachable from and reaching it, including the value
+    /// itself. Returns nothing if the value isn't on a cycle.
+    pub fn cycles_through(&self, value: Value<I>) -> Vec<Value<I>> {
+        let id = self.aliases.root(value.0);
+        let mut cycle: Vec<Value<I>> = self
+            .r
+            .downstream(id)
+            .filter(|&other| self.r.has_edge(other, id))
+            .map(Value)
+            .collect();
+        // a graph skipping self-edges has no edge from the value to itself.
+        if !cycle.is_empty() && !self.r.has_edge(id, id) {
+            cycle.push(Value(id));
+        }
+        cycle
+    }
+
+    /// Merges the vars of every cycle into one var standing in for all of
+    /// them, leaving any heads in the cycle as they are, so that later flows
+    /// through the cycle add one edge rather than one per member. The other
+    /// members are detached from the graph and flows through their handles
+    /// are redirected, as `canonical_value` and `canonical_use` report.
+    /// Returns the number of vars merged away.
+    pub fn condense_cycles(&mut self) -> usize {
+        let mut merged = 0;
+        for idx in 0..self.types.len() {
+            let id = EntityId::at(idx);
+            if self.aliases.find(id) != id || self.r.is_removed(id) {
+                continue;
+            }
+            // heads in a cycle keep their own nodes, as merging a head into
+            // a var would lose it, but the vars alongside them still merge.
+            let vars: Vec<Value<I>> = self
+                .cycles_through(Value(id))
+                .into_iter()
+                .filter(|member| matches!(self.types[usize::from(member.0)], TypeNode::Var))
+                .collect();
+            if vars.len() < 2 {
+                continue;
+            }
+
+            let root = vars[1..]
+                .iter()
+                .fold(vars[0].0, |root, member| self.aliases.union(root, member.0));
+            for member in vars.into_iter().filter(|member| member.0 != root) {
+                self.r.detach_mut(member.0);
+                merged += 1;
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Head, HeadTypeSystem};
+    use crate::*;
+
+    #[test]
+    fn cyclic_flows_should_terminate_and_be_reported() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let (x_val, x_use) = t.var();
+        let (y_val, y_use) = t.var();
+        let (z_val, _) = t.var();
+        assert!(t.flow(x_val, y_use).is_ok());
+        assert!(t.flow(y_val, x_use).is_ok());
+        assert_eq!(vec![y_val, x_val], t.cycles_through(x_val));
+        assert!(t.cycles_through(z_val).is_empty());
+
+        // a list whose elements are the list itself, read as a cons cell
+        // holding its own tail, which meets against itself through the cycle.
+        let list = t.new_val(Head::List(x_val));
+        let list_use = t.new_use(Head::List(Value(x_use.0)));
+        assert!(t.flow(list, x_use).is_ok());
+        assert!(t.flow(x_val, list_use).is_ok());
+        assert_eq!("rec a. list(a)", t.describe(x_val).to_string());
+    }
+
+    #[test]
+    fn condensing_should_merge_each_cycle_into_one_var() {
+        let config = GraphConfig {
+            skip_self_edges: true,
+            ..GraphConfig::default()
+        };
+        let mut t = TypeChecker::new_with_config(HeadTypeSystem, config);
+        let vars: Vec<(Value, Use)> = (0..3).map(|_| t.var()).collect();
+        for (idx, &(value, _)) in vars.iter().enumerate() {
+            assert!(t.flow(value, vars[(idx + 1) % 3].1).is_ok());
+        }
+        let (_, sink) = t.var();
+        assert!(t.flow(vars[0].0, sink).is_ok());
+        assert_eq!(9, t.r.edges().count());
+
+        assert_eq!(2, t.condense_cycles());
+        assert_eq!(0, t.condense_cycles());
+        let root = t.canonical_value(vars[0].0);
+        assert!(vars
+            .iter()
+            .all(|&(value, _)| t.canonical_value(value) == root));
+        assert_eq!(1, t.r.edges().count());
+
+        // flows through any member now only add edges to the survivor.
+        let (_, other) = t.var();
+        assert!(t.flow(vars[2].0, other).is_ok());
+        assert_eq!(2, t.r.edges().count());
+        assert!(t.reaches(vars[1].0, other));
+    }
+
+    #[test]
+    fn condensing_should_merge_the_vars_of_cycles_through_heads() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let (x_val, x_use) = t.var();
+        let (y_val, y_use) = t.var();
+        let list = t.new_val(Head::List(x_val));
+        assert!(t.flow(list, x_use).is_ok());
+        assert!(t.flow(x_val, y_use).is_ok());
+        assert!(t.flow(y_val, Use(list.0)).is_ok());
+        assert_eq!(3, t.cycles_through(list).len());
+
+        assert_eq!(1, t.condense_cycles());
+        assert_eq!(t.canonical_value(x_val), t.canonical_value(y_val));
+        assert_ne!(t.canonical_value(x_val), t.canonical_value(list));
+        assert!(t.head_of_value(list).is_some());
+    }
+}
@@ -0,0 +1,479 @@
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, TypeNodeRef, Use, Value};
+use core::marker::PhantomData;
+
+/// Child is a handle nested inside a head, such as the argument use and
+/// return value of a function type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Child<I = u32> {
+    Value(Value<I>),
+    Use(Use<I>),
+}
+
+/// Describe exposes enough of a head for a type to be rebuilt from the flow
+/// graph.
+pub trait Describe<I = u32> {
+    /// Returns the name of the head's constructor, such as `int` or `fn`.
+    fn label(&self) -> String;
+
+    /// Returns the handles nested inside the head, in display order.
+    fn children(&self) -> Vec<Child<I>> {
+        Vec::new()
+    }
+}
+
+/// TypeDescription is a polar type reconstructed from the flow graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDescription {
+    /// A variable that no head reaches.
+    Var(String),
+    /// A single head and the descriptions of its children, each in the
+    /// polarity it appears in.
+    Head {
+        label: String,
+        children: Vec<(Polarity, TypeDescription)>,
+    },
+    /// The join of every value head reaching a value.
+    Union(Vec<TypeDescription>),
+    /// The meet of every use head reached from a use.
+    Intersection(Vec<TypeDescription>),
+    /// A recursive type, where occurrences of `name` within `body` refer
+    /// back to the type itself.
+    Recursive {
+        name: String,
+        body: Box<TypeDescription>,
+    },
+}
+
+impl core::fmt::Display for TypeDescription {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        fn join<'d>(
+            f: &mut core::fmt::Formatter<'_>,
+            members: impl Iterator<Item = &'d TypeDescription>,
+            sep: &str,
+        ) -> core::fmt::Result {
+            for (i, member) in members.enumerate() {
+                if i > 0 {
+                    write!(f, "{}", sep)?;
+                }
+                write!(f, "{}", member)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Self::Var(name) => write!(f, "{}", name),
+            Self::Head { label, children } if children.is_empty() => write!(f, "{}", label),
+            Self::Head { label, children } => {
+                write!(f, "{}(", label)?;
+                join(f, children.iter().map(|(_, child)| child), ", ")?;
+                write!(f, ")")
+            }
+            Self::Union(members) => join(f, members.iter(), " | "),
+            Self::Intersection(members) => join(f, members.iter(), " & "),
+            Self::Recursive { name, body } => write!(f, "rec {}. {}", name, body),
+        }
+    }
+}
+
+/// Returns the name given to the nth variable of a description: `a` through
+/// `z`, then `a1` and so on.
+pub(crate) fn nth_name(n: usize) -> String {
+    let (letter, round) = (n % 26, n / 26);
+    let name = ((b'a' + letter as u8) as char).to_string();
+    if round == 0 {
+        name
+    } else {
+        format!("{}{}", name, round)
+    }
+}
+
+/// Polarity distinguishes describing a node as a value from describing it
+/// as a use, since a var reads differently from either side.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Polarity {
+    Positive,
+    Negative,
+}
+
+impl Polarity {
+    /// Returns the opposite polarity.
+    pub fn flip(self) -> Self {
+        match self {
+            Polarity::Positive => Polarity::Negative,
+            Polarity::Negative => Polarity::Positive,
+        }
+    }
+}
+
+/// TypeFolder builds a caller's own representation of a type while the flow
+/// graph is walked, such as a display type for a language's own syntax.
+/// Each method receives the already folded children of the part of the
+/// type it builds.
+pub trait TypeFolder<V, U> {
+    type Output;
+
+    /// Folds a var that no head reaches, given its display name.
+    fn var(&mut self, name: &str) -> Self::Output;
+
+    /// Folds a value head, with its children folded in the order returned
+    /// by `Describe::children`.
+    fn value(&mut self, head: &V, children: Vec<(Polarity, Self::Output)>) -> Self::Output;
+
+    /// Folds a use head, with its children folded in the order returned by
+    /// `Describe::children`.
+    fn use_head(&mut self, head: &U, children: Vec<(Polarity, Self::Output)>) -> Self::Output;
+
+    /// Folds the join of the value heads reaching a node, when more than one
+    /// does.
+    fn union(&mut self, members: Vec<Self::Output>) -> Self::Output;
+
+    /// Folds the meet of the use heads reached from a node, when more than
+    /// one is.
+    fn intersection(&mut self, members: Vec<Self::Output>) -> Self::Output;
+
+    /// Folds a type that refers back to itself through `reference`.
+    fn recursive(&mut self, name: &str, body: Self::Output) -> Self::Output;
+
+    /// Folds a reference back to an enclosing recursive type.
+    fn reference(&mut self, name: &str) -> Self::Output;
+}
+
+/// Descriptions folds a type into a TypeDescription, dropping duplicate
+/// members of unions and intersections. It is parameterised by the node
+/// index only so that it can fold heads describing themselves over it.
+struct Descriptions<I>(PhantomData<I>);
+
+impl<I> Descriptions<I> {
+    fn join(
+        members: Vec<TypeDescription>,
+        join: fn(Vec<TypeDescription>) -> TypeDescription,
+    ) -> TypeDescription {
+        let mut unique: Vec<TypeDescription> = Vec::new();
+        for member in members {
+            if !unique.contains(&member) {
+                unique.push(member);
+            }
+        }
+        match unique.len() {
+            1 => unique.pop().unwrap(),
+            _ => join(unique),
+        }
+    }
+}
+
+impl<V: Describe<I>, U: Describe<I>, I> TypeFolder<V, U> for Descriptions<I> {
+    type Output = TypeDescription;
+
+    fn var(&mut self, name: &str) -> TypeDescription {
+        TypeDescription::Var(name.to_string())
+    }
+
+    fn value(&mut self, head: &V, children: Vec<(Polarity, TypeDescription)>) -> TypeDescription {
+        TypeDescription::Head {
+            label: head.label(),
+            children,
+        }
+    }
+
+    fn use_head(
+        &mut self,
+        head: &U,
+        children: Vec<(Polarity, TypeDescription)>,
+    ) -> TypeDescription {
+        TypeDescription::Head {
+            label: head.label(),
+            children,
+        }
+    }
+
+    fn union(&mut self, members: Vec<TypeDescription>) -> TypeDescription {
+        Self::join(members, TypeDescription::Union)
+    }
+
+    fn intersection(&mut self, members: Vec<TypeDescription>) -> TypeDescription {
+        Self::join(members, TypeDescription::Intersection)
+    }
+
+    fn recursive(&mut self, name: &str, body: TypeDescription) -> TypeDescription {
+        TypeDescription::Recursive {
+            name: name.to_string(),
+            body: Box::new(body),
+        }
+    }
+
+    fn reference(&mut self, name: &str) -> TypeDescription {
+        TypeDescription::Var(name.to_string())
+    }
+}
+
+/// Describer carries the names handed out while describing a single type,
+/// so that every occurrence of a node is named consistently.
+struct Describer<'a, V, U, AT, F, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    checker: &'a TypeChecker<V, U, AT, I>,
+    folder: F,
+    names: usize,
+    vars: HashMap<EntityId<I>, String>,
+    recursive: HashMap<(EntityId<I>, Polarity), String>,
+    stack: Vec<(EntityId<I>, Polarity)>,
+}
+
+impl<'a, V, U, AT, F, I> Describer<'a, V, U, AT, F, I>
+where
+    I: NodeIndex,
+    V: Describe<I>,
+    U: Describe<I>,
+    AT: AbstractTypes<V, U, I>,
+    F: TypeFolder<V, U>,
+{
+    fn new(checker: &'a TypeChecker<V, U, AT, I>, folder: F) -> Self {
+        Self {
+            checker,
+            folder,
+            names: 0,
+            vars: HashMap::new(),
+            recursive: HashMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn fresh_name(&mut self) -> String {
+        self.names += 1;
+        nth_name(self.names - 1)
+    }
+
+    /// Returns the heads reaching the node in the given polarity: every head
+    /// of the values flowing into it, or of the uses it flows into.
+    fn heads(&self, id: EntityId<I>, polarity: Polarity) -> Vec<TypeNodeRef<'a, V, U>> {
+        let checker = self.checker;
+        let reaching: Vec<EntityId<I>> = match polarity {
+            Polarity::Positive => checker.r.upstream(id).collect(),
+            Polarity::Negative => checker.r.downstream(id).collect(),
+        };
+        core::iter::once(id)
+            .chain(reaching)
+            .flat_map(|id| -> Vec<TypeNodeRef<'a, V, U>> {
+                match polarity {
+                    Polarity::Positive => checker.value_heads(id).map(TypeNodeRef::Value).collect(),
+                    Polarity::Negative => checker.use_heads(id).map(TypeNodeRef::Use).collect(),
+                }
+            })
+            .collect()
+    }
+
+    fn describe(&mut self, id: EntityId<I>, polarity: Polarity) -> F::Output {
+        let id = self.checker.aliases.root(id);
+        let key = (id, polarity);
+        if self.stack.contains(&key) {
+            if !self.recursive.contains_key(&key) {
+                let name = self.fresh_name();
+                self.recursive.insert(key, name);
+            }
+            return self.folder.reference(&self.recursive[&key]);
+        }
+
+        let heads = self.heads(id, polarity);
+        if heads.is_empty() {
+            if let Some(name) = self.checker.var_names.get(&id) {
+                return self.folder.var(name);
+            }
+            if !self.vars.contains_key(&id) {
+                let name = self.fresh_name();
+                self.vars.insert(id, name);
+            }
+            return self.folder.var(&self.vars[&id]);
+        }
+
+        self.stack.push(key);
+        let mut members: Vec<F::Output> = heads
+            .into_iter()
+            .map(|head| self.describe_head(head))
+            .collect();
+        self.stack.pop();
+
+        let description = match (members.len(), polarity) {
+            (1, _) => members.pop().unwrap(),
+            (_, Polarity::Positive) => self.folder.union(members),
+            (_, Polarity::Negative) => self.folder.intersection(members),
+        };
+        match self.recursive.remove(&key) {
+            Some(name) => self.folder.recursive(&name, description),
+            None => description,
+        }
+    }
+
+    fn describe_head(&mut self, head: TypeNodeRef<'a, V, U>) -> F::Output {
+        let children = match head {
+            TypeNodeRef::Value(head) => head.children(),
+            TypeNodeRef::Use(head) => head.children(),
+            TypeNodeRef::Var => unreachable!("vars are never heads"),
+        };
+
+        let children = children
+            .into_iter()
+            .map(|child| match child {
+                Child::Value(value) => (
+                    Polarity::Positive,
+                    self.describe(value.0, Polarity::Positive),
+                ),
+                Child::Use(rhs) => (Polarity::Negative, self.describe(rhs.0, Polarity::Negative)),
+            })
+            .collect();
+        match head {
+            TypeNodeRef::Value(head) => self.folder.value(head, children),
+            TypeNodeRef::Use(head) => self.folder.use_head(head, children),
+            TypeNodeRef::Var => unreachable!("vars are never heads"),
+        }
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Describe<I>,
+    U: Describe<I>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Reconstructs the type of a value as the join of every value head
+    /// reaching it. Vars that no head reaches are shown with the name given
+    /// to them by a VarSupply, or else named in order of appearance, and a
+    /// type that reaches itself is folded into a recursive type rather than
+    /// unrolled.
+    pub fn describe(&self, value: Value<I>) -> TypeDescription {
+        self.fold_type(value, Descriptions(PhantomData))
+    }
+
+    /// Reconstructs the type of a use as the meet of every use head it
+    /// reaches, following the same naming as `describe`.
+    pub fn describe_use(&self, rhs: Use<I>) -> TypeDescription {
+        self.fold_use_type(rhs, Descriptions(PhantomData))
+    }
+
+    /// Reconstructs the type of a value like `describe`, but builds it with
+    /// the given folder instead of as a TypeDescription.
+    pub fn fold_type<F: TypeFolder<V, U>>(&self, value: Value<I>, folder: F) -> F::Output {
+        Describer::new(self, folder).describe(value.0, Polarity::Positive)
+    }
+
+    /// Reconstructs the type of a use like `describe_use`, but builds it
+    /// with the given folder instead of as a TypeDescription.
+    pub fn fold_use_type<F: TypeFolder<V, U>>(&self, rhs: Use<I>, folder: F) -> F::Output {
+        Describer::new(self, folder).describe(rhs.0, Polarity::Negative)
+    }
+
+    /// Describes a single use head on its own, ignoring anything else
+    /// reaching its node.
+    pub(crate) fn describe_use_head(&self, head: &U) -> TypeDescription {
+        Describer::new(self, Descriptions(PhantomData)).describe_head(TypeNodeRef::Use(head))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::{Head, HeadTypeSystem};
+
+    #[test]
+    fn describe_should_join_heads_and_name_vars() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let (x_val, x_use) = t.var();
+        let identity = t.new_val(Head::Func(x_use, x_val));
+        assert_eq!("fn(a, a)", t.describe(identity).to_string());
+
+        let (y_val, y_use) = t.var();
+        let int = t.new_val(Head::Int);
+        let boolean = t.new_val(Head::Bool);
+        assert!(t.flow(int, y_use).is_ok());
+        assert!(t.flow(boolean, y_use).is_ok());
+        assert_eq!("int | bool", t.describe(y_val).to_string());
+    }
+
+    #[test]
+    fn describe_should_join_every_head_of_a_node() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let int = t.new_val(Head::Int);
+        assert!(t.add_val_head(int, Head::Bool).is_ok());
+        assert_eq!("int | bool", t.describe(int).to_string());
+
+        let (x_val, x_use) = t.var();
+        assert!(t.flow(int, x_use).is_ok());
+        assert_eq!("int | bool", t.describe(x_val).to_string());
+    }
+
+    /// Sizes counts the heads in a type, for checking that folders are
+    /// handed every part of it.
+    struct Sizes;
+
+    impl TypeFolder<Head, Head> for Sizes {
+        type Output = usize;
+
+        fn var(&mut self, _: &str) -> usize {
+            0
+        }
+
+        fn value(&mut self, _: &Head, children: Vec<(Polarity, usize)>) -> usize {
+            1 + children.iter().map(|(_, size)| size).sum::<usize>()
+        }
+
+        fn use_head(&mut self, _: &Head, children: Vec<(Polarity, usize)>) -> usize {
+            1 + children.iter().map(|(_, size)| size).sum::<usize>()
+        }
+
+        fn union(&mut self, members: Vec<usize>) -> usize {
+            members.into_iter().sum()
+        }
+
+        fn intersection(&mut self, members: Vec<usize>) -> usize {
+            members.into_iter().sum()
+        }
+
+        fn recursive(&mut self, _: &str, body: usize) -> usize {
+            body
+        }
+
+        fn reference(&mut self, _: &str) -> usize {
+            0
+        }
+    }
+
+    #[test]
+    fn folders_should_build_their_own_types() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let (x_val, x_use) = t.var();
+        let int = t.new_val(Head::Int);
+        let boolean = t.new_val(Head::Bool);
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(boolean, x_use).is_ok());
+        let list = t.new_val(Head::List(x_val));
+        assert_eq!(3, t.fold_type(list, Sizes));
+
+        let (y_val, y_use) = t.var();
+        let nested = t.new_val(Head::List(y_val));
+        assert!(t.flow(nested, y_use).is_ok());
+        assert_eq!(1, t.fold_type(y_val, Sizes));
+    }
+
+    #[test]
+    fn describe_should_fold_recursive_types() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let (x_val, x_use) = t.var();
+        let list = t.new_val(Head::List(x_val));
+        assert!(t.flow(list, x_use).is_ok());
+
+        assert_eq!(
+            TypeDescription::Recursive {
+                name: "a".to_string(),
+                body: Box::new(TypeDescription::Head {
+                    label: "list".to_string(),
+                    children: vec![(Polarity::Positive, TypeDescription::Var("a".to_string()))],
+                }),
+            },
+            t.describe(x_val)
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, TypeNode};
+use core::fmt::Write;
+
+/// Escapes a string for use inside a quoted DOT identifier.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: core::fmt::Debug,
+    U: core::fmt::Debug,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Renders the flow graph in the Graphviz DOT format, with every node
+    /// labeled by its index and head, or its name for a named var, and every
+    /// edge of the closed graph drawn from value to use.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph flows {\n");
+        for (idx, node) in self.types.iter().enumerate() {
+            if self.r.is_removed(EntityId::at(idx)) {
+                continue;
+            }
+            let (head, shape) = match node {
+                TypeNode::Var => match self.var_names.get(&EntityId::at(idx)) {
+                    Some(name) => (format!("Var {}", name), "ellipse"),
+                    None => ("Var".to_string(), "ellipse"),
+                },
+                TypeNode::Value(head) => (format!("Value({:?})", head), "box"),
+                TypeNode::Use(head) => (format!("Use({:?})", head), "invhouse"),
+            };
+            let _ = writeln!(
+                dot,
+                "    n{} [label=\"{}: {}\", shape={}];",
+                idx,
+                idx,
+                escape(&head),
+                shape
+            );
+        }
+        for (lhs, rhs) in self.r.edges() {
+            let _ = writeln!(dot, "    n{} -> n{};", usize::from(lhs), usize::from(rhs));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::Permissive;
+    use crate::*;
+
+    #[test]
+    fn to_dot_should_render_nodes_and_edges() {
+        let mut t = TypeChecker::new(Permissive);
+        let (x_val, x_use) = t.var_named("x");
+        let int = t.new_val("int");
+        let sink = t.new_use("sink");
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+
+        assert_eq!(
+            "digraph flows {\n    \
+             n0 [label=\"0: Var x\", shape=ellipse];\n    \
+             n1 [label=\"1: Value(\\\"int\\\")\", shape=box];\n    \
+             n2 [label=\"2: Use(\\\"sink\\\")\", shape=invhouse];\n    \
+             n0 -> n2;\n    \
+             n1 -> n0;\n    \
+             n1 -> n2;\n\
+             }\n",
+            t.to_dot()
+        );
+    }
+}
@@ -0,0 +1,571 @@
+//! A compact binary encoding of a checker, for build caches where the size
+//! of JSON becomes a cost. Node indices are LEB128 varints, edge lists are
+//! delta-encoded against the node before, and heads are encoded by a
+//! `HeadCodec` supplied by the caller.
+//!
+//! The encoding keeps the nodes and their heads, the closed graph, merged
+//! vars, recorded flows, holes, forbidden flows, labels, var names and the
+//! graph configuration. Like serialization, it leaves out the type system,
+//! guarded flows, resource limits and work left over from a cancelled
+//! check. It also leaves out constraint groups and reported violations and
+//! warnings, so a decoded checker starts without them.
+
+use crate::prelude::*;
+use crate::{
+    AbstractTypes, EntityId, GraphConfig, NodeIndex, RecordedFlow, TypeChecker, TypeNode, Use,
+    Value, WorklistOrder,
+};
+use std::io::{self, Read, Write};
+
+/// Pairs of nodes, such as the edges of the graph.
+type Pairs<I> = Vec<(EntityId<I>, EntityId<I>)>;
+
+const MAGIC: &[u8; 4] = b"TYPC";
+const VERSION: u64 = 1;
+
+/// HeadCodec encodes the heads of a checker, writing any handles they hold
+/// with `Encoder::value` and `Encoder::constraint` so that they are checked
+/// when decoded.
+pub trait HeadCodec<V, U> {
+    fn encode_value(&self, head: &V, out: &mut Encoder);
+    fn encode_use(&self, head: &U, out: &mut Encoder);
+    fn decode_value(&self, input: &mut Decoder<'_>) -> Result<V, DecodeError>;
+    fn decode_use(&self, input: &mut Decoder<'_>) -> Result<U, DecodeError>;
+}
+
+/// DecodeError enumerates the ways decoding a checker can fail.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// Reading the input failed.
+    Io(io::Error),
+    /// The input doesn't start with the encoding's magic bytes.
+    BadMagic,
+    /// The input was encoded by an unsupported version of the encoding.
+    UnsupportedVersion(u64),
+    /// The input ended in the middle of an item.
+    Truncated,
+    /// The input holds something the encoding can't, such as a handle to a
+    /// node that doesn't exist.
+    Malformed(&'static str),
+}
+
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read the encoding: {}.", error),
+            Self::BadMagic => write!(f, "input isn't an encoded checker."),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported encoding version {}.", version)
+            }
+            Self::Truncated => write!(f, "encoding ends early."),
+            Self::Malformed(what) => write!(f, "malformed encoding: {}.", what),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Encoder collects the bytes of an encoded checker.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    /// Writes an integer as a LEB128 varint, taking a byte per seven bits.
+    pub fn varint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.buf.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.buf.push(n as u8);
+    }
+
+    /// Writes bytes along with their length.
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.varint(bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Writes a string along with its length.
+    pub fn str(&mut self, s: &str) {
+        self.bytes(s.as_bytes());
+    }
+
+    /// Writes a value handle.
+    pub fn value<I: NodeIndex>(&mut self, value: Value<I>) {
+        self.id(value.0);
+    }
+
+    /// Writes a use handle.
+    pub fn constraint<I: NodeIndex>(&mut self, rhs: Use<I>) {
+        self.id(rhs.0);
+    }
+
+    fn id<I: NodeIndex>(&mut self, id: EntityId<I>) {
+        self.varint(usize::from(id) as u64);
+    }
+
+    /// Writes a signed integer as a zigzag varint, so small deltas either
+    /// way take a single byte.
+    fn delta<I: NodeIndex>(&mut self, from: EntityId<I>, to: EntityId<I>) {
+        let delta = usize::from(to) as i64 - usize::from(from) as i64;
+        self.varint(((delta << 1) ^ (delta >> 63)) as u64);
+    }
+
+    /// Writes pairs of nodes, each node a delta from the one before.
+    fn pairs<I: NodeIndex>(
+        &mut self,
+        pairs: impl ExactSizeIterator<Item = (EntityId<I>, EntityId<I>)>,
+    ) {
+        self.varint(pairs.len() as u64);
+        let mut prev = EntityId::at(0);
+        for (lhs, rhs) in pairs {
+            self.delta(prev, lhs);
+            self.delta(lhs, rhs);
+            prev = lhs;
+        }
+    }
+
+    fn node<V, U>(&mut self, codec: &impl HeadCodec<V, U>, node: &TypeNode<V, U>) {
+        match node {
+            TypeNode::Var => self.buf.push(0),
+            TypeNode::Value(head) => {
+                self.buf.push(1);
+                codec.encode_value(head, self);
+            }
+            TypeNode::Use(head) => {
+                self.buf.push(2);
+                codec.encode_use(head, self);
+            }
+        }
+    }
+}
+
+/// Decoder reads back the bytes of an encoded checker.
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    input: &'a [u8],
+    /// the number of nodes handles may refer to.
+    nodes: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Reads an integer written by `Encoder::varint`.
+    pub fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(DecodeError::Malformed("varint too long"))
+    }
+
+    /// Reads bytes written by `Encoder::bytes`.
+    pub fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.varint()? as usize;
+        if len > self.input.len() {
+            return Err(DecodeError::Truncated);
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    /// Reads a string written by `Encoder::str`.
+    pub fn str(&mut self) -> Result<&'a str, DecodeError> {
+        core::str::from_utf8(self.bytes()?).map_err(|_| DecodeError::Malformed("invalid utf-8"))
+    }
+
+    /// Reads a value handle, checking that its node exists.
+    pub fn value<I: NodeIndex>(&mut self) -> Result<Value<I>, DecodeError> {
+        let id = self.varint()?;
+        Ok(Value(self.check(id)?))
+    }
+
+    /// Reads a use handle, checking that its node exists.
+    pub fn constraint<I: NodeIndex>(&mut self) -> Result<Use<I>, DecodeError> {
+        let id = self.varint()?;
+        Ok(Use(self.check(id)?))
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let (&byte, rest) = self.input.split_first().ok_or(DecodeError::Truncated)?;
+        self.input = rest;
+        Ok(byte)
+    }
+
+    /// Reads the number of items that follow. Every item takes at least a
+    /// byte, so counts beyond the remaining input are rejected before
+    /// anything is allocated for them.
+    fn count(&mut self) -> Result<usize, DecodeError> {
+        let count = self.varint()?;
+        if count > self.input.len() as u64 {
+            return Err(DecodeError::Truncated);
+        }
+        Ok(count as usize)
+    }
+
+    fn check<I: NodeIndex>(&self, id: u64) -> Result<EntityId<I>, DecodeError> {
+        if id < self.nodes as u64 {
+            Ok(EntityId::at(id as usize))
+        } else {
+            Err(DecodeError::Malformed(
+                "handle to a node that doesn't exist",
+            ))
+        }
+    }
+
+    fn id<I: NodeIndex>(&mut self) -> Result<EntityId<I>, DecodeError> {
+        let id = self.varint()?;
+        self.check(id)
+    }
+
+    fn delta<I: NodeIndex>(&mut self, from: EntityId<I>) -> Result<EntityId<I>, DecodeError> {
+        let zigzag = self.varint()?;
+        let delta = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+        let id = (usize::from(from) as i64).wrapping_add(delta);
+        self.check(id as u64)
+    }
+
+    fn pairs<I: NodeIndex>(&mut self) -> Result<Pairs<I>, DecodeError> {
+        let count = self.count()?;
+        let mut pairs = Vec::with_capacity(count);
+        let mut prev = EntityId::at(0);
+        for _ in 0..count {
+            let lhs = self.delta(prev)?;
+            let rhs = self.delta(lhs)?;
+            pairs.push((lhs, rhs));
+            prev = lhs;
+        }
+        Ok(pairs)
+    }
+
+    fn node<V, U>(&mut self, codec: &impl HeadCodec<V, U>) -> Result<TypeNode<V, U>, DecodeError> {
+        match self.byte()? {
+            0 => Ok(TypeNode::Var),
+            1 => codec.decode_value(self).map(TypeNode::Value),
+            2 => codec.decode_use(self).map(TypeNode::Use),
+            _ => Err(DecodeError::Malformed("unknown node kind")),
+        }
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Writes the checker in the binary encoding, with heads encoded by the
+    /// codec.
+    pub fn encode(&self, codec: &impl HeadCodec<V, U>, out: &mut impl Write) -> io::Result<()> {
+        let mut enc = Encoder::default();
+        enc.buf.extend_from_slice(MAGIC);
+        enc.varint(VERSION);
+
+        let config = &self.graph_config;
+        enc.varint(u64::from(config.dense));
+        enc.varint(u64::from(config.skip_self_edges));
+        enc.varint(config.max_out_degree.map_or(0, |max| max as u64 + 1));
+        enc.varint(match config.worklist_order {
+            WorklistOrder::Lifo => 0,
+            WorklistOrder::Fifo => 1,
+        });
+        enc.varint(u64::from(config.queue_duplicates));
+
+        enc.varint(self.types.len() as u64);
+        for node in &self.types {
+            enc.node(codec, node);
+        }
+        for id in (0..self.types.len()).map(EntityId::at) {
+            enc.delta(id, self.aliases.root(id));
+        }
+        let free = self.r.free_nodes();
+        enc.varint(free.len() as u64);
+        for &id in free {
+            enc.id(id);
+        }
+        for lhs in (0..self.types.len()).map(EntityId::at) {
+            enc.varint(self.r.out_degree(lhs) as u64);
+            let mut prev = lhs;
+            for rhs in self.r.downstream(lhs) {
+                enc.delta(prev, rhs);
+                prev = rhs;
+            }
+        }
+        enc.pairs(self.direct.iter().copied());
+        enc.pairs(self.flows.iter().map(|flow| (flow.lhs.0, flow.rhs.0)));
+
+        // extra heads are written in the order they were added, so that
+        // decoding can add them again in the same order.
+        enc.varint(self.added_heads.len() as u64);
+        let mut written: crate::collections::HashMap<EntityId<I>, usize> = Default::default();
+        for &id in &self.added_heads {
+            let idx = written.entry(id).or_insert(0);
+            enc.id(id);
+            enc.node(codec, &self.extra_heads[&id][*idx]);
+            *idx += 1;
+        }
+
+        enc.varint(self.holes.len() as u64);
+        for &hole in &self.holes {
+            enc.constraint(hole);
+        }
+        enc.pairs(self.forbidden.iter().map(|&(lhs, rhs)| (lhs.0, rhs.0)));
+        for names in [&self.labels, &self.var_names] {
+            let mut names: Vec<_> = names.iter().collect();
+            names.sort();
+            enc.varint(names.len() as u64);
+            for (&id, name) in names {
+                enc.id(id);
+                enc.str(name);
+            }
+        }
+        let mut named: Vec<_> = self.named_vars.iter().collect();
+        named.sort();
+        enc.varint(named.len() as u64);
+        for (name, &id) in named {
+            enc.str(name);
+            enc.id(id);
+        }
+        let mut unrefined: Vec<_> = self.unrefined.iter().collect();
+        unrefined.sort_by_key(|&(&id, _)| id);
+        enc.varint(unrefined.len() as u64);
+        for (&id, head) in unrefined {
+            enc.id(id);
+            codec.encode_value(head, &mut enc);
+        }
+        let mut meet_nodes: Vec<EntityId<I>> = self.meet_nodes.iter().copied().collect();
+        meet_nodes.sort_unstable();
+        enc.varint(meet_nodes.len() as u64);
+        for id in meet_nodes {
+            enc.id(id);
+        }
+        enc.varint(self.meets as u64);
+        out.write_all(&enc.buf)
+    }
+
+    /// Reads back a checker written by `encode`, meeting its heads with the
+    /// given type system from then on.
+    pub fn decode(
+        abstract_type_mapper: AT,
+        codec: &impl HeadCodec<V, U>,
+        input: &mut impl Read,
+    ) -> Result<Self, DecodeError> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        if !bytes.starts_with(MAGIC) {
+            return Err(DecodeError::BadMagic);
+        }
+        let mut dec = Decoder {
+            input: &bytes[MAGIC.len()..],
+            nodes: 0,
+        };
+        match dec.varint()? {
+            VERSION => {}
+            version => return Err(DecodeError::UnsupportedVersion(version)),
+        }
+
+        let config = GraphConfig {
+            dense: dec.varint()? != 0,
+            skip_self_edges: dec.varint()? != 0,
+            max_out_degree: match dec.varint()? {
+                0 => None,
+                max => Some(max as usize - 1),
+            },
+            worklist_order: match dec.varint()? {
+                0 => WorklistOrder::Lifo,
+                1 => WorklistOrder::Fifo,
+                _ => return Err(DecodeError::Malformed("unknown worklist order")),
+            },
+            queue_duplicates: dec.varint()? != 0,
+        };
+        let mut t = Self::new_with_index(abstract_type_mapper, config);
+
+        dec.nodes = dec.count()?;
+        for _ in 0..dec.nodes {
+            let node = dec.node(codec)?;
+            t.add_node(node);
+        }
+        for id in (0..dec.nodes).map(EntityId::at) {
+            let root = dec.delta(id)?;
+            if root != id {
+                // every node is merged into its root once, so the root
+                // keeps the higher rank and stays the representative.
+                t.aliases.union(root, id);
+            }
+        }
+        for _ in 0..dec.count()? {
+            let id = dec.id()?;
+            t.r.remove_node_mut(id);
+        }
+        for lhs in (0..dec.nodes).map(EntityId::at) {
+            let mut prev = lhs;
+            for _ in 0..dec.count()? {
+                let rhs = dec.delta(prev)?;
+                t.r.add_closed_edge_mut(lhs, rhs);
+                prev = rhs;
+            }
+        }
+        t.direct = crate::collections::Shared::new(dec.pairs()?);
+        t.flows = dec
+            .pairs()?
+            .into_iter()
+            .map(|(lhs, rhs)| RecordedFlow {
+                lhs: Value(lhs),
+                rhs: Use(rhs),
+                group: None,
+            })
+            .collect();
+        t.sync_recorded();
+
+        for _ in 0..dec.count()? {
+            let id = dec.id()?;
+            let head = dec.node(codec)?;
+            t.extra_heads.entry(id).or_default().push(head);
+            t.added_heads.push(id);
+        }
+
+        for _ in 0..dec.count()? {
+            let hole = dec.constraint()?;
+            t.holes.push(hole);
+        }
+        t.forbidden = dec
+            .pairs()?
+            .into_iter()
+            .map(|(lhs, rhs)| (Value(lhs), Use(rhs)))
+            .collect();
+        for names in [&mut t.labels, &mut t.var_names] {
+            for _ in 0..dec.count()? {
+                let id = dec.id()?;
+                names.insert(id, dec.str()?.to_string());
+            }
+        }
+        for _ in 0..dec.count()? {
+            let name = dec.str()?.to_string();
+            t.named_vars.insert(name, dec.id()?);
+        }
+        for _ in 0..dec.count()? {
+            let id = dec.id()?;
+            let head = codec.decode_value(&mut dec)?;
+            t.unrefined.insert(id, head);
+        }
+        for _ in 0..dec.count()? {
+            let id = dec.id()?;
+            t.meet_nodes.insert(id);
+        }
+        t.meets = dec.varint()? as usize;
+        if !dec.input.is_empty() {
+            return Err(DecodeError::Malformed("trailing bytes"));
+        }
+        Ok(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::Strict;
+    use crate::TypeError;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Head {
+        Int,
+        List(Value),
+    }
+
+    struct Codec;
+
+    impl HeadCodec<Head, Head> for Codec {
+        fn encode_value(&self, head: &Head, out: &mut Encoder) {
+            match head {
+                Head::Int => out.varint(0),
+                Head::List(elem) => {
+                    out.varint(1);
+                    out.value(*elem);
+                }
+            }
+        }
+
+        fn encode_use(&self, head: &Head, out: &mut Encoder) {
+            self.encode_value(head, out);
+        }
+
+        fn decode_value(&self, input: &mut Decoder<'_>) -> Result<Head, DecodeError> {
+            match input.varint()? {
+                0 => Ok(Head::Int),
+                1 => Ok(Head::List(input.value()?)),
+                _ => Err(DecodeError::Malformed("unknown head")),
+            }
+        }
+
+        fn decode_use(&self, input: &mut Decoder<'_>) -> Result<Head, DecodeError> {
+            self.decode_value(input)
+        }
+    }
+
+    #[test]
+    fn decoded_checkers_should_match_the_encoded_ones() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Head::Int);
+        let (x_val, x_use) = t.var_named("x");
+        let list = t.new_val(Head::List(x_val));
+        let sink = t.new_use(Head::Int);
+        t.label_value(list, "[x]");
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+        assert!(t.add_use_head(sink, Head::Int).is_ok());
+
+        let mut bytes = Vec::new();
+        assert!(t.encode(&Codec, &mut bytes).is_ok());
+        let mut decoded = TypeChecker::decode(Strict, &Codec, &mut bytes.as_slice()).unwrap();
+        assert!(t.snapshot().diff(&decoded.snapshot()).is_empty());
+        assert!(decoded.snapshot().diff(&t.snapshot()).is_empty());
+        assert_eq!(Some("x"), decoded.name_of(x_val));
+        assert_eq!(t.labels, decoded.labels);
+
+        let mut encoded_again = Vec::new();
+        assert!(decoded.encode(&Codec, &mut encoded_again).is_ok());
+        assert_eq!(bytes, encoded_again);
+
+        let list_use = decoded.new_use(Head::Int);
+        assert_eq!(Err(TypeError::Converge), decoded.flow(list, list_use));
+    }
+
+    #[test]
+    fn malformed_encodings_should_be_rejected() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Head::Int);
+        let sink = t.new_use(Head::Int);
+        assert!(t.flow(int, sink).is_ok());
+        let mut bytes = Vec::new();
+        assert!(t.encode(&Codec, &mut bytes).is_ok());
+
+        let decode = |bytes: &[u8]| TypeChecker::decode(Strict, &Codec, &mut &bytes[..]);
+        assert!(matches!(decode(b"JSON"), Err(DecodeError::BadMagic)));
+        assert!(matches!(
+            decode(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::Truncated)
+        ));
+        let mut dangling = bytes.clone();
+        // the head of the first node, an int, becomes a list of node 9.
+        dangling.splice(11..12, [1, 9]);
+        assert!(matches!(decode(&dangling), Err(DecodeError::Malformed(_))));
+    }
+}
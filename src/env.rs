@@ -0,0 +1,147 @@
+//! A scoped symbol table for frontends, mapping names to the nodes or
+//! schemes bound to them.
+
+use crate::prelude::*;
+use crate::{Use, Value};
+
+/// Binding is what a name in an environment refers to: the two sides of a
+/// monomorphic var, or a scheme to be instantiated at each use.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding<S> {
+    Var(Value, Use),
+    Scheme(S),
+}
+
+#[derive(Debug, Clone)]
+struct Scope<S> {
+    bindings: Vec<(String, Binding<S>)>,
+    /// whether the scope is the body of a closure, which records the names
+    /// it refers to from enclosing scopes.
+    closure: bool,
+    captures: Vec<String>,
+}
+
+impl<S> Scope<S> {
+    fn new(closure: bool) -> Self {
+        Self {
+            bindings: Vec::new(),
+            closure,
+            captures: Vec::new(),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&Binding<S>> {
+        self.bindings
+            .iter()
+            .rev()
+            .find(|(bound, _)| bound == name)
+            .map(|(_, binding)| binding)
+    }
+}
+
+/// Env is a stack of scopes, where a name resolves to its innermost
+/// binding. Rebinding a name, in the same scope or a nested one, shadows the
+/// earlier binding until the scope holding the new one is popped.
+#[derive(Debug, Clone)]
+pub struct Env<S> {
+    scopes: Vec<Scope<S>>,
+}
+
+impl<S> Default for Env<S> {
+    fn default() -> Self {
+        Self {
+            scopes: vec![Scope::new(false)],
+        }
+    }
+}
+
+impl<S> Env<S> {
+    /// Creates an environment holding a single, outermost scope.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of scopes, including the outermost one.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Enters a new scope, such as a block.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Scope::new(false))
+    }
+
+    /// Enters the scope of a closure body, which records every name it
+    /// resolves from an enclosing scope.
+    pub fn push_closure_scope(&mut self) {
+        self.scopes.push(Scope::new(true))
+    }
+
+    /// Leaves the innermost scope, returning the names it captured from
+    /// enclosing scopes in the order they were first resolved. Only closure
+    /// scopes capture names.
+    ///
+    /// Panics if only the outermost scope remains.
+    pub fn pop_scope(&mut self) -> Vec<String> {
+        assert!(self.scopes.len() > 1, "the outermost scope can't be popped");
+        self.scopes.pop().unwrap().captures
+    }
+
+    /// Binds a name in the innermost scope.
+    pub fn bind(&mut self, name: impl Into<String>, binding: Binding<S>) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .bindings
+            .push((name.into(), binding))
+    }
+
+    /// Returns the innermost binding of a name without recording a capture.
+    pub fn get(&self, name: &str) -> Option<&Binding<S>> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Returns the innermost binding of a name, recording it as a capture of
+    /// every closure scope between the innermost scope and the one it was
+    /// bound in.
+    pub fn resolve(&mut self, name: &str) -> Option<&Binding<S>> {
+        let bound_in = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.get(name).is_some())?;
+
+        for scope in self.scopes[bound_in + 1..].iter_mut() {
+            if scope.closure && !scope.captures.iter().any(|c| c == name) {
+                scope.captures.push(name.to_string());
+            }
+        }
+        self.scopes[bound_in].get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::Permissive;
+    use crate::TypeChecker;
+
+    #[test]
+    fn env_should_shadow_and_capture_bindings() {
+        let mut t: TypeChecker<(), (), _> = TypeChecker::new(Permissive);
+        let (x, y) = (t.var(), t.var());
+
+        let mut env: Env<()> = Env::new();
+        env.bind("x", Binding::Var(x.0, x.1));
+        env.bind("y", Binding::Var(y.0, y.1));
+
+        env.push_closure_scope();
+        env.bind("y", Binding::Scheme(()));
+        assert_eq!(Some(&Binding::Var(x.0, x.1)), env.resolve("x"));
+        assert_eq!(Some(&Binding::Scheme(())), env.resolve("y"));
+        assert_eq!(None, env.resolve("z"));
+        assert_eq!(vec!["x".to_string()], env.pop_scope());
+
+        assert_eq!(Some(&Binding::Var(y.0, y.1)), env.get("y"));
+        assert_eq!(1, env.depth());
+    }
+}
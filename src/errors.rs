@@ -0,0 +1,108 @@
+use crate::prelude::*;
+use crate::EntityId;
+
+/// TypeError enumerates the failures reported by the checkers in this crate.
+/// The set of variants is expected to grow, so matches against it must
+/// include a wildcard arm.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TypeError {
+    /// Two types could not be converged.
+    Converge,
+    /// Two entities were constructed from differing numbers of children.
+    /// `position` holds the child indices leading from the unified entities
+    /// to the mismatch.
+    ArityMismatch {
+        expected: usize,
+        found: usize,
+        position: Vec<usize>,
+    },
+    /// A variable was unified with a type containing itself. Holds the chain
+    /// of entities leading from the type back to the variable.
+    InfiniteType(Vec<EntityId>),
+    /// Two types of different kinds, for example a type and an effect, were
+    /// related to each other.
+    KindMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// A configured resource limit was exceeded.
+    LimitExceeded {
+        resource: &'static str,
+        limit: usize,
+    },
+    /// A handle did not refer to a node of the checker it was passed to.
+    HandleInvalid { index: usize },
+    /// A single flow met more pairs of heads than the configured budget,
+    /// as happens when meets keep creating ever larger heads.
+    Diverged { meets: usize },
+}
+
+impl core::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Converge => write!(f, "unable to converge types."),
+            Self::ArityMismatch {
+                expected,
+                found,
+                position,
+            } => write!(
+                f,
+                "expected arity of {} but found {} at position {:?}.",
+                expected, found, position
+            ),
+            Self::InfiniteType(chain) => write!(
+                f,
+                "infinite type through entities {}.",
+                chain
+                    .iter()
+                    .map(|id| format!("{}", usize::from(*id)))
+                    .collect::<Vec<String>>()
+                    .join(" -> ")
+            ),
+            Self::KindMismatch { expected, found } => {
+                write!(f, "expected kind {} but found {}.", expected, found)
+            }
+            Self::LimitExceeded { resource, limit } => {
+                write!(f, "exceeded limit of {} {}.", limit, resource)
+            }
+            Self::HandleInvalid { index } => {
+                write!(f, "handle {} does not belong to this checker.", index)
+            }
+            Self::Diverged { meets } => {
+                write!(
+                    f,
+                    "checking diverged after meeting {} pairs of heads.",
+                    meets
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TypeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_display_errors_as_messages() {
+        assert_eq!("unable to converge types.", TypeError::Converge.to_string());
+        assert_eq!(
+            "infinite type through entities 0 -> 2.",
+            TypeError::InfiniteType(vec![EntityId::at(0), EntityId::at(2)]).to_string()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn errors_should_box_as_std_errors() {
+        let boxed: Box<dyn std::error::Error> = Box::new(TypeError::LimitExceeded {
+            resource: "nodes",
+            limit: 8,
+        });
+        assert_eq!("exceeded limit of 8 nodes.", boxed.to_string());
+    }
+}
@@ -0,0 +1,304 @@
+//! A C ABI over a checker whose heads are opaque `u32` tags, for driving
+//! the solver from other languages, such as a TypeScript playground loading
+//! the crate as WASM. Enabled by the `ffi` feature, and built for linking
+//! with `cargo rustc --features ffi --crate-type cdylib`.
+//!
+//! Nodes are referred to by their raw index, with a var's index standing
+//! for both of its sides. Heads are met by a callback supplied by the host,
+//! which returns 0 if the heads agree or a positive error code of its own
+//! choosing if they don't. A callback relating the children of two heads,
+//! which the host tracks alongside their tags, adds flows between them with
+//! `typical_push_flow`.
+//!
+//! Negative codes are reserved for the wrapper itself:
+//! `TYPICAL_INVALID_HANDLE` for an index that isn't a node of the right
+//! kind, `TYPICAL_NULL` for a null checker and `TYPICAL_RESERVED_CODE` for
+//! a callback that failed with a negative code.
+
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, FlowError, TypeChecker, Use, Value};
+use core::ffi::c_void;
+
+/// Returned when an index isn't a node of the kind the call needs.
+pub const TYPICAL_INVALID_HANDLE: i32 = -1;
+/// Returned when the checker passed is null.
+pub const TYPICAL_NULL: i32 = -2;
+/// Returned when the meet callback fails with a negative code, as those are
+/// reserved for the wrapper.
+pub const TYPICAL_RESERVED_CODE: i32 = -3;
+
+/// MeetFn meets the tags of a value and a use, adding any flows between
+/// their children to `flows` and returning 0 if they agree or a positive
+/// error code if they don't.
+pub type MeetFn =
+    extern "C" fn(data: *mut c_void, value: u32, constraint: u32, flows: *mut Flows) -> i32;
+
+/// Flows collects the flows a meet callback adds between existing nodes.
+#[derive(Debug)]
+pub struct Flows<'a> {
+    kinds: &'a [Kind],
+    flows: Vec<(Value, Use)>,
+}
+
+/// Kind is what a node handed to the host is, deciding which sides of a
+/// flow it can be on, as `value_at` and `use_at` do for the checker.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Value,
+    Use,
+    Var,
+}
+
+/// Callback meets tags through the host's function.
+#[derive(Debug)]
+pub struct Callback {
+    meet: MeetFn,
+    data: *mut c_void,
+    /// the kind of every node handed to the host, as the checker can't be
+    /// asked while it is meeting heads.
+    kinds: Vec<Kind>,
+}
+
+impl AbstractTypes<u32, u32> for Callback {
+    type Error = i32;
+
+    fn meet(&self, lhs: &u32, rhs: &u32) -> Result<Vec<(Value, Use)>, Self::Error> {
+        let mut flows = Flows {
+            kinds: &self.kinds,
+            flows: Vec::new(),
+        };
+        match (self.meet)(self.data, *lhs, *rhs, &mut flows) {
+            0 => Ok(flows.flows),
+            code if code < 0 => Err(TYPICAL_RESERVED_CODE),
+            code => Err(code),
+        }
+    }
+}
+
+/// Checker is a checker over tags, along with the failure of the last flow
+/// that failed.
+#[derive(Debug)]
+pub struct Checker {
+    checker: TypeChecker<u32, u32, Callback>,
+    failure: Option<FlowError<i32>>,
+}
+
+impl Checker {
+    /// Records the kind of a node just created, returning its index. Nodes
+    /// are only ever created by the wrapper, and never removed, so they are
+    /// numbered in the order they are recorded.
+    fn created(&mut self, id: usize, kind: Kind) -> i32 {
+        let kinds = &mut self.checker.abstract_type_mapper.kinds;
+        debug_assert_eq!(kinds.len(), id);
+        kinds.push(kind);
+        id as i32
+    }
+}
+
+/// Creates a checker meeting heads through the callback, which is passed
+/// `data` on every call. The checker is freed with `typical_checker_free`.
+#[no_mangle]
+pub extern "C" fn typical_checker_new(meet: MeetFn, data: *mut c_void) -> *mut Checker {
+    let callback = Callback {
+        meet,
+        data,
+        kinds: Vec::new(),
+    };
+    Box::into_raw(Box::new(Checker {
+        checker: TypeChecker::new(callback),
+        failure: None,
+    }))
+}
+
+/// Frees a checker created by `typical_checker_new`.
+///
+/// # Safety
+///
+/// The checker must have come from `typical_checker_new` and not already
+/// been freed. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn typical_checker_free(checker: *mut Checker) {
+    if !checker.is_null() {
+        drop(Box::from_raw(checker));
+    }
+}
+
+/// Adds a value with the tag as its head, returning its index.
+///
+/// # Safety
+///
+/// The checker must be a live checker from `typical_checker_new`.
+#[no_mangle]
+pub unsafe extern "C" fn typical_new_val(checker: *mut Checker, tag: u32) -> i32 {
+    with_checker(checker, |c| {
+        let id = c.checker.new_val(tag).index();
+        c.created(id, Kind::Value)
+    })
+}
+
+/// Adds a use with the tag as its head, returning its index.
+///
+/// # Safety
+///
+/// The checker must be a live checker from `typical_checker_new`.
+#[no_mangle]
+pub unsafe extern "C" fn typical_new_use(checker: *mut Checker, tag: u32) -> i32 {
+    with_checker(checker, |c| {
+        let id = c.checker.new_use(tag).index();
+        c.created(id, Kind::Use)
+    })
+}
+
+/// Adds a var, returning the index standing for both of its sides.
+///
+/// # Safety
+///
+/// The checker must be a live checker from `typical_checker_new`.
+#[no_mangle]
+pub unsafe extern "C" fn typical_var(checker: *mut Checker) -> i32 {
+    with_checker(checker, |c| {
+        let id = c.checker.var().0.index();
+        c.created(id, Kind::Var)
+    })
+}
+
+/// Adds a flow from a value or var into a use or var, returning 0 if it
+/// checks or the error code it failed with.
+///
+/// # Safety
+///
+/// The checker must be a live checker from `typical_checker_new`.
+#[no_mangle]
+pub unsafe extern "C" fn typical_flow(checker: *mut Checker, value: u32, constraint: u32) -> i32 {
+    with_checker(checker, |c| {
+        let handles = (
+            c.checker.value_at(value as usize),
+            c.checker.use_at(constraint as usize),
+        );
+        let (lhs, rhs) = match handles {
+            (Ok(lhs), Ok(rhs)) => (lhs, rhs),
+            _ => return TYPICAL_INVALID_HANDLE,
+        };
+        match c.checker.flow_traced(lhs, rhs) {
+            Ok(()) => 0,
+            Err(failure) => {
+                let code = failure.error;
+                c.failure = Some(failure);
+                code
+            }
+        }
+    })
+}
+
+/// Returns the error code of the last flow that failed, or 0 if none has,
+/// writing the indices of the value and use whose heads couldn't be met
+/// through the pointers that aren't null.
+///
+/// # Safety
+///
+/// The checker must be a live checker from `typical_checker_new`, and the
+/// pointers must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn typical_last_error(
+    checker: *mut Checker,
+    value: *mut u32,
+    constraint: *mut u32,
+) -> i32 {
+    with_checker(checker, |c| match &c.failure {
+        None => 0,
+        Some(failure) => {
+            if !value.is_null() {
+                *value = failure.lhs.index() as u32;
+            }
+            if !constraint.is_null() {
+                *constraint = failure.rhs.index() as u32;
+            }
+            failure.error
+        }
+    })
+}
+
+/// Adds a flow between two nodes from inside a meet callback, returning 0
+/// or `TYPICAL_INVALID_HANDLE` if the value isn't a value or var or the use
+/// isn't a use or var.
+///
+/// # Safety
+///
+/// The flows must be the pointer passed to the running meet callback.
+#[no_mangle]
+pub unsafe extern "C" fn typical_push_flow(flows: *mut Flows, value: u32, constraint: u32) -> i32 {
+    let flows = match flows.as_mut() {
+        Some(flows) => flows,
+        None => return TYPICAL_NULL,
+    };
+    let kind = |index: u32| flows.kinds.get(index as usize).copied();
+    match (kind(value), kind(constraint)) {
+        (Some(Kind::Value), Some(Kind::Use))
+        | (Some(Kind::Value), Some(Kind::Var))
+        | (Some(Kind::Var), Some(Kind::Use))
+        | (Some(Kind::Var), Some(Kind::Var)) => {}
+        _ => return TYPICAL_INVALID_HANDLE,
+    }
+    let lhs = Value(EntityId::at(value as usize));
+    let rhs = Use(EntityId::at(constraint as usize));
+    flows.flows.push((lhs, rhs));
+    0
+}
+
+/// Runs a call against a checker, or returns `TYPICAL_NULL` if it is null.
+unsafe fn with_checker(checker: *mut Checker, call: impl FnOnce(&mut Checker) -> i32) -> i32 {
+    match checker.as_mut() {
+        Some(checker) => call(checker),
+        None => TYPICAL_NULL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ptr;
+
+    /// Tag 0 is an int, and tag `n + 1` is a list whose elements the host
+    /// keeps at index `n`.
+    extern "C" fn meet(_: *mut c_void, value: u32, constraint: u32, flows: *mut Flows) -> i32 {
+        match (value, constraint) {
+            (0, 0) => 0,
+            (0, _) | (_, 0) => 7,
+            (value, constraint) => unsafe { typical_push_flow(flows, value - 1, constraint - 1) },
+        }
+    }
+
+    #[test]
+    fn tagged_checkers_should_report_failed_flows() {
+        unsafe {
+            let c = typical_checker_new(meet, ptr::null_mut());
+            let int = typical_new_val(c, 0) as u32;
+            let x = typical_var(c) as u32;
+            let value_list = typical_new_val(c, int + 1) as u32;
+            let use_list = typical_new_use(c, x + 1) as u32;
+            let int_use = typical_new_use(c, 0) as u32;
+            assert_eq!(0, typical_flow(c, value_list, use_list));
+            assert_eq!(0, typical_flow(c, x, int_use));
+            assert_eq!(0, typical_last_error(c, ptr::null_mut(), ptr::null_mut()));
+
+            assert_eq!(7, typical_flow(c, value_list, int_use));
+            let (mut lhs, mut rhs) = (0, 0);
+            assert_eq!(7, typical_last_error(c, &mut lhs, &mut rhs));
+            assert_eq!((value_list, int_use), (lhs, rhs));
+            assert_eq!(TYPICAL_INVALID_HANDLE, typical_flow(c, int_use, x));
+
+            // a list of uses can't hand its element to a flow as a value, and
+            // the callback's negative code is reported as reserved.
+            let uses_list = typical_new_val(c, int_use + 1) as u32;
+            assert_eq!(TYPICAL_RESERVED_CODE, typical_flow(c, uses_list, use_list));
+            let mut flows = Flows {
+                kinds: &[Kind::Use, Kind::Var],
+                flows: Vec::new(),
+            };
+            assert_eq!(TYPICAL_INVALID_HANDLE, typical_push_flow(&mut flows, 0, 1));
+            assert_eq!(0, typical_push_flow(&mut flows, 1, 0));
+            assert_eq!(TYPICAL_NULL, typical_var(ptr::null_mut()));
+            typical_checker_free(c);
+        }
+    }
+}
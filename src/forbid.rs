@@ -0,0 +1,119 @@
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, Use, Value};
+
+/// Violation reports a forbidden flow that was derived, along with the path
+/// of edges it was derived through, from the value to the use.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Violation<I = u32> {
+    pub lhs: Value<I>,
+    pub rhs: Use<I>,
+    pub path: Vec<EntityId<I>>,
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Registers a flow that must never exist, such as tainted data reaching
+    /// a sink. Deriving it doesn't fail the flow that caused it, but records
+    /// a violation to be reported through `violations`.
+    pub fn forbid(&mut self, lhs: Value<I>, rhs: Use<I>) {
+        self.forbidden.push((lhs, rhs));
+        self.check_forbidden(&[]);
+    }
+
+    /// Returns every forbidden flow that has been derived, in the order they
+    /// were found.
+    pub fn violations(&self) -> &[Violation<I>] {
+        &self.violations
+    }
+
+    /// Records a violation for every forbidden flow that now exists and
+    /// hasn't been reported yet. Only the new edges, and forbidden flows
+    /// that were registered after the fact, need to be considered.
+    pub(crate) fn check_forbidden(&mut self, new_edges: &[(EntityId<I>, EntityId<I>)]) {
+        let found: Vec<(Value<I>, Use<I>)> = self
+            .forbidden
+            .iter()
+            .copied()
+            .filter(|(lhs, rhs)| {
+                let edge = (self.aliases.root(lhs.0), self.aliases.root(rhs.0));
+                self.r.has_edge(edge.0, edge.1)
+                    && (new_edges.is_empty() || new_edges.contains(&edge))
+            })
+            .filter(|&(lhs, rhs)| {
+                !self
+                    .violations
+                    .iter()
+                    .any(|violation| violation.lhs == lhs && violation.rhs == rhs)
+            })
+            .collect();
+
+        for (lhs, rhs) in found {
+            let path = self.derivation(self.aliases.root(lhs.0), self.aliases.root(rhs.0));
+            self.violations.push(Violation { lhs, rhs, path });
+        }
+    }
+
+    /// Returns the shortest path of direct edges from one node to another.
+    pub(crate) fn derivation(&self, from: EntityId<I>, to: EntityId<I>) -> Vec<EntityId<I>> {
+        let mut previous: HashMap<EntityId<I>, EntityId<I>> = HashMap::new();
+        let mut frontier = vec![from];
+
+        while !frontier.is_empty() && !previous.contains_key(&to) {
+            let mut next = Vec::new();
+            for &node in &frontier {
+                for &(lhs, rhs) in self.direct.iter().filter(|&&(lhs, _)| lhs == node) {
+                    if rhs != from && !previous.contains_key(&rhs) {
+                        previous.insert(rhs, lhs);
+                        next.push(rhs);
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        let mut path = vec![to];
+        while let Some(&prev) = path.last().and_then(|node| previous.get(node)) {
+            path.push(prev);
+            if prev == from {
+                break;
+            }
+        }
+        path.reverse();
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::Permissive;
+    use crate::*;
+
+    #[test]
+    fn forbidden_flows_should_be_reported_with_their_path() {
+        let mut t = TypeChecker::new(Permissive);
+        let tainted = t.new_val(());
+        let sink = t.new_use(());
+        let (x_val, x_use) = t.var();
+        let (y_val, y_use) = t.var();
+        t.forbid(tainted, sink);
+
+        assert!(t.flow(tainted, x_use).is_ok());
+        assert!(t.flow(y_val, sink).is_ok());
+        assert!(t.violations().is_empty());
+
+        assert!(t.flow(x_val, y_use).is_ok());
+        assert_eq!(
+            vec![Violation {
+                lhs: tainted,
+                rhs: sink,
+                path: vec![tainted.0, x_val.0, y_val.0, sink.0],
+            }],
+            t.violations()
+        );
+    }
+}
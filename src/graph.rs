@@ -1,43 +1,113 @@
-use std::fmt::Debug;
+use crate::collections::{Key, Shared};
+use crate::prelude::*;
+use alloc::sync::Arc;
+use core::convert::TryFrom;
+use core::fmt::Debug;
+
+/// Returns the index of the node at a position. Positions only come from
+/// nodes the graph holds, each of which got its index when it was added.
+fn to_idx<Idx: TryFrom<usize>>(idx: usize) -> Idx {
+    match Idx::try_from(idx) {
+        Ok(node) => node,
+        Err(_) => panic!("node {} doesn't fit in the graph's index", idx),
+    }
+}
 
 #[derive(Default, Clone)]
 /// OrderedSet maintains a consistent order of items determined by the sequence
-/// that elements were added to the set.
+/// that elements were added to the set. Clones share their elements until
+/// one of them is written to, at which point it takes a copy of its own.
 struct OrderedSet<T> {
-    v: Vec<T>,
-    s: std::collections::HashSet<T>,
+    v: Arc<Vec<T>>,
+    s: Arc<crate::collections::HashSet<T>>,
 }
 
 impl<T> OrderedSet<T>
 where
-    T: Eq + std::hash::Hash + Clone + Copy,
+    T: Key + Clone + Copy,
 {
     /// If an element doesn't currently exist in a set, it is appended to the
     /// end of the set and true is returned.
     fn insert(&mut self, value: T) -> bool {
-        if self.s.insert(value.clone()) {
-            self.v.push(value);
+        if !self.s.contains(&value) {
+            Arc::make_mut(&mut self.s).insert(value);
+            Arc::make_mut(&mut self.v).push(value);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns true if the element is in the set.
+    fn contains(&self, value: &T) -> bool {
+        self.s.contains(value)
+    }
+
+    /// Removes an element from the set, preserving the order of the
+    /// remaining elements, returning true if it was present.
+    fn remove(&mut self, value: &T) -> bool {
+        if self.s.contains(value) {
+            Arc::make_mut(&mut self.s).remove(value);
+            Arc::make_mut(&mut self.v).retain(|v| v != value);
             true
         } else {
             false
         }
     }
+
+    /// Appends an element without indexing it, for when membership is
+    /// tracked elsewhere and the element is known to be absent.
+    fn push_unindexed(&mut self, value: T) {
+        Arc::make_mut(&mut self.v).push(value);
+    }
+
+    /// Removes an element that was added by `push_unindexed`.
+    fn remove_unindexed(&mut self, value: &T) {
+        Arc::make_mut(&mut self.v).retain(|v| v != value);
+    }
 }
 
 impl<T> OrderedSet<T>
 where
     T: Clone + Copy,
 {
-    fn iter(&self) -> std::slice::Iter<T> {
+    fn iter(&self) -> core::slice::Iter<'_, T> {
         self.v.iter()
     }
 }
 
-impl<T> std::fmt::Debug for OrderedSet<T>
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for OrderedSet<T>
+where
+    T: serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.v.serialize(serializer)
+    }
+}
+
+/// An OrderedSet deserializes from its elements in order, rebuilding the
+/// set used for membership.
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for OrderedSet<T>
+where
+    T: serde::Deserialize<'de> + Key + Copy,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let v: Vec<T> = Vec::deserialize(deserializer)?;
+        let s = v.iter().copied().collect();
+        Ok(Self {
+            v: Arc::new(v),
+            s: Arc::new(s),
+        })
+    }
+}
+
+impl<T> core::fmt::Debug for OrderedSet<T>
 where
-    T: std::fmt::Debug + Clone + Copy,
+    T: core::fmt::Debug + Clone + Copy,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "OrderedSet{{{}}}",
@@ -50,30 +120,287 @@ where
     }
 }
 
+/// BitMatrix records the edges of a dense graph as one row of bits per
+/// source node, growing each row as edges to higher nodes are added. Clones
+/// share each row until one of them writes to it.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct BitMatrix {
+    rows: Vec<Shared<Vec<u64>>>,
+}
+
+impl BitMatrix {
+    fn contains(&self, lhs: usize, rhs: usize) -> bool {
+        self.rows
+            .get(lhs)
+            .and_then(|row| row.get(rhs / 64))
+            .is_some_and(|word| word >> (rhs % 64) & 1 == 1)
+    }
+
+    /// Sets the bit for an edge, returning true if it wasn't already set.
+    fn insert(&mut self, lhs: usize, rhs: usize) -> bool {
+        if self.rows.len() <= lhs {
+            self.rows.resize_with(lhs + 1, Shared::default);
+        }
+        let row = &mut *self.rows[lhs];
+        if row.len() <= rhs / 64 {
+            row.resize(rhs / 64 + 1, 0);
+        }
+        let bit = 1 << (rhs % 64);
+        let absent = row[rhs / 64] & bit == 0;
+        row[rhs / 64] |= bit;
+        absent
+    }
+
+    fn remove(&mut self, lhs: usize, rhs: usize) {
+        if let Some(word) = self.rows.get_mut(lhs).and_then(|row| row.get_mut(rhs / 64)) {
+            *word &= !(1 << (rhs % 64));
+        }
+    }
+}
+
+/// UndoEntry records a single mutation to a graph so that it can be reverted
+/// when rolling back a snapshot.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum UndoEntry<Idx> {
+    AddNode,
+    AddEdge(Idx, Idx),
+    RemoveEdge(Idx, Idx),
+}
+
+/// Snapshot marks a point in time that a Graph can be rolled back to.
+#[derive(Debug)]
+pub struct Snapshot {
+    undo_len: usize,
+}
+
+/// GraphConfig chooses how a graph represents and closes over its edges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphConfig {
+    /// tracks edges in a bit matrix rather than in hashed sets, as
+    /// `Graph::dense` does.
+    pub dense: bool,
+    /// leaves out edges from a node to itself, whether added directly or
+    /// derived from a cycle. They never relate two distinct heads, so
+    /// leaving them out only saves space.
+    pub skip_self_edges: bool,
+    /// the number of nodes any one node may flow into before its checker
+    /// warns about it, as a sign of a hot spot that slows checking down.
+    pub max_out_degree: Option<usize>,
+    /// the order a checker takes queued flows and meets off its worklist.
+    pub worklist_order: WorklistOrder,
+    /// queues flows and pairs of heads again while they are still queued,
+    /// so that a pair re-queued before it is met is met once per time it
+    /// was queued. It never changes which flows check, and is only useful as
+    /// a baseline to measure deduplicating the worklist against.
+    pub queue_duplicates: bool,
+}
+
+/// WorklistOrder chooses which queued work a checker takes next. The order
+/// never changes which flows check, only the order edges are derived in and
+/// which failure is hit first when more than one flow would fail.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WorklistOrder {
+    /// takes the work queued last first, following each flow as deep as it
+    /// goes before moving on to the next.
+    #[default]
+    Lifo,
+    /// takes the work queued first first, spreading out over every flow
+    /// before following any of them deeper.
+    Fifo,
+}
+
 /// Graph represents a series of value IDs as upstream and downstream sets
 /// where upstream sets map all the nodes that have edges to a given node
-/// and downsets that map all edges from a given node.
+/// and downsets that map all edges from a given node. Cloning a graph only
+/// copies a pointer per set and per row of a dense matrix, and each clone
+/// copies a set or row the first time it changes it, so forking a graph to
+/// try something out is cheap.
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "Idx: serde::Serialize",
+        deserialize = "Idx: serde::Deserialize<'de> + crate::Key"
+    ))
+)]
 pub struct Graph<Idx>
 where
-    Idx: Into<usize> + From<usize> + Debug + Copy,
+    Idx: Into<usize> + TryFrom<usize> + Debug + Copy,
 {
     /// maps all nodes that have an edge _to_ a given node.
     upstream_sets: Vec<OrderedSet<Idx>>,
     /// maps all nodes that have an edge _from_ a given node.
     downstream_sets: Vec<OrderedSet<Idx>>,
+    /// the number of edges in the graph.
+    edge_count: usize,
+    /// the edges of the graph as a bit matrix, when the graph was created
+    /// dense. Membership is then looked up in the matrix, and the sets only
+    /// keep the order edges were added in.
+    dense: Option<BitMatrix>,
+    undo_log: Vec<UndoEntry<Idx>>,
+    open_snapshots: usize,
+    /// removed nodes, whose slots are free to be reused.
+    free: Vec<Idx>,
+    /// a bit per node, set while the node is removed.
+    removed: Vec<u64>,
+    skip_self_edges: bool,
 }
 
 impl<Idx> Graph<Idx>
 where
-    Idx: Clone + Copy + Eq + std::hash::Hash + Default + Into<usize> + From<usize> + Debug,
+    Idx: Clone + Copy + Key + Default + Into<usize> + TryFrom<usize> + Debug,
 {
+    /// Creates a graph tracking its edges in a bit matrix rather than in
+    /// hashed sets. This takes up to a bit for every pair of nodes, so it
+    /// suits graphs where most nodes end up related, for which it uses less
+    /// memory than hashed sets and adds edges faster.
+    #[allow(dead_code)]
+    pub fn dense() -> Self {
+        Self {
+            dense: Some(BitMatrix::default()),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a graph as configured.
+    pub fn with_config(config: &GraphConfig) -> Self {
+        Self {
+            dense: config.dense.then(BitMatrix::default),
+            skip_self_edges: config.skip_self_edges,
+            ..Self::default()
+        }
+    }
+
+    /// Records an edge in both sets, returning true if it wasn't already
+    /// present.
+    fn link(&mut self, lhs: Idx, rhs: Idx) -> bool {
+        match &mut self.dense {
+            Some(matrix) => {
+                let absent = matrix.insert(lhs.into(), rhs.into());
+                if absent {
+                    self.downstream_sets[lhs.into()].push_unindexed(rhs);
+                    self.upstream_sets[rhs.into()].push_unindexed(lhs);
+                }
+                absent
+            }
+            None => {
+                let absent = self.downstream_sets[lhs.into()].insert(rhs);
+                if absent {
+                    self.upstream_sets[rhs.into()].insert(lhs);
+                }
+                absent
+            }
+        }
+    }
+
+    /// Removes an edge from the set of its source node, leaving the set of
+    /// its target untouched.
+    fn unlink_downstream(&mut self, lhs: Idx, rhs: Idx) {
+        match &mut self.dense {
+            Some(matrix) => {
+                matrix.remove(lhs.into(), rhs.into());
+                self.downstream_sets[lhs.into()].remove_unindexed(&rhs);
+            }
+            None => {
+                self.downstream_sets[lhs.into()].remove(&rhs);
+            }
+        }
+    }
+
+    /// Removes an edge from the set of its target node, leaving the set of
+    /// its source untouched.
+    fn unlink_upstream(&mut self, lhs: Idx, rhs: Idx) {
+        match &mut self.dense {
+            Some(matrix) => {
+                matrix.remove(lhs.into(), rhs.into());
+                self.upstream_sets[rhs.into()].remove_unindexed(&lhs);
+            }
+            None => {
+                self.upstream_sets[rhs.into()].remove(&lhs);
+            }
+        }
+    }
+
     /// Adds a new node in place by reference, returning the Id of the node.
     pub fn add_node_mut(&mut self) -> Idx {
         self.upstream_sets.push(OrderedSet::default());
         self.downstream_sets.push(OrderedSet::default());
+        self.log(UndoEntry::AddNode);
+
+        to_idx(self.upstream_sets.len() - 1)
+    }
+
+    /// Reserves room for at least `additional` more nodes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.upstream_sets.reserve(additional);
+        self.downstream_sets.reserve(additional);
+        if let Some(matrix) = &mut self.dense {
+            matrix.rows.reserve(additional);
+        }
+    }
+
+    /// Removes a node along with every edge to or from it, leaving its slot
+    /// free to be reused by `reuse_node_mut`.
+    ///
+    /// Panics if a snapshot is open.
+    pub fn remove_node_mut(&mut self, node: Idx) {
+        assert_eq!(
+            0, self.open_snapshots,
+            "can't remove a node from a graph with open snapshots"
+        );
+        self.detach_mut(node);
+        self.free.push(node);
+        self.set_removed(node, true);
+    }
 
-        Idx::from(self.upstream_sets.len() - 1)
+    fn set_removed(&mut self, node: Idx, removed: bool) {
+        let idx: usize = node.into();
+        if self.removed.len() <= idx / 64 {
+            self.removed.resize(idx / 64 + 1, 0);
+        }
+        let bit = 1 << (idx % 64);
+        if removed {
+            self.removed[idx / 64] |= bit;
+        } else {
+            self.removed[idx / 64] &= !bit;
+        }
+    }
+
+    /// Returns the slot of a removed node for use as a new node, or None if
+    /// there is none or a snapshot is open, as rolling back can only remove
+    /// nodes from the end.
+    pub fn reuse_node_mut(&mut self) -> Option<Idx> {
+        if self.open_snapshots > 0 {
+            return None;
+        }
+        let node = self.free.pop()?;
+        self.set_removed(node, false);
+        Some(node)
+    }
+
+    /// Returns true if a node was removed and its slot hasn't been reused.
+    pub fn is_removed(&self, node: Idx) -> bool {
+        let idx: usize = node.into();
+        self.removed
+            .get(idx / 64)
+            .is_some_and(|word| word >> (idx % 64) & 1 == 1)
+    }
+
+    /// Returns the number of node slots, including removed ones.
+    pub fn len(&self) -> usize {
+        self.upstream_sets.len()
+    }
+
+    /// Returns every node that hasn't been removed, in order.
+    pub fn nodes(&self) -> impl Iterator<Item = Idx> + '_ {
+        (0..self.upstream_sets.len())
+            .map(to_idx)
+            .filter(move |&node| !self.is_removed(node))
     }
 
     /// Adds a new node by value, returning the modified instance of itself.
@@ -89,9 +416,15 @@ where
         let mut new_edges = Vec::new();
 
         while let Some((lhs, rhs)) = work.pop() {
+            // a self-edge adds nothing to the closure, as every node it
+            // would relate is already related to the node itself.
+            if self.skip_self_edges && lhs == rhs {
+                continue;
+            }
             // Attempt to insert the rhs into the downstream_set
-            if self.downstream_sets[lhs.into()].insert(rhs) {
-                self.upstream_sets[rhs.into()].insert(lhs);
+            if self.link(lhs, rhs) {
+                self.edge_count += 1;
+                self.log(UndoEntry::AddEdge(lhs, rhs));
                 // Inform the caller that a new edge was added
                 new_edges.push((lhs, rhs));
 
@@ -114,6 +447,210 @@ where
 
         (self, new_edges)
     }
+
+    /// Returns the nodes with an edge to the given node, in insertion order.
+    #[allow(dead_code)]
+    pub fn upstream(&self, node: Idx) -> impl Iterator<Item = Idx> + '_ {
+        self.upstream_sets[node.into()].iter().copied()
+    }
+
+    /// Returns the nodes with an edge from the given node, in insertion order.
+    #[allow(dead_code)]
+    pub fn downstream(&self, node: Idx) -> impl Iterator<Item = Idx> + '_ {
+        self.downstream_sets[node.into()].iter().copied()
+    }
+
+    /// Returns true if there is an edge from `lhs` to `rhs`.
+    pub fn has_edge(&self, lhs: Idx, rhs: Idx) -> bool {
+        match &self.dense {
+            Some(matrix) => matrix.contains(lhs.into(), rhs.into()),
+            None => self.downstream_sets[lhs.into()].contains(&rhs),
+        }
+    }
+
+    /// Removes every edge to or from a node, leaving the node itself in
+    /// place.
+    pub fn detach_mut(&mut self, node: Idx) {
+        let upstream = core::mem::take(&mut self.upstream_sets[node.into()]);
+        let downstream = core::mem::take(&mut self.downstream_sets[node.into()]);
+
+        for &lhs in upstream.iter() {
+            self.unlink_downstream(lhs, node);
+            self.log(UndoEntry::RemoveEdge(lhs, node));
+        }
+        for &rhs in downstream.iter() {
+            self.unlink_upstream(node, rhs);
+            // a self-loop was already logged as an upstream edge.
+            if rhs != node {
+                self.log(UndoEntry::RemoveEdge(node, rhs));
+            }
+        }
+        // a self-loop appears in both sets but is a single edge.
+        let self_loop = downstream.iter().any(|&rhs| rhs == node) as usize;
+        self.edge_count -= upstream.v.len() + downstream.v.len() - self_loop;
+    }
+
+    fn log(&mut self, entry: UndoEntry<Idx>) {
+        if self.open_snapshots > 0 {
+            self.undo_log.push(entry);
+        }
+    }
+
+    /// Begins recording mutations so they can be rolled back.
+    pub fn snapshot(&mut self) -> Snapshot {
+        self.open_snapshots += 1;
+        Snapshot {
+            undo_len: self.undo_log.len(),
+        }
+    }
+
+    /// Reverts every mutation made since the snapshot was taken, including
+    /// nodes and the transitive edges added to close over new edges. Edges
+    /// removed since then are restored at the end of their sets.
+    pub fn rollback_to(&mut self, snapshot: Snapshot) {
+        while self.undo_log.len() > snapshot.undo_len {
+            match self.undo_log.pop().unwrap() {
+                UndoEntry::AddNode => {
+                    self.upstream_sets.pop();
+                    self.downstream_sets.pop();
+                    if let Some(matrix) = &mut self.dense {
+                        matrix.rows.truncate(self.upstream_sets.len());
+                    }
+                }
+                UndoEntry::AddEdge(lhs, rhs) => {
+                    self.unlink_downstream(lhs, rhs);
+                    self.unlink_upstream(lhs, rhs);
+                    self.edge_count -= 1;
+                }
+                UndoEntry::RemoveEdge(lhs, rhs) => {
+                    self.link(lhs, rhs);
+                    self.edge_count += 1;
+                }
+            }
+        }
+        self.open_snapshots -= 1;
+    }
+
+    /// Returns true if a snapshot is open.
+    pub fn is_snapshotting(&self) -> bool {
+        self.open_snapshots > 0
+    }
+
+    /// Keeps every mutation made since the snapshot was taken.
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        self.open_snapshots -= 1;
+        if self.open_snapshots == 0 {
+            self.undo_log.truncate(snapshot.undo_len);
+        }
+    }
+
+    /// Rebuilds the graph over only the nodes given a new index by `remap`,
+    /// which maps every current node to its new index, if it is kept. Kept
+    /// nodes must be given indices counting up from zero, and the edges
+    /// between them stay closed as the graph was already closed over the
+    /// dropped ones.
+    ///
+    /// Panics if a snapshot is open, since the undo log refers to the old
+    /// indices.
+    pub fn compact_mut(&mut self, remap: &[Option<Idx>]) {
+        assert_eq!(
+            0, self.open_snapshots,
+            "can't compact a graph with open snapshots"
+        );
+        let mut graph = Self::with_config(&GraphConfig {
+            dense: self.dense.is_some(),
+            skip_self_edges: self.skip_self_edges,
+            ..GraphConfig::default()
+        });
+        for _ in remap.iter().flatten() {
+            graph.add_node_mut();
+        }
+        for (lhs, rhs) in self.edges() {
+            if let (Some(lhs), Some(rhs)) = (remap[lhs.into()], remap[rhs.into()]) {
+                graph.link(lhs, rhs);
+                graph.edge_count += 1;
+            }
+        }
+        for node in self.free.iter().filter_map(|&node| remap[node.into()]) {
+            graph.free.push(node);
+            graph.set_removed(node, true);
+        }
+        *self = graph;
+    }
+
+    /// Appends the nodes and edges of another graph, shifting its indices
+    /// past the nodes of this one. Both graphs are closed and share no
+    /// nodes, so the result is closed too.
+    ///
+    /// Panics if a snapshot is open on either graph.
+    pub fn append_mut(&mut self, other: &Self) {
+        assert!(
+            self.open_snapshots == 0 && other.open_snapshots == 0,
+            "can't append graphs with open snapshots"
+        );
+        let base = self.len();
+        let shift = |node: Idx| to_idx(base + node.into());
+        for _ in 0..other.len() {
+            self.add_node_mut();
+        }
+        for (lhs, rhs) in other.edges() {
+            if self.link(shift(lhs), shift(rhs)) {
+                self.edge_count += 1;
+            }
+        }
+        for &node in &other.free {
+            self.free.push(shift(node));
+            self.set_removed(shift(node), true);
+        }
+    }
+
+    /// Adds an edge without closing over it, for rebuilding a graph that was
+    /// already closed, returning true if it wasn't already present.
+    #[cfg(feature = "std")]
+    pub fn add_closed_edge_mut(&mut self, lhs: Idx, rhs: Idx) -> bool {
+        let absent = self.link(lhs, rhs);
+        if absent {
+            self.edge_count += 1;
+            self.log(UndoEntry::AddEdge(lhs, rhs));
+        }
+        absent
+    }
+
+    /// Returns the removed nodes whose slots haven't been reused, in the
+    /// order they were removed.
+    #[cfg(feature = "std")]
+    pub fn free_nodes(&self) -> &[Idx] {
+        &self.free
+    }
+
+    /// Returns the number of nodes a node has an edge to.
+    pub fn out_degree(&self, node: Idx) -> usize {
+        self.downstream_sets[node.into()].v.len()
+    }
+
+    /// Returns the size of the largest downstream set.
+    pub fn max_downstream(&self) -> usize {
+        self.downstream_sets
+            .iter()
+            .map(|set| set.v.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of edges in the graph.
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    /// Returns an iterator over every edge in the graph, ordered by source
+    /// node and then by the order in which each edge was inserted.
+    #[allow(dead_code)]
+    pub fn edges(&self) -> impl Iterator<Item = (Idx, Idx)> + '_ {
+        self.downstream_sets
+            .iter()
+            .enumerate()
+            .flat_map(|(lhs, set)| set.iter().map(move |&rhs| (to_idx(lhs), rhs)))
+    }
 }
 
 #[cfg(test)]
@@ -122,7 +659,7 @@ mod tests {
 
     #[test]
     fn edges_should_resolve_transitivity() {
-        let graph = (0..10).fold(Graph::default(), |acc, _| acc.add_node().0);
+        let graph = (0..10).fold(Graph::<usize>::default(), |acc, _| acc.add_node().0);
 
         let (_, mut new_edges) = [(0, 3), (1, 3), (2, 3), (3, 4)]
             .iter()
@@ -136,4 +673,89 @@ mod tests {
         expected.sort_unstable();
         assert_eq!(expected, new_edges);
     }
+
+    #[test]
+    fn clones_should_share_sets_until_they_change() {
+        let mut graph = (0..4).fold(Graph::<usize>::default(), |acc, _| acc.add_node().0);
+        graph.add_edge_mut(0, 1);
+        graph.add_edge_mut(2, 3);
+
+        let mut fork = graph.clone();
+        fork.add_edge_mut(1, 2);
+        assert!(fork.has_edge(0, 3) && !graph.has_edge(0, 3));
+        assert!(Arc::ptr_eq(
+            &graph.upstream_sets[1].v,
+            &fork.upstream_sets[1].v
+        ));
+        assert!(!Arc::ptr_eq(
+            &graph.upstream_sets[3].v,
+            &fork.upstream_sets[3].v
+        ));
+    }
+
+    #[test]
+    fn removed_nodes_should_be_skipped_and_reused() {
+        let mut graph = (0..3).fold(Graph::<usize>::default(), |acc, _| acc.add_node().0);
+        graph.add_edge_mut(0, 1);
+        graph.add_edge_mut(1, 2);
+        graph.remove_node_mut(1);
+
+        assert_eq!(vec![(0, 2)], graph.edges().collect::<Vec<_>>());
+        assert_eq!(vec![0, 2], graph.nodes().collect::<Vec<_>>());
+        assert_eq!(Some(1), graph.reuse_node_mut());
+        assert_eq!(None, graph.reuse_node_mut());
+        assert_eq!(3, graph.nodes().count());
+    }
+
+    #[test]
+    fn dense_graphs_should_match_sparse_ones() {
+        let edges = [(0, 3), (1, 3), (3, 4), (4, 1), (2, 0), (2, 4)];
+        let (mut sparse, mut dense) = (Graph::<usize>::default(), Graph::<usize>::dense());
+        for _ in 0..70 {
+            sparse.add_node_mut();
+            dense.add_node_mut();
+        }
+
+        for &(lhs, rhs) in edges.iter().chain(&[(4, 69), (69, 0)]) {
+            assert_eq!(sparse.add_edge_mut(lhs, rhs), dense.add_edge_mut(lhs, rhs));
+        }
+        sparse.detach_mut(3);
+        dense.detach_mut(3);
+
+        assert_eq!(
+            sparse.edges().collect::<Vec<_>>(),
+            dense.edges().collect::<Vec<_>>()
+        );
+        assert_eq!(sparse.edge_count(), dense.edge_count());
+        assert!(dense.has_edge(69, 4) && !dense.has_edge(3, 4));
+    }
+
+    #[test]
+    fn detach_should_remove_all_edges_of_a_node() {
+        let graph = (0..3).fold(Graph::<usize>::default(), |acc, _| acc.add_node().0);
+        let (mut graph, _) = graph.add_edge(0, 1).0.add_edge(1, 2);
+
+        graph.detach_mut(1);
+        assert_eq!(vec![(0, 2)], graph.edges().collect::<Vec<_>>());
+        assert_eq!(1, graph.edge_count());
+        assert_eq!(0, graph.upstream(1).count() + graph.downstream(1).count());
+    }
+
+    #[test]
+    fn rollback_should_undo_edges_and_their_closure() {
+        let graph = (0..3).fold(Graph::<usize>::default(), |acc, _| acc.add_node().0);
+        let (mut graph, _) = graph.add_edge(0, 1);
+
+        let snapshot = graph.snapshot();
+        let node = graph.add_node_mut();
+        graph.add_edge_mut(1, 2);
+        graph.add_edge_mut(2, node);
+        graph.detach_mut(0);
+        assert_eq!(3, graph.edge_count());
+
+        graph.rollback_to(snapshot);
+        assert_eq!(vec![(0, 1)], graph.edges().collect::<Vec<_>>());
+        assert_eq!(1, graph.edge_count());
+        assert_eq!(3, graph.upstream_sets.len());
+    }
 }
@@ -0,0 +1,129 @@
+use crate::collections::{HashMap, HashSet};
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker};
+
+/// Groups tracks the constraint groups of a checker, each identified by the
+/// index of its tag.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "I: NodeIndex + serde::Serialize",
+        deserialize = "I: NodeIndex + serde::Deserialize<'de>"
+    ))
+)]
+pub(crate) struct Groups<I> {
+    pub(crate) tags: Vec<String>,
+    /// the group currently open, if any.
+    pub(crate) open: Option<usize>,
+    /// the group each node was created in.
+    pub(crate) nodes: HashMap<EntityId<I>, usize>,
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Opens a constraint group, tagging every node and flow created until
+    /// the group is ended so that they can be retracted together. Opening a
+    /// tag that was used before adds to the same group.
+    ///
+    /// Panics if a group is already open, as groups don't nest.
+    pub fn begin_group(&mut self, tag: impl Into<String>) {
+        assert!(self.groups.open.is_none(), "constraint groups don't nest");
+        let tag = tag.into();
+        let group = match self.groups.tags.iter().position(|t| *t == tag) {
+            Some(group) => group,
+            None => {
+                self.groups.tags.push(tag);
+                self.groups.tags.len() - 1
+            }
+        };
+        self.groups.open = Some(group);
+    }
+
+    /// Ends the open constraint group.
+    pub fn end_group(&mut self) {
+        self.groups.open = None;
+    }
+
+    /// Removes every flow created in a group, along with every edge derived
+    /// from them, by re-checking the regions of the graph they touched. The
+    /// nodes created in the group are detached from the graph, as are flows
+    /// from outside the group involving them, and their handles shouldn't be
+    /// used again.
+    pub fn retract_group(&mut self, tag: &str) -> Result<(), AT::Error> {
+        let group = match self.groups.tags.iter().position(|t| t == tag) {
+            Some(group) => group,
+            None => return Ok(()),
+        };
+        if self.groups.open == Some(group) {
+            self.groups.open = None;
+        }
+
+        let nodes: HashSet<EntityId<I>> = self
+            .groups
+            .nodes
+            .iter()
+            .filter(|&(_, &g)| g == group)
+            .map(|(&node, _)| node)
+            .collect();
+        self.groups.nodes.retain(|_, &mut g| g != group);
+
+        let touched: Vec<EntityId<I>> = self
+            .flows
+            .iter()
+            .filter(|flow| flow.group == Some(group))
+            .map(|flow| flow.lhs.0)
+            .chain(nodes.iter().copied())
+            .collect();
+        let mut region = HashSet::new();
+        for node in touched {
+            let node = self.aliases.root(node);
+            if !region.contains(&node) {
+                region.extend(self.region(node));
+            }
+        }
+
+        let aliases = &self.aliases;
+        let retracted = |id: EntityId<I>| nodes.contains(&aliases.root(id));
+        let positions: Vec<usize> = (0..self.flows.len())
+            .filter(|&idx| {
+                let flow = &self.flows[idx];
+                flow.group == Some(group) || retracted(flow.lhs.0) || retracted(flow.rhs.0)
+            })
+            .collect();
+        self.forget_flows(&positions);
+        self.recheck(&region)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn retracting_a_group_should_remove_its_consequences() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        let int_use = t.new_use(Prim::Int);
+        assert!(t.flow(x_val, int_use).is_ok());
+
+        t.begin_group("body");
+        let literal = t.new_val(Prim::Bool);
+        assert_eq!(Err(TypeError::Converge), t.flow(literal, x_use));
+        t.end_group();
+
+        assert!(t.retract_group("body").is_ok());
+        assert_eq!(vec![(x_val.0, int_use.0)], t.r.edges().collect::<Vec<_>>());
+
+        t.begin_group("body");
+        let literal = t.new_val(Prim::Int);
+        assert!(t.flow(literal, x_use).is_ok());
+        t.end_group();
+        assert_eq!(3, t.r.edges().count());
+    }
+}
@@ -0,0 +1,116 @@
+//! Guarded flows, for occurrence typing: a flow that only takes effect once
+//! some value flowing into a guard has a head satisfying a predicate, such
+//! as the scrutinee of `if x is String { ... }` turning out to be a string.
+
+use crate::{AbstractTypes, NodeIndex, TypeChecker, Use, Value};
+
+/// Guarded is a flow waiting on its guard.
+pub(crate) struct Guarded<V, I> {
+    pub(crate) guard: Value<I>,
+    pub(crate) lhs: Value<I>,
+    pub(crate) rhs: Use<I>,
+    predicate: fn(&V) -> bool,
+    /// the number of recorded flows when the guarded flow fired, if it has.
+    pub(crate) fired: Option<usize>,
+}
+
+impl<V, I: Copy> Clone for Guarded<V, I> {
+    fn clone(&self) -> Self {
+        Self {
+            guard: self.guard,
+            lhs: self.lhs,
+            rhs: self.rhs,
+            predicate: self.predicate,
+            fired: self.fired,
+        }
+    }
+}
+
+impl<V, I: core::fmt::Debug> core::fmt::Debug for Guarded<V, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Guarded")
+            .field("guard", &self.guard)
+            .field("lhs", &self.lhs)
+            .field("rhs", &self.rhs)
+            .field("fired", &self.fired)
+            .finish()
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Adds a flow that only takes effect once the head of the guard, or of
+    /// any value flowing into it, satisfies the predicate. The guard is
+    /// checked again whenever a check settles, so the flow fires as soon as
+    /// a later flow or refinement satisfies it, and stays once it has.
+    pub fn flow_if(
+        &mut self,
+        guard: Value<I>,
+        lhs: Value<I>,
+        rhs: Use<I>,
+        predicate: fn(&V) -> bool,
+    ) -> Result<(), AT::Error> {
+        self.guarded.push(Guarded {
+            guard,
+            lhs,
+            rhs,
+            predicate,
+            fired: None,
+        });
+        self.settle()
+    }
+
+    fn guard_holds(&self, guarded: &Guarded<V, I>) -> bool {
+        let root = self.aliases.root(guarded.guard.0);
+        core::iter::once(root)
+            .chain(self.r.upstream(root))
+            .flat_map(|id| self.value_heads(id))
+            .any(guarded.predicate)
+    }
+
+    /// Fires every guarded flow whose guard now holds, returning true if
+    /// any did.
+    pub(crate) fn fire_guards(&mut self) -> bool {
+        let mut fired = false;
+        for idx in 0..self.guarded.len() {
+            if self.guarded[idx].fired.is_none() && self.guard_holds(&self.guarded[idx]) {
+                self.guarded[idx].fired = Some(self.flows.len());
+                let (lhs, rhs) = (self.guarded[idx].lhs, self.guarded[idx].rhs);
+                self.enqueue(lhs, rhs);
+                fired = true;
+            }
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn guarded_flows_should_fire_once_their_guard_holds() {
+        let mut t = TypeChecker::new(Strict);
+        let (scrutinee, scrutinee_use) = t.var();
+        let (narrowed, _) = t.var();
+        let str_use = t.new_use(Prim::Str);
+        assert!(t
+            .flow_if(scrutinee, narrowed, str_use, |head| *head == Prim::Str)
+            .is_ok());
+
+        let int = t.new_val(Prim::Int);
+        assert!(t.flow(int, scrutinee_use).is_ok());
+        assert!(!t.reaches(narrowed, str_use));
+
+        let checkpoint = t.save();
+        let string = t.new_val(Prim::Str);
+        assert!(t.flow(string, scrutinee_use).is_ok());
+        assert!(t.reaches(narrowed, str_use));
+        t.restore(checkpoint);
+        assert!(!t.reaches(narrowed, str_use));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, TypeNode, Use, Value};
+
+/// ExtraHeads holds the heads nodes were given after the one they were
+/// created with.
+pub(crate) type ExtraHeads<V, U, I> = HashMap<EntityId<I>, Vec<TypeNode<V, U>>>;
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Gives a value another head alongside the one it was created with,
+    /// making it a union of all of them, such as a var used both as a record
+    /// and as a function. Every head of a value is met against every head of
+    /// each use it flows into. Only the head a value was created with is
+    /// refined.
+    ///
+    /// The new head is checked against everything the value already flows
+    /// into, which meets its other heads again too. If the check fails the
+    /// head isn't added, though edges derived before the failure are kept.
+    ///
+    /// Panics if the value is the value side of a var, which has no head.
+    pub fn add_val_head(&mut self, value: Value<I>, extra: V) -> Result<(), AT::Error> {
+        let id = self.aliases.find(value.0);
+        assert!(
+            matches!(self.types[usize::from(id)], TypeNode::Value(_)),
+            "only value nodes can be given more heads"
+        );
+        let pairs: Vec<(EntityId<I>, EntityId<I>)> =
+            self.r.downstream(id).map(|rhs| (id, rhs)).collect();
+        self.add_head(id, TypeNode::Value(extra), pairs)
+    }
+
+    /// Gives a use another head alongside the one it was created with,
+    /// making it an intersection of all of them, as `add_val_head` does for
+    /// values.
+    ///
+    /// Panics if the use is the use side of a var, which has no head.
+    pub fn add_use_head(&mut self, rhs: Use<I>, extra: U) -> Result<(), AT::Error> {
+        let id = self.aliases.find(rhs.0);
+        assert!(
+            matches!(self.types[usize::from(id)], TypeNode::Use(_)),
+            "only use nodes can be given more heads"
+        );
+        let pairs: Vec<(EntityId<I>, EntityId<I>)> =
+            self.r.upstream(id).map(|lhs| (lhs, id)).collect();
+        self.add_head(id, TypeNode::Use(extra), pairs)
+    }
+
+    /// Adds a head to a node and re-checks the pairs of nodes it is in.
+    fn add_head(
+        &mut self,
+        id: EntityId<I>,
+        head: TypeNode<V, U>,
+        pairs: Vec<(EntityId<I>, EntityId<I>)>,
+    ) -> Result<(), AT::Error> {
+        self.extra_heads.entry(id).or_default().push(head);
+        self.added_heads.push(id);
+        self.worklist
+            .extend_pairs(pairs, self.graph_config.queue_duplicates);
+        let result = self.settle();
+        if result.is_err() {
+            self.pop_head();
+        }
+        result
+    }
+
+    /// Removes the head added last.
+    pub(crate) fn pop_head(&mut self) {
+        if let Some(id) = self.added_heads.pop() {
+            let heads = self.extra_heads.get_mut(&id).expect("added heads are kept");
+            heads.pop();
+            if heads.is_empty() {
+                self.extra_heads.remove(&id);
+            }
+        }
+    }
+
+    /// Returns every head of a value node, the one it was created with
+    /// first, or none for a use or a var.
+    pub(crate) fn value_heads(&self, id: EntityId<I>) -> impl Iterator<Item = &V> + '_ {
+        let first = match &self.types[usize::from(id)] {
+            TypeNode::Value(head) => Some(head),
+            _ => None,
+        };
+        first.into_iter().chain(extra_values(&self.extra_heads, id))
+    }
+
+    /// Returns every head of a use node, the one it was created with first,
+    /// or none for a value or a var.
+    pub(crate) fn use_heads(&self, id: EntityId<I>) -> impl Iterator<Item = &U> + '_ {
+        let first = match &self.types[usize::from(id)] {
+            TypeNode::Use(head) => Some(head),
+            _ => None,
+        };
+        first.into_iter().chain(extra_uses(&self.extra_heads, id))
+    }
+}
+
+/// Returns every head of a value node after the one it was created with.
+pub(crate) fn extra_values<V, U, I: NodeIndex>(
+    heads: &ExtraHeads<V, U, I>,
+    id: EntityId<I>,
+) -> impl Iterator<Item = &V> {
+    heads
+        .get(&id)
+        .into_iter()
+        .flatten()
+        .filter_map(|node| match node {
+            TypeNode::Value(head) => Some(head),
+            _ => None,
+        })
+}
+
+/// Returns every head of a use node after the one it was created with.
+pub(crate) fn extra_uses<V, U, I: NodeIndex>(
+    heads: &ExtraHeads<V, U, I>,
+    id: EntityId<I>,
+) -> impl Iterator<Item = &U> {
+    heads
+        .get(&id)
+        .into_iter()
+        .flatten()
+        .filter_map(|node| match node {
+            TypeNode::Use(head) => Some(head),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heads;
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn every_head_should_be_met_against_every_other() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let sink = t.new_use(Prim::Int);
+        assert!(t.flow(int, sink).is_ok());
+
+        // the new head is checked against what the value already flows into.
+        assert_eq!(Err(TypeError::Converge), t.add_val_head(int, Prim::Bool));
+        assert_eq!(0, heads::extra_values(&t.extra_heads, int.0).count());
+
+        let (x_val, x_use) = t.var();
+        let both = t.new_use(Prim::Int);
+        assert!(t.add_use_head(both, Prim::Int).is_ok());
+        assert!(t.flow(x_val, both).is_ok());
+        assert!(t.add_use_head(both, Prim::Bool).is_ok());
+        assert_eq!(Err(TypeError::Converge), t.flow(int, x_use));
+
+        let checkpoint = t.save();
+        let boolean = t.new_val(Prim::Bool);
+        assert!(t.add_val_head(boolean, Prim::Int).is_ok());
+        t.restore(checkpoint);
+        assert_eq!(2, t.added_heads.len());
+        assert_eq!(2, heads::extra_uses(&t.extra_heads, both.0).count());
+    }
+}
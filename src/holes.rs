@@ -0,0 +1,74 @@
+use crate::prelude::*;
+use crate::{AbstractTypes, NodeIndex, TypeChecker, TypeNode, Use, Value};
+
+/// HoleFill is a value head that reached a hole, along with the value it
+/// originated from.
+#[derive(Debug, PartialEq)]
+pub struct HoleFill<'a, V, I = u32> {
+    pub source: Value<I>,
+    pub head: &'a V,
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Creates a hole, a use that accepts any value without constraining
+    /// it, so that what flows into it can be inspected once checking is
+    /// done.
+    pub fn new_hole(&mut self) -> Use<I> {
+        let hole = Use(self.add_node(TypeNode::Var));
+        self.holes.push(hole);
+        hole
+    }
+
+    /// Returns every hole in the order they were created.
+    pub fn holes(&self) -> impl Iterator<Item = Use<I>> + '_ {
+        self.holes.iter().copied()
+    }
+
+    /// Returns every value head that flowed into a hole, in the order the
+    /// flows were added.
+    pub fn hole_fills(&self, hole: Use<I>) -> Vec<HoleFill<'_, V, I>> {
+        let id = self.aliases.root(hole.0);
+        self.r
+            .upstream(id)
+            .flat_map(|source| {
+                self.value_heads(source).map(move |head| HoleFill {
+                    source: Value(source),
+                    head,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn holes_should_report_every_head_reaching_them() {
+        let mut t = TypeChecker::new(Strict);
+        let hole = t.new_hole();
+        let (var_val, var_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        let boolean = t.new_val(Prim::Bool);
+
+        assert!(t.flow(int, var_use).is_ok());
+        assert!(t.flow(var_val, hole).is_ok());
+        assert!(t.flow(boolean, hole).is_ok());
+
+        let fills = t.hole_fills(hole);
+        assert_eq!(
+            vec![(int, &Prim::Int), (boolean, &Prim::Bool)],
+            fills
+                .iter()
+                .map(|fill| (fill.source, fill.head))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec![hole], t.holes().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::describe::{Describe, TypeDescription};
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, TypeNode, Use, Value};
+
+/// TypeSummary gathers everything known about a value for display in an
+/// editor, such as on hover.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeSummary<'a> {
+    /// the simplified type of the value.
+    pub ty: TypeDescription,
+    /// every use head the value flows into.
+    pub constraints: Vec<TypeDescription>,
+    /// the label attached to the value itself.
+    pub label: Option<&'a str>,
+    /// the labels attached to the value heads reaching the value, in the
+    /// order their flows were added.
+    pub origins: Vec<&'a str>,
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Attaches a label to the node behind a value, replacing any label it
+    /// already had.
+    pub fn label_value(&mut self, value: Value<I>, label: impl Into<String>) {
+        self.labels.insert(value.0, label.into());
+    }
+
+    /// Attaches a label to the node behind a use, replacing any label it
+    /// already had.
+    pub fn label_use(&mut self, rhs: Use<I>, label: impl Into<String>) {
+        self.labels.insert(rhs.0, label.into());
+    }
+
+    fn label_of(&self, id: EntityId<I>) -> Option<&str> {
+        self.labels.get(&id).map(String::as_str)
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Describe<I>,
+    U: Describe<I>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Summarizes the inferred type at a value, combining its description,
+    /// the constraints it flows into and the labels of the node and of
+    /// everything reaching it.
+    pub fn type_at(&self, value: Value<I>) -> TypeSummary<'_> {
+        let id = self.aliases.root(value.0);
+        let constraints = self
+            .r
+            .downstream(id)
+            .flat_map(|rhs| self.use_heads(rhs))
+            .map(|head| self.describe_use_head(head))
+            .collect();
+        let origins = self
+            .r
+            .upstream(id)
+            .filter(|&lhs| matches!(self.types[usize::from(lhs)], TypeNode::Value(_)))
+            .filter_map(|lhs| self.label_of(lhs))
+            .collect();
+
+        TypeSummary {
+            ty: self.describe_simplified(value),
+            constraints,
+            label: self.label_of(value.0),
+            origins,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Permissive, Prim};
+    use crate::*;
+
+    #[test]
+    fn type_at_should_summarize_a_value() {
+        let mut t = TypeChecker::new(Permissive);
+        let (x_val, x_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        let boolean = t.new_val(Prim::Bool);
+        let sink = t.new_use(Prim::Int);
+        t.label_value(x_val, "x");
+        t.label_value(int, "1:5");
+
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(boolean, x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+
+        let summary = t.type_at(x_val);
+        assert_eq!("int | bool", summary.ty.to_string());
+        assert_eq!(
+            vec!["int".to_string()],
+            summary
+                .constraints
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some("x"), summary.label);
+        assert_eq!(vec!["1:5"], summary.origins);
+    }
+}
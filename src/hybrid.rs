@@ -0,0 +1,152 @@
+use crate::prelude::*;
+use crate::union_find::UnionFind;
+use crate::{AbstractTypes, EntityId, TypeChecker, Use, Value};
+
+/// Var is a handle to a variable managed by a HybridChecker. Unlike the
+/// variables of a TypeChecker, a Var isn't backed by a node in the flow graph
+/// until it is first used directionally.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Var(EntityId);
+
+/// HybridChecker combines unification with flow-based checking. Symmetric
+/// constraints between plain variables are solved with union-find, and only
+/// genuinely directional constraints are added to the flow graph, so that a
+/// whole class of unified variables shares a single graph node.
+#[derive(Debug, Clone)]
+pub struct HybridChecker<V, U, AT>
+where
+    AT: AbstractTypes<V, U>,
+{
+    checker: TypeChecker<V, U, AT>,
+    /// partitions variables into classes known to be equal.
+    vars: UnionFind,
+    /// the graph node backing each class, only meaningful for roots.
+    nodes: Vec<Option<(Value, Use)>>,
+}
+
+impl<V, U, AT> HybridChecker<V, U, AT>
+where
+    AT: AbstractTypes<V, U>,
+{
+    pub fn new(abstract_type_mapper: AT) -> Self {
+        Self {
+            checker: TypeChecker::new(abstract_type_mapper),
+            vars: UnionFind::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Returns the flow-based checker holding all directional constraints.
+    pub fn checker(&self) -> &TypeChecker<V, U, AT> {
+        &self.checker
+    }
+
+    pub fn new_val(&mut self, val_type: V) -> Value {
+        self.checker.new_val(val_type)
+    }
+
+    pub fn new_use(&mut self, constraint: U) -> Use {
+        self.checker.new_use(constraint)
+    }
+
+    /// Creates a new variable without allocating a graph node for it.
+    pub fn var(&mut self) -> Var {
+        self.nodes.push(None);
+        Var(self.vars.make_set())
+    }
+
+    fn find(&mut self, var: Var) -> EntityId {
+        self.vars.find(var.0)
+    }
+
+    /// Returns the graph node backing a variable's class, allocating it on
+    /// first use.
+    fn node(&mut self, var: Var) -> (Value, Use) {
+        let root = usize::from(self.find(var));
+        match self.nodes[root] {
+            Some(node) => node,
+            None => {
+                let node = self.checker.var();
+                self.nodes[root] = Some(node);
+                node
+            }
+        }
+    }
+
+    /// Returns the value side of a variable, for flowing it into a use.
+    pub fn value_of(&mut self, var: Var) -> Value {
+        self.node(var).0
+    }
+
+    /// Returns the use side of a variable, for flowing a value into it.
+    pub fn use_of(&mut self, var: Var) -> Use {
+        self.node(var).1
+    }
+
+    /// Adds a directional constraint to the flow graph.
+    pub fn flow(&mut self, lhs: Value, rhs: Use) -> Result<(), AT::Error> {
+        self.checker.flow(lhs, rhs)
+    }
+
+    /// Constrains two variables to be equal. If at most one of them has been
+    /// used directionally, this is solved by union-find alone. Otherwise both
+    /// graph nodes already exist and are equated by flowing each into the
+    /// other.
+    pub fn unify(&mut self, lhs: Var, rhs: Var) -> Result<(), AT::Error> {
+        let (lhs_root, rhs_root) = (self.find(lhs), self.find(rhs));
+        if lhs_root == rhs_root {
+            return Ok(());
+        }
+
+        let (lhs_idx, rhs_idx) = (usize::from(lhs_root), usize::from(rhs_root));
+        let root = usize::from(self.vars.union(lhs_root, rhs_root));
+        let (lhs_node, rhs_node) = (self.nodes[lhs_idx].take(), self.nodes[rhs_idx].take());
+        self.nodes[root] = lhs_node.or(rhs_node);
+
+        if let (Some(lhs_node), Some(rhs_node)) = (lhs_node, rhs_node) {
+            self.checker.flow(lhs_node.0, rhs_node.1)?;
+            self.checker.flow(rhs_node.0, lhs_node.1)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::{Prim, Strict};
+    use crate::TypeError;
+
+    #[test]
+    fn unified_vars_should_share_a_single_graph_node() {
+        let mut h = HybridChecker::new(Strict);
+        let vars: Vec<Var> = (0..100).map(|_| h.var()).collect();
+        for pair in vars.windows(2) {
+            assert!(h.unify(pair[0], pair[1]).is_ok());
+        }
+
+        let int = h.new_val(Prim::Int);
+        let first = h.use_of(vars[0]);
+        assert!(h.flow(int, first).is_ok());
+
+        // one node for the value and one shared by all 100 variables.
+        assert_eq!(2, h.checker().types.len());
+    }
+
+    #[test]
+    fn should_fall_back_to_flows_for_materialized_vars() {
+        let mut h = HybridChecker::new(Strict);
+        let (a, b) = (h.var(), h.var());
+
+        let int = h.new_val(Prim::Int);
+        let a_use = h.use_of(a);
+        assert!(h.flow(int, a_use).is_ok());
+
+        let bool_use = h.new_use(Prim::Bool);
+        let b_value = h.value_of(b);
+        assert!(h.flow(b_value, bool_use).is_ok());
+
+        // both sides already have graph nodes, so int now reaches bool.
+        assert_eq!(Err(TypeError::Converge), h.unify(a, b));
+    }
+}
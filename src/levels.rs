@@ -0,0 +1,95 @@
+//! Binding levels for let-polymorphism. The nodes created while checking a
+//! let-bound expression belong to the level entered for it, and when the
+//! level is left the ones that stayed local to it are generalized into a
+//! scheme, while the ones that escaped into an outer level stay shared.
+
+use crate::collections::HashSet;
+use crate::prelude::*;
+use crate::scheme::Instantiate;
+use crate::{AbstractTypes, EntityId, NodeIndex, Scheme, TypeChecker, Value};
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Instantiate<I>,
+    U: Instantiate<I>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Enters a new binding level, which every node created from now until
+    /// the matching `pop_level` belongs to.
+    pub fn push_level(&mut self) {
+        self.levels.push(self.types.len());
+    }
+
+    /// Returns the number of binding levels currently entered.
+    pub fn level(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Leaves the innermost binding level, generalizing the subgraph
+    /// reachable from the roots into a scheme. Nodes created within the
+    /// level are copied by every instance of it, unless they escaped: that
+    /// is, unless they are related to a node from an outer level or are
+    /// nested inside the head of a node that escaped. Escaped nodes, like
+    /// the nodes of outer levels, are shared by every instance.
+    ///
+    /// Panics if no level is open.
+    pub fn pop_level(&mut self, roots: &[Value<I>]) -> Scheme<I> {
+        let start = self.levels.pop().expect("no binding level is open");
+        let outer = |id: EntityId<I>| usize::from(id) < start;
+
+        let mut work: Vec<EntityId<I>> = (start..self.types.len())
+            .map(EntityId::at)
+            .filter(|&id| {
+                let root = self.aliases.root(id);
+                outer(root)
+                    || self.r.upstream(root).any(outer)
+                    || self.r.downstream(root).any(outer)
+            })
+            .collect();
+        let mut escaped = HashSet::new();
+        while let Some(node) = work.pop() {
+            if escaped.insert(self.aliases.root(node)) {
+                work.extend(self.nested(node));
+            }
+        }
+
+        self.freeze(roots, &|id| outer(id) || escaped.contains(&id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Head, HeadTypeSystem};
+    use crate::*;
+
+    #[test]
+    fn popping_a_level_should_only_generalize_local_nodes() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let (env_val, env_use) = t.var();
+
+        // let f = fun x -> env in ..., where the result escapes but the
+        // argument doesn't.
+        t.push_level();
+        let (_, x_use) = t.var();
+        let func = t.new_val(Head::Func(x_use, env_val));
+        let scheme = t.pop_level(&[func]);
+        assert_eq!(0, t.level());
+
+        let instances: Vec<Value> = (0..2).map(|_| t.instantiate(&scheme)[0]).collect();
+        for (instance, head) in instances.iter().zip(vec![Head::Int, Head::Bool]) {
+            let arg = t.new_val(head);
+            let call = t.new_use(Head::Func(Use(arg.0), env_val));
+            assert!(t.flow(*instance, call).is_ok());
+        }
+        assert!(!instances.contains(&func) && instances[0] != instances[1]);
+        assert_eq!(0, t.flows_from(x_use).count());
+
+        // flowing into an outer var lets a node escape, so it is shared.
+        t.push_level();
+        let (y_val, _) = t.var();
+        assert!(t.flow(y_val, env_use).is_ok());
+        let scheme = t.pop_level(&[y_val]);
+        assert_eq!(vec![y_val], t.instantiate(&scheme));
+    }
+}
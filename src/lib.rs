@@ -1,48 +1,485 @@
 //! This crate functions as a test/toy implementation of an algebraic
 //! type-checker based on the work by Robert Grosse.
+//!
+//! Two disciplines are provided. `TypeChecker` solves subtyping constraints
+//! by flowing values into uses over a type system implementing
+//! `AbstractTypes`, while `UnificationChecker` solves equality constraints
+//! Hindley-Milner style over entities implementing `AbstractEntity`.
+//!
+//! Checking is deterministic: the same calls in the same order always build
+//! the same graph, report the same errors and yield the same iteration
+//! order. Queries over the graph list nodes and edges in the order they were
+//! added, never in hash order. `shuffle_worklist` trades this away for
+//! testing, to find meets whose results depend on the order of checking.
+//!
+//! The `std` feature is on by default. Without it the crate is `no_std` and
+//! only needs `alloc`, for running in sandboxes such as WASM plugins.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+mod absorb;
+mod annotate;
+mod automaton;
+mod brand;
+mod bulk;
+mod cancel;
+mod checkpoint;
+mod coerce;
+mod collections;
+mod compact;
+mod completion;
+mod cooccurrence;
+mod cursor;
+mod cycles;
+mod describe;
+mod dot;
+#[cfg(feature = "std")]
+mod encode;
+pub mod env;
+mod errors;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod forbid;
 mod graph;
+mod groups;
+mod guards;
+mod heads;
+mod holes;
+mod hover;
+mod hybrid;
+mod levels;
+mod limits;
+mod listing;
+mod memo;
+mod meta;
+#[cfg(feature = "trace")]
+mod observe;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod prelude;
+mod pretty;
+mod provenance;
+mod query;
+#[cfg(test)]
+mod reference;
+mod remove;
+mod replace;
+mod retract;
+mod scheme;
+pub mod script;
+mod shuffle;
+mod simplify;
+mod snapshot;
+mod stats;
+mod supply;
+pub mod systems;
+#[cfg(test)]
+mod test_systems;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "trace")]
+mod trace;
+mod typed;
+mod types;
+mod unify;
+pub mod union_find;
+mod validate;
+mod variance;
+#[cfg(any(test, feature = "verify-idempotence"))]
+mod verify;
+mod view;
+
+pub use annotate::AnnotationError;
+pub use brand::{branded, Branded, BrandedChecker};
+pub use cancel::{Budget, Cancellable, CancellationToken};
+pub use checkpoint::Checkpoint;
+pub use coerce::{CoercionRequired, Coercions};
+pub use collections::Key;
+pub use compact::Remap;
+pub use completion::{Completion, Fields};
+pub use cursor::{FlowCursor, FlowSession, StepResult};
+pub use describe::{Child, Describe, Polarity, TypeDescription, TypeFolder};
+#[cfg(feature = "std")]
+pub use encode::{DecodeError, Decoder, Encoder, HeadCodec};
+pub use errors::TypeError;
+pub use forbid::Violation;
+pub use graph::{GraphConfig, WorklistOrder};
+pub use holes::HoleFill;
+pub use hover::TypeSummary;
+pub use hybrid::{HybridChecker, Var};
+pub use limits::Limits;
+pub use listing::{EdgeKind, GraphListing, TypeNodeRef};
+pub use memo::Memoized;
+pub use meta::{MetaChecker, MetaFailure};
+#[cfg(feature = "trace")]
+pub use observe::FlowObserver;
+pub use provenance::FlowError;
+pub use scheme::{Instantiate, Scheme};
+pub use snapshot::{GraphDiff, Snapshot};
+pub use stats::Stats;
+pub use supply::VarSupply;
+#[cfg(feature = "trace")]
+pub use trace::{TraceEvent, Tracer};
+pub use typed::{Typed, TypedChecker, TypedUse, TypedValue};
+pub use types::{AbstractEntity, EntityId, EntityTypes, IndexOverflow, NodeIndex};
+pub use unify::{UnificationChecker, Warning};
+pub use validate::Inconsistency;
+pub use variance::Variance;
+pub use view::TypeCheckerView;
 
-pub type EntityId = usize;
+use alloc::collections::VecDeque;
+use prelude::*;
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum TypeError {
-    Converge,
+/// Flow is a value flowing into a use, such as one of the flows a meet
+/// returns between the children of its heads.
+pub(crate) type Flow<I> = (Value<I>, Use<I>);
+
+/// AbstractTypes is a type system whose heads the checker meets, relating
+/// the ids of its nodes over the index `I`, which is `u32` unless the
+/// checker was created over another.
+pub trait AbstractTypes<V, U, I = u32> {
+    type Error;
+
+    fn meet(&self, lhs: &V, rhs: &U) -> Result<Vec<Flow<I>>, Self::Error>;
+
+    /// Checks a pair of heads like `meet`, but with a context for creating
+    /// the nodes that some constructors need in order to relate their
+    /// children, such as an intermediate var. The nodes are added to the
+    /// checker once the meet returns. This is what the checker calls, and
+    /// it defers to `meet` unless overridden.
+    fn meet_with(
+        &self,
+        ctx: &mut MeetContext<V, U, I>,
+        lhs: &V,
+        rhs: &U,
+    ) -> Result<Vec<Flow<I>>, Self::Error> {
+        let _ = ctx;
+        self.meet(lhs, rhs)
+    }
+
+    /// Narrows a value head once it has met a use successfully, such as by
+    /// intersecting the fields of two records. The returned head replaces
+    /// the value's head in place, and is only met against uses the value
+    /// flows into from then on. Heads are left as they are unless
+    /// overridden.
+    fn refine(&self, lhs: &V, rhs: &U) -> Option<V> {
+        let _ = (lhs, rhs);
+        None
+    }
+
+    /// Returns true if the value head satisfies every use, as a bottom type
+    /// such as `Never` does. Such heads are never met, and a pair of nodes
+    /// is never checked at all if every head of its value is one. No head
+    /// is treated as bottom unless overridden.
+    fn is_bottom(&self, lhs: &V) -> bool {
+        let _ = lhs;
+        false
+    }
+
+    /// Returns true if every value satisfies the use head, as a top type
+    /// such as `Any` does. Such heads are never met, and a pair of nodes is
+    /// never checked at all if every head of its use is one. No head is
+    /// treated as top unless overridden.
+    fn is_top(&self, rhs: &U) -> bool {
+        let _ = rhs;
+        false
+    }
+}
+
+/// MeetContext creates nodes on behalf of a meet. The nodes are held back
+/// until the meet returns, as the checker is borrowed for the heads being
+/// met in the meantime, but their handles can be used in the returned flows
+/// straight away.
+#[derive(Debug)]
+pub struct MeetContext<V, U, I = u32> {
+    base: usize,
+    nodes: Vec<TypeNode<V, U>>,
+    index: core::marker::PhantomData<I>,
 }
 
-impl std::fmt::Debug for TypeError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Converge => write!(f, "unable to converge types."),
+impl<V, U, I: NodeIndex> MeetContext<V, U, I> {
+    fn new(base: usize) -> Self {
+        Self {
+            base,
+            nodes: Vec::new(),
+            index: core::marker::PhantomData,
         }
     }
+
+    fn push(&mut self, node: TypeNode<V, U>) -> EntityId<I> {
+        self.nodes.push(node);
+        EntityId::at(self.base + self.nodes.len() - 1)
+    }
+
+    pub fn new_val(&mut self, val_type: V) -> Value<I> {
+        Value(self.push(TypeNode::Value(val_type)))
+    }
+
+    pub fn new_use(&mut self, constraint: U) -> Use<I> {
+        Use(self.push(TypeNode::Use(constraint)))
+    }
+
+    pub fn var(&mut self) -> (Value<I>, Use<I>) {
+        let i = self.push(TypeNode::Var);
+        (Value(i), Use(i))
+    }
 }
 
-pub trait AbstractTypes<V, U> {
-    type Error;
+/// Value is a handle to a node that can flow into uses.
+///
+/// With the `serde` feature enabled, handles serialize as their raw index.
+/// A deserialized handle is only meaningful alongside the checker it was
+/// created by, so it should be validated with `TypeChecker::value_at` against
+/// the restored checker before use.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Value<I = u32>(EntityId<I>);
 
-    fn meet(lhs: &V, rhs: &U) -> Result<Vec<(Value, Use)>, Self::Error>;
+/// Use is a handle to a node that values can flow into.
+///
+/// Serialization follows the same rules as `Value`, with deserialized
+/// handles validated through `TypeChecker::use_at`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Use<I = u32>(EntityId<I>);
+
+impl<I: NodeIndex> Value<I> {
+    /// Returns the raw index of the node this handle refers to.
+    pub fn index(&self) -> usize {
+        self.0.into()
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct Value(usize);
-#[derive(Copy, Clone, Debug)]
-pub struct Use(usize);
+impl<I: NodeIndex> Use<I> {
+    /// Returns the raw index of the node this handle refers to.
+    pub fn index(&self) -> usize {
+        self.0.into()
+    }
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum TypeNode<V, U> {
     Var,
     Value(V),
     Use(U),
 }
 
+/// FlowWarning describes a non-fatal issue a TypeChecker found while closing
+/// over its flows. It is kept apart from the unification checker's
+/// `Warning`, as the two checkers never report the same issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FlowWarning<I = u32> {
+    /// The node `entity` flows into more nodes than the configured maximum.
+    HighOutDegree { entity: EntityId<I>, degree: usize },
+}
+
+/// Worklist holds the flows still to be added to the graph, and the pairs of
+/// nodes newly connected by them whose heads are still to be checked. Work
+/// queued again while it is still queued is dropped, so that a pair
+/// re-queued before it is met is only met once.
+#[derive(Debug, Clone, Default)]
+struct Worklist<I> {
+    edges: VecDeque<(Value<I>, Use<I>)>,
+    pairs: VecDeque<(EntityId<I>, EntityId<I>)>,
+    /// the items in `edges` and `pairs`.
+    queued_edges: collections::HashSet<(Value<I>, Use<I>)>,
+    queued_pairs: collections::HashSet<(EntityId<I>, EntityId<I>)>,
+    /// the number of edges derived since solving last started.
+    derived: usize,
+    /// the number of pairs of heads met since solving last started.
+    meets: usize,
+    /// whether a failed head check keeps the remaining work, so that every
+    /// failure of a batch can be collected.
+    accumulate: bool,
+}
+
+impl<I: NodeIndex> Worklist<I> {
+    /// Queues a flow, unless it is still queued and `duplicates` is false.
+    fn push_edge(&mut self, edge: (Value<I>, Use<I>), duplicates: bool) {
+        if duplicates || self.queued_edges.insert(edge) {
+            self.edges.push_back(edge);
+        }
+    }
+
+    fn extend_edges(
+        &mut self,
+        edges: impl IntoIterator<Item = (Value<I>, Use<I>)>,
+        duplicates: bool,
+    ) {
+        for edge in edges {
+            self.push_edge(edge, duplicates);
+        }
+    }
+
+    fn extend_pairs(
+        &mut self,
+        pairs: impl IntoIterator<Item = (EntityId<I>, EntityId<I>)>,
+        duplicates: bool,
+    ) {
+        for pair in pairs {
+            if duplicates || self.queued_pairs.insert(pair) {
+                self.pairs.push_back(pair);
+            }
+        }
+    }
+
+    fn pop_edge(
+        &mut self,
+        shuffle: &mut Option<u64>,
+        order: WorklistOrder,
+    ) -> Option<(Value<I>, Use<I>)> {
+        let edge = shuffle::pop(shuffle, order, &mut self.edges)?;
+        self.queued_edges.remove(&edge);
+        Some(edge)
+    }
+
+    fn pop_pair(
+        &mut self,
+        shuffle: &mut Option<u64>,
+        order: WorklistOrder,
+    ) -> Option<(EntityId<I>, EntityId<I>)> {
+        let pair = shuffle::pop(shuffle, order, &mut self.pairs)?;
+        self.queued_pairs.remove(&pair);
+        Some(pair)
+    }
+}
+
+/// RecordedFlow is a flow added by the caller, along with the constraint
+/// group that was open when it was added.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct RecordedFlow<I> {
+    lhs: Value<I>,
+    rhs: Use<I>,
+    group: Option<usize>,
+}
+
+/// FlowKey identifies a recorded flow by its handles and group.
+type FlowKey<I> = (Value<I>, Use<I>, Option<usize>);
+
+impl<I: NodeIndex> RecordedFlow<I> {
+    fn key(&self) -> FlowKey<I> {
+        (self.lhs, self.rhs, self.group)
+    }
+}
+
+/// With the `serde` feature enabled, a checker serializes its nodes, graph
+/// and bookkeeping so that it can be persisted between runs and restored
+/// instead of re-deriving every flow. The type system itself isn't part of
+/// the state and is recreated with `Default` on deserialization, while
+/// resource limits and work left over from a cancelled check are dropped.
+///
+/// Cloning a checker, such as to check a speculative branch, copies its
+/// table of nodes and heads, one entry per node. The edges, the recorded
+/// flows and the names and labels are shared between the clones instead,
+/// and each set of edges out of or into a node, or list of flows or map of
+/// names, is copied the first time either clone writes to it.
 #[derive(Debug, Clone)]
-pub struct TypeChecker<V, U, AT>
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "V: serde::Serialize, U: serde::Serialize, I: serde::Serialize",
+        deserialize = "V: serde::Deserialize<'de>, U: serde::Deserialize<'de>, \
+                       I: serde::Deserialize<'de>, AT: Default"
+    ))
+)]
+pub struct TypeChecker<V, U, AT, I = u32>
 where
-    AT: AbstractTypes<V, U>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
 {
-    r: graph::Graph<EntityId>,
+    r: graph::Graph<EntityId<I>>,
     types: Vec<TypeNode<V, U>>,
+    /// the heads given to nodes after the one they were created with.
+    extra_heads: heads::ExtraHeads<V, U, I>,
+    /// the nodes in `extra_heads` in the order each head was added, so that
+    /// restoring a checkpoint can remove the heads added since.
+    added_heads: Vec<EntityId<I>>,
+    /// maps vars merged by simplification to the node standing in for them.
+    aliases: union_find::UnionFind<I>,
+    /// holes created by `new_hole`, in creation order.
+    holes: Vec<Use<I>>,
+    /// display names given to vars by a VarSupply.
+    var_names: collections::Shared<collections::HashMap<EntityId<I>, String>>,
+    /// the vars created by `var_named`, by name.
+    named_vars: collections::Shared<collections::HashMap<String, EntityId<I>>>,
+    /// every flow added by the caller, as opposed to those derived by meets.
+    flows: collections::Shared<Vec<RecordedFlow<I>>>,
+    /// the flows in `flows`, so that adding one again doesn't record it twice.
+    recorded: collections::Shared<collections::HashSet<FlowKey<I>>>,
+    /// the constraint groups nodes and flows were created in.
+    groups: groups::Groups<I>,
+    /// every edge added to the graph before closing over it, whether by the
+    /// caller or by a meet.
+    direct: collections::Shared<Vec<(EntityId<I>, EntityId<I>)>>,
+    /// flows that must never exist, and the ones that were derived anyway.
+    forbidden: Vec<(Value<I>, Use<I>)>,
+    violations: Vec<Violation<I>>,
+    /// the heads replaced in place while a checkpoint was open, oldest
+    /// first, so that restoring the checkpoint can put them back.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    overwritten: Vec<checkpoint::Overwritten<V, U, I>>,
+    /// the heads values had before `refine` first narrowed them, so that
+    /// re-checking a region can start over from them.
+    unrefined: collections::HashMap<EntityId<I>, V>,
+    /// the nodes created by meets, which re-checking their region discards
+    /// before the meets are repeated.
+    meet_nodes: collections::HashSet<EntityId<I>>,
+    /// the pair of heads whose check failed most recently.
+    failed: Option<(EntityId<I>, EntityId<I>)>,
+    /// the number of pairs of heads met so far.
+    meets: usize,
+    /// flows waiting on their guards, and those that fired. Guards aren't
+    /// serialized, as their predicates can't be.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    guarded: Vec<guards::Guarded<V, I>>,
+    graph_config: GraphConfig,
+    warnings: Vec<FlowWarning<I>>,
+    /// the state of the generator shuffling the worklist, if it is.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    shuffle: Option<u64>,
+    /// the function every event is reported to, if tracing.
+    #[cfg(feature = "trace")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tracer: Option<trace::Tracer<V, U, I>>,
+    /// the observer told about every edge and meet, if there is one.
+    #[cfg(feature = "trace")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    observer: observe::Observer<V, U, AT, I>,
+    /// returns the conversion each pair of heads needs while `flow_coercing`
+    /// runs, and the pairs that needed one, tagged as they were met.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    coerces: Option<coerce::Coerces<V, U, AT>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    coerced: Vec<(Value<I>, Use<I>, coerce::Tag)>,
+    /// the number of nodes that existed when each open binding level was
+    /// entered, innermost last.
+    levels: Vec<usize>,
+    /// the configured resource limits, along with the conversion used to
+    /// report them as the type system's error.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    limits: Option<limits::LimitConfig<V, U, AT, I>>,
+    /// work left over from a check that was cancelled.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    worklist: Worklist<I>,
+    /// labels attached to nodes, such as source spans, for display.
+    labels: collections::Shared<collections::HashMap<EntityId<I>, String>>,
+    /// the type system heads are met by. It isn't serialized, and is rebuilt
+    /// from its Default when deserializing.
+    #[cfg_attr(feature = "serde", serde(skip))]
     abstract_type_mapper: AT,
 }
 
@@ -51,67 +488,537 @@ where
     AT: AbstractTypes<V, U>,
 {
     pub fn new(abstract_type_mapper: AT) -> Self {
+        Self::new_with_config(abstract_type_mapper, GraphConfig::default())
+    }
+
+    /// Creates a checker whose graph tracks its edges in a bit matrix, which
+    /// is faster and smaller for large programs where most nodes end up
+    /// related, but larger for sparse ones.
+    pub fn with_dense_graph(abstract_type_mapper: AT) -> Self {
+        let config = GraphConfig {
+            dense: true,
+            ..GraphConfig::default()
+        };
+        Self::new_with_config(abstract_type_mapper, config)
+    }
+
+    /// Creates a checker whose graph is configured as given.
+    pub fn new_with_config(abstract_type_mapper: AT, config: GraphConfig) -> Self {
+        Self::new_with_index(abstract_type_mapper, config)
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Creates a checker whose graph is configured as given, numbering its
+    /// nodes with the index `I` rather than `u32`: a smaller one for
+    /// checkers that stay small, or `usize` for those that outgrow `u32`.
+    pub fn new_with_index(abstract_type_mapper: AT, config: GraphConfig) -> Self {
         Self {
-            r: Default::default(),
+            r: graph::Graph::with_config(&config),
             types: Vec::new(),
+            extra_heads: collections::HashMap::new(),
+            added_heads: Vec::new(),
+            aliases: union_find::UnionFind::default(),
+            holes: Vec::new(),
+            labels: Default::default(),
+            var_names: Default::default(),
+            named_vars: Default::default(),
+            flows: Default::default(),
+            recorded: Default::default(),
+            groups: groups::Groups::default(),
+            direct: Default::default(),
+            forbidden: Vec::new(),
+            violations: Vec::new(),
+            overwritten: Vec::new(),
+            unrefined: collections::HashMap::new(),
+            meet_nodes: collections::HashSet::new(),
+            failed: None,
+            meets: 0,
+            guarded: Vec::new(),
+            graph_config: config,
+            warnings: Vec::new(),
+            shuffle: None,
+            #[cfg(feature = "trace")]
+            tracer: None,
+            #[cfg(feature = "trace")]
+            observer: observe::Observer::default(),
+            coerces: None,
+            coerced: Vec::new(),
+            levels: Vec::new(),
+            limits: None,
+            worklist: Worklist::default(),
             abstract_type_mapper,
         }
     }
 
-    pub fn new_val(&mut self, val_type: V) -> Value {
-        let i = self.r.add_node_mut();
-        assert!(i == self.types.len());
-        self.types.push(TypeNode::Value(val_type));
-        Value(i)
+    /// Returns the warnings raised so far, such as for nodes that flow into
+    /// more nodes than the configured maximum.
+    pub fn warnings(&self) -> &[FlowWarning<I>] {
+        &self.warnings
     }
 
-    pub fn new_use(&mut self, constraint: U) -> Use {
+    fn add_node(&mut self, node: TypeNode<V, U>) -> EntityId<I> {
         let i = self.r.add_node_mut();
-        assert!(i == self.types.len());
-        self.types.push(TypeNode::Use(constraint));
-        Use(i)
+        let alias = self.aliases.make_set();
+        assert!(usize::from(i) == self.types.len() && alias == i);
+        if let Some(group) = self.groups.open {
+            self.groups.nodes.insert(i, group);
+        }
+        self.types.push(node);
+        i
     }
 
-    pub fn var(&mut self) -> (Value, Use) {
-        let i = self.r.add_node_mut();
-        assert!(i == self.types.len());
-        self.types.push(TypeNode::Var);
+    pub fn new_val(&mut self, val_type: V) -> Value<I> {
+        Value(self.alloc_node(TypeNode::Value(val_type)))
+    }
+
+    pub fn new_use(&mut self, constraint: U) -> Use<I> {
+        Use(self.alloc_node(TypeNode::Use(constraint)))
+    }
+
+    pub fn var(&mut self) -> (Value<I>, Use<I>) {
+        let i = self.alloc_node(TypeNode::Var);
         (Value(i), Use(i))
     }
 
-    pub fn flow(&mut self, lhs: Value, rhs: Use) -> Result<(), AT::Error> {
-        let mut pending_edges = vec![(lhs, rhs)];
-        let mut type_pairs_to_check = Vec::new();
-        while let Some((lhs, rhs)) = pending_edges.pop() {
-            type_pairs_to_check.extend(self.r.add_edge_mut(lhs.0, rhs.0));
+    /// Returns a value handle for the node at a raw index, provided the node
+    /// exists, hasn't been removed and is a value or var.
+    pub fn value_at(&self, index: usize) -> Result<Value<I>, TypeError> {
+        if self.is_removed(index) {
+            return Err(TypeError::HandleInvalid { index });
+        }
+        match self.types.get(index) {
+            Some(TypeNode::Value(_)) | Some(TypeNode::Var) => Ok(Value(EntityId::at(index))),
+            _ => Err(TypeError::HandleInvalid { index }),
+        }
+    }
+
+    /// Returns a use handle for the node at a raw index, provided the node
+    /// exists, hasn't been removed and is a use or var.
+    pub fn use_at(&self, index: usize) -> Result<Use<I>, TypeError> {
+        if self.is_removed(index) {
+            return Err(TypeError::HandleInvalid { index });
+        }
+        match self.types.get(index) {
+            Some(TypeNode::Use(_)) | Some(TypeNode::Var) => Ok(Use(EntityId::at(index))),
+            _ => Err(TypeError::HandleInvalid { index }),
+        }
+    }
+
+    /// Queues a flow added by the caller, recording it so that it can be
+    /// replayed if the region of the graph it belongs to is re-checked.
+    /// Returns false if the flow was already recorded.
+    fn enqueue(&mut self, lhs: Value<I>, rhs: Use<I>) -> bool {
+        let recorded = self.record(lhs, rhs);
+        self.worklist
+            .push_edge((lhs, rhs), self.graph_config.queue_duplicates);
+        recorded
+    }
+
+    /// Queues every flow like `enqueue`, returning the positions of those
+    /// newly recorded so that they can be forgotten if their check fails.
+    fn enqueue_all(&mut self, pairs: impl IntoIterator<Item = (Value<I>, Use<I>)>) -> Vec<usize> {
+        let mut recorded = Vec::new();
+        for (lhs, rhs) in pairs {
+            let idx = self.flows.len();
+            if self.enqueue(lhs, rhs) {
+                recorded.push(idx);
+            }
+        }
+        recorded
+    }
+
+    /// Records a flow added by the caller without queueing it, returning
+    /// false if it was already recorded.
+    pub(crate) fn record(&mut self, lhs: Value<I>, rhs: Use<I>) -> bool {
+        let flow = RecordedFlow {
+            lhs,
+            rhs,
+            group: self.groups.open,
+        };
+        let recorded = self.recorded.insert(flow.key());
+        if recorded {
+            self.flows.push(flow);
+        }
+        recorded
+    }
+
+    /// Rebuilds the set of recorded flows after they were edited in bulk,
+    /// dropping any flows that the edit made duplicates of one another.
+    pub(crate) fn sync_recorded(&mut self) {
+        let mut recorded = collections::HashSet::new();
+        self.flows.retain(|flow| recorded.insert(flow.key()));
+        self.recorded = collections::Shared::new(recorded);
+    }
+
+    /// Adds a flow from a value into a use and checks everything it implies.
+    /// A flow whose check fails isn't recorded, so it isn't replayed when
+    /// its region of the graph is re-checked.
+    pub fn flow(&mut self, lhs: Value<I>, rhs: Use<I>) -> Result<(), AT::Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("flow", lhs = lhs.index(), rhs = rhs.index()).entered();
+        #[cfg(feature = "trace")]
+        self.trace(trace::TraceEvent::Flow { lhs, rhs });
+        let idx = self.flows.len();
+        let recorded = self.enqueue(lhs, rhs);
+        if let Err(error) = self.settle() {
+            if recorded {
+                self.forget_flow(idx);
+            }
+            return Err(error);
+        }
+        #[cfg(any(test, feature = "verify-idempotence"))]
+        self.verify_idempotent(lhs, rhs);
+        Ok(())
+    }
+
+    /// Replaces a node in place, logging the old one if a checkpoint is open.
+    pub(crate) fn set_node(&mut self, id: EntityId<I>, node: TypeNode<V, U>) {
+        let old = core::mem::replace(&mut self.types[usize::from(id)], node);
+        if self.r.is_snapshotting() {
+            self.overwritten
+                .push(checkpoint::Overwritten::Node(id, old));
+        }
+    }
+
+    /// Narrows the head of a value in place, keeping the head it had before
+    /// its first refinement.
+    pub(crate) fn refine_node(&mut self, id: EntityId<I>, head: V) {
+        if self.unrefined.contains_key(&id) {
+            return self.set_node(id, TypeNode::Value(head));
+        }
+        if let TypeNode::Value(old) =
+            core::mem::replace(&mut self.types[usize::from(id)], TypeNode::Value(head))
+        {
+            self.unrefined.insert(id, old);
+            if self.r.is_snapshotting() {
+                self.overwritten.push(checkpoint::Overwritten::Refined(id));
+            }
+        }
+    }
+
+    /// Puts back the head a value had before it was refined, if it was.
+    pub(crate) fn unrefine_node(&mut self, id: EntityId<I>) {
+        if let Some(original) = self.unrefined.remove(&id) {
+            if let TypeNode::Value(refined) =
+                core::mem::replace(&mut self.types[usize::from(id)], TypeNode::Value(original))
+            {
+                if self.r.is_snapshotting() {
+                    self.overwritten
+                        .push(checkpoint::Overwritten::Unrefined(id, refined));
+                }
+            }
+        }
+    }
+
+    /// Forgets the head a value had before it was refined, such as when the
+    /// head is replaced outright.
+    pub(crate) fn forget_unrefined(&mut self, id: EntityId<I>) {
+        if let Some(original) = self.unrefined.remove(&id) {
+            if self.r.is_snapshotting() {
+                self.overwritten
+                    .push(checkpoint::Overwritten::Forgotten(id, original));
+            }
+        }
+    }
+
+    /// Drops a recorded flow, keeping the positions guards fired at in step.
+    fn forget_flow(&mut self, idx: usize) {
+        self.forget_flows(&[idx]);
+    }
+
+    /// Drops the recorded flows at each position, given in ascending order,
+    /// keeping the positions guards fired at in step. The flows are logged
+    /// if a checkpoint is open, so that restoring it puts them back.
+    pub(crate) fn forget_flows(&mut self, positions: &[usize]) {
+        if positions.is_empty() {
+            return;
+        }
+        let removed: Vec<(usize, RecordedFlow<I>)> = positions
+            .iter()
+            .map(|&idx| (idx, self.flows[idx]))
+            .collect();
+        let mut idx = 0;
+        let mut next = positions.iter().peekable();
+        self.flows.retain(|_| {
+            let dropped = next.peek() == Some(&&idx);
+            if dropped {
+                next.next();
+            }
+            idx += 1;
+            !dropped
+        });
+        for (_, flow) in &removed {
+            self.recorded.remove(&flow.key());
+        }
+        for fired in self.guarded.iter_mut().filter_map(|g| g.fired.as_mut()) {
+            *fired -= positions.iter().filter(|&&idx| idx < *fired).count();
+        }
+        if self.r.is_snapshotting() {
+            let len = self.flows.len();
+            self.overwritten
+                .push(checkpoint::Overwritten::Flows { removed, len });
+        }
+    }
+
+    /// Drops every recorded flow that `keep` rejects, as `forget_flows`
+    /// does.
+    pub(crate) fn retain_flows(&mut self, mut keep: impl FnMut(&RecordedFlow<I>) -> bool) {
+        let positions: Vec<usize> = (0..self.flows.len())
+            .filter(|&idx| !keep(&self.flows[idx]))
+            .collect();
+        self.forget_flows(&positions);
+    }
+
+    /// Processes the worklist to completion.
+    fn settle(&mut self) -> Result<(), AT::Error> {
+        self.solve(None, None).map_err(|e| match e {
+            Cancellable::Failed(e) => e,
+            Cancellable::Cancelled | Cancellable::Interrupted => {
+                unreachable!("solving without a budget can't be cancelled")
+            }
+        })
+    }
+
+    /// Warns about every node whose out-degree the new edges took past the
+    /// maximum, which happens once per node as edges are never re-added.
+    fn check_out_degrees(&mut self, new_pairs: &[(EntityId<I>, EntityId<I>)], max: usize) {
+        for &(lhs, _) in new_pairs {
+            let degree = self.r.out_degree(lhs);
+            if degree == max + 1 {
+                self.warnings.push(FlowWarning::HighOutDegree {
+                    entity: lhs,
+                    degree,
+                });
+            }
+        }
+    }
+
+    /// Adds a direct edge to the graph, logging it and checking the pairs
+    /// of nodes it newly connects against the forbidden flows and the
+    /// maximum out-degree. Returns the newly connected pairs.
+    pub(crate) fn link(
+        &mut self,
+        lhs: EntityId<I>,
+        rhs: EntityId<I>,
+    ) -> Vec<(EntityId<I>, EntityId<I>)> {
+        // only new edges are logged, so repeated flows don't grow it.
+        let skipped = lhs == rhs && self.graph_config.skip_self_edges;
+        let added = !skipped && !self.r.has_edge(lhs, rhs);
+        if added {
+            self.direct.push((lhs, rhs));
+        }
+        let new_pairs = {
+            #[cfg(feature = "tracing")]
+            let _span =
+                tracing::trace_span!("add_edge", lhs = usize::from(lhs), rhs = usize::from(rhs))
+                    .entered();
+            self.r.add_edge_mut(lhs, rhs)
+        };
+        #[cfg(feature = "trace")]
+        {
+            self.trace(trace::TraceEvent::Edge {
+                lhs,
+                rhs,
+                derived: new_pairs.len(),
+            });
+            self.observe_edges(lhs, rhs, added, &new_pairs);
+        }
+        if !self.forbidden.is_empty() && !new_pairs.is_empty() {
+            self.check_forbidden(&new_pairs);
+        }
+        if let Some(max) = self.graph_config.max_out_degree {
+            self.check_out_degrees(&new_pairs, max);
+        }
+        new_pairs
+    }
+
+    /// Processes the worklist until it is empty, a head check fails, the
+    /// token is cancelled or the steps run out. A cancelled or interrupted
+    /// check keeps its remaining work so it can be resumed, while a failed
+    /// one discards it.
+    fn solve(
+        &mut self,
+        token: Option<&CancellationToken>,
+        max_steps: Option<usize>,
+    ) -> Result<(), Cancellable<AT::Error>> {
+        self.worklist.derived = 0;
+        self.worklist.meets = 0;
+        let mut steps = 0;
+        loop {
+            if token.is_some_and(CancellationToken::is_cancelled) {
+                return Err(Cancellable::Cancelled);
+            }
+            if max_steps.is_some_and(|max| steps >= max) && !self.is_settled() {
+                return Err(Cancellable::Interrupted);
+            }
+            if !self.step().map_err(Cancellable::Failed)? {
+                return Ok(());
+            }
+            steps += 1;
+        }
+    }
+
+    /// Returns true if every head of a value satisfies every head of a use
+    /// without being met, as either the value's heads are all bottom or the
+    /// use's are all top, so that the pair needn't be checked.
+    fn trivially_satisfied(&self, lhs: EntityId<I>, rhs: EntityId<I>) -> bool {
+        let types = &self.abstract_type_mapper;
+        match (&self.types[usize::from(lhs)], &self.types[usize::from(rhs)]) {
+            (TypeNode::Value(value), TypeNode::Use(constraint)) => {
+                (types.is_bottom(value)
+                    && heads::extra_values(&self.extra_heads, lhs).all(|v| types.is_bottom(v)))
+                    || (types.is_top(constraint)
+                        && heads::extra_uses(&self.extra_heads, rhs).all(|u| types.is_top(u)))
+            }
+            _ => false,
+        }
+    }
+
+    /// Performs a single step of the worklist, either checking one pair of
+    /// heads or adding one edge, returning false if there was no work left.
+    fn step(&mut self) -> Result<bool, AT::Error> {
+        // Check any type pairs resulting from a new edge before adding the next one
+        let order = self.graph_config.worklist_order;
+        if let Some((lhs, rhs)) = self.worklist.pop_pair(&mut self.shuffle, order) {
+            let mut ctx = MeetContext::new(self.types.len());
+            let mut refined = None;
+            let result = match (&self.types[usize::from(lhs)], &self.types[usize::from(rhs)]) {
+                (TypeNode::Value(lhs_head), TypeNode::Use(rhs_head)) => {
+                    let values = core::iter::once(lhs_head)
+                        .chain(heads::extra_values(&self.extra_heads, lhs));
+                    let mut flows = Vec::new();
+                    let mut result = Ok(());
+                    'heads: for (value_idx, value) in values.enumerate() {
+                        let uses = core::iter::once(rhs_head)
+                            .chain(heads::extra_uses(&self.extra_heads, rhs));
+                        for (use_idx, constraint) in uses.enumerate() {
+                            if self.abstract_type_mapper.is_bottom(value)
+                                || self.abstract_type_mapper.is_top(constraint)
+                            {
+                                continue;
+                            }
+                            if let Err(e) = self.check_meet_limit() {
+                                self.worklist = Worklist::default();
+                                return Err(e);
+                            }
+                            self.meets += 1;
+                            self.worklist.meets += 1;
+                            #[cfg(feature = "trace")]
+                            self.trace(trace::TraceEvent::Meet {
+                                lhs,
+                                rhs,
+                                value,
+                                constraint,
+                            });
+                            let met = {
+                                #[cfg(feature = "tracing")]
+                                let _span = tracing::trace_span!(
+                                    "meet",
+                                    lhs = usize::from(lhs),
+                                    rhs = usize::from(rhs)
+                                )
+                                .entered();
+                                self.abstract_type_mapper
+                                    .meet_with(&mut ctx, value, constraint)
+                            };
+                            #[cfg(feature = "trace")]
+                            if let Some(observer) = self.observer.0.as_mut() {
+                                observer.on_meet(lhs, rhs, met.as_deref());
+                            }
+                            match met {
+                                Ok(new_flows) => flows.extend(new_flows),
+                                Err(e) => {
+                                    result = Err(e);
+                                    break 'heads;
+                                }
+                            }
+                            // only the head a value was created with is refined.
+                            if value_idx == 0 && use_idx == 0 {
+                                let tag = self.coerces.and_then(|coerces| {
+                                    coerces(&self.abstract_type_mapper, value, constraint)
+                                });
+                                match tag {
+                                    Some(tag) => self.coerced.push((Value(lhs), Use(rhs), tag)),
+                                    None => {
+                                        refined =
+                                            self.abstract_type_mapper.refine(value, constraint)
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    result.map(|()| flows)
+                }
+                _ => Ok(Vec::new()),
+            };
+            if let Err(e) = self.check_created_nodes(ctx.nodes.len()) {
+                self.worklist = Worklist::default();
+                return Err(e);
+            }
+            for node in ctx.nodes {
+                let id = self.add_node(node);
+                self.meet_nodes.insert(id);
+            }
+            if let Some(head) = refined {
+                self.refine_node(lhs, head);
+            }
 
-            // Check if adding that edge resulted in any new type pairs needing to be checked
-            while let Some((lhs, rhs)) = type_pairs_to_check.pop() {
-                if let TypeNode::Value(lhs_head) = &self.types[lhs] {
-                    if let TypeNode::Use(rhs_head) = &self.types[rhs] {
-                        let new_edges = AT::meet(lhs_head, rhs_head)?;
-                        pending_edges.extend(new_edges.into_iter());
+            match result {
+                Ok(new_edges) => self
+                    .worklist
+                    .extend_edges(new_edges, self.graph_config.queue_duplicates),
+                Err(e) => {
+                    if !self.worklist.accumulate {
+                        self.worklist = Worklist::default();
                     }
+                    self.failed = Some((lhs, rhs));
+                    return Err(e);
                 }
             }
+            return Ok(true);
+        }
+
+        match self.worklist.pop_edge(&mut self.shuffle, order) {
+            Some((lhs, rhs)) => {
+                let (lhs, rhs) = (self.aliases.find(lhs.0), self.aliases.find(rhs.0));
+                let mut new_pairs = self.link(lhs, rhs);
+                self.worklist.derived += new_pairs.len();
+                new_pairs.retain(|&(lhs, rhs)| !self.trivially_satisfied(lhs, rhs));
+                self.worklist
+                    .extend_pairs(new_pairs, self.graph_config.queue_duplicates);
+                if let Err(e) = self.check_edge_limits() {
+                    self.worklist = Worklist::default();
+                    return Err(e);
+                }
+                Ok(true)
+            }
+            None => Ok(!self.guarded.is_empty() && self.fire_guards()),
         }
-        assert!(pending_edges.is_empty() && type_pairs_to_check.is_empty());
-        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_systems::{Range, RangeTypeSystem};
 
+    #[allow(dead_code, clippy::enum_variant_names)]
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum AbstractTypeValue {
         VBool,
         VInteger,
         VFloat,
         VString,
     }
+    #[allow(dead_code, clippy::enum_variant_names)]
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum AbstractTypeUse {
         UBool,
         UInteger,
@@ -119,13 +1026,14 @@ mod tests {
         UString,
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Default)]
     pub struct LiteralTypeSystem;
 
     impl AbstractTypes<AbstractTypeValue, AbstractTypeUse> for LiteralTypeSystem {
         type Error = TypeError;
 
         fn meet(
+            &self,
             lhs: &AbstractTypeValue,
             rhs: &AbstractTypeUse,
         ) -> Result<Vec<(Value, Use)>, Self::Error> {
@@ -148,6 +1056,308 @@ mod tests {
         assert!(t.flow(vid, uid).is_ok());
     }
 
+    #[test]
+    fn handles_should_round_trip_through_raw_indices() {
+        let mut t = TypeChecker::new(LiteralTypeSystem);
+        let vid = t.new_val(AbstractTypeValue::VBool);
+        let uid = t.new_use(AbstractTypeUse::UBool);
+        let (var_val, var_use) = t.var();
+
+        assert_eq!(Ok(vid), t.value_at(vid.index()));
+        assert_eq!(Ok(uid), t.use_at(uid.index()));
+        assert_eq!(Ok(var_val), t.value_at(var_use.index()));
+        assert_eq!(
+            Err(TypeError::HandleInvalid { index: uid.index() }),
+            t.value_at(uid.index())
+        );
+        assert_eq!(Err(TypeError::HandleInvalid { index: 10 }), t.use_at(10));
+
+        let symbols: crate::collections::HashMap<Value, &str> =
+            vec![(vid, "x"), (var_val, "y")].into_iter().collect();
+        assert_eq!(Some(&"y"), symbols.get(&var_val));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn handles_should_serialize_as_raw_indices() {
+        let mut t = TypeChecker::new(LiteralTypeSystem);
+        let vid = t.new_val(AbstractTypeValue::VBool);
+        let uid = t.new_use(AbstractTypeUse::UBool);
+
+        let symbols = vec![("x".to_string(), vid)];
+        let encoded = serde_json::to_string(&(symbols, uid)).unwrap();
+        assert_eq!(r#"[[["x",0]],1]"#, encoded);
+
+        let (symbols, decoded_use): (Vec<(String, Value)>, Use) =
+            serde_json::from_str(&encoded).unwrap();
+        assert_eq!(Ok(vid), t.value_at(symbols[0].1.index()));
+        assert_eq!(Ok(uid), t.use_at(decoded_use.index()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn checkers_should_round_trip_through_serde() {
+        let mut t = TypeChecker::new(LiteralTypeSystem);
+        let (var_val, var_use) = t.var();
+        let vid = t.new_val(AbstractTypeValue::VBool);
+        let uid = t.new_use(AbstractTypeUse::UBool);
+        assert!(t.flow(vid, var_use).is_ok());
+
+        let encoded = serde_json::to_string(&t).unwrap();
+        let mut restored: TypeChecker<_, _, LiteralTypeSystem> =
+            serde_json::from_str(&encoded).unwrap();
+        assert!(restored.reaches(vid, var_use));
+        assert!(restored.flow(var_val, uid).is_ok());
+        assert_eq!(
+            t.r.edges().count() + 2,
+            restored.r.edges().collect::<Vec<_>>().len()
+        );
+    }
+
+    #[cfg(all(feature = "tracing", feature = "std"))]
+    #[test]
+    fn flows_should_open_spans_for_their_edges_and_meets() {
+        use std::sync::{Arc, Mutex};
+        use tracing::span::{Attributes, Id, Record};
+
+        /// Spans records the name of every span opened.
+        struct Spans(Arc<Mutex<Vec<&'static str>>>);
+
+        impl tracing::Subscriber for Spans {
+            fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut names = self.0.lock().unwrap();
+                names.push(span.metadata().name());
+                Id::from_u64(names.len() as u64)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &tracing::Event<'_>) {}
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let mut t = TypeChecker::new(LiteralTypeSystem);
+        let vid = t.new_val(AbstractTypeValue::VBool);
+        let uid = t.new_use(AbstractTypeUse::UBool);
+        tracing::subscriber::with_default(Spans(names.clone()), || {
+            assert!(t.flow(vid, uid).is_ok());
+        });
+        assert_eq!(vec!["flow", "add_edge", "meet"], *names.lock().unwrap());
+    }
+
+    #[test]
+    fn clones_should_share_their_bookkeeping_until_they_diverge() {
+        use crate::test_systems::{Prim, Strict};
+
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var_named("x");
+        let vid = t.new_val(Prim::Bool);
+        let uid = t.new_use(Prim::Bool);
+        t.label_value(vid, "true");
+        assert!(t.flow(vid, x_use).is_ok());
+
+        let mut fork = t.clone();
+        assert!(t.flows.ptr_eq(&fork.flows));
+        assert!(t.direct.ptr_eq(&fork.direct));
+        assert!(t.recorded.ptr_eq(&fork.recorded));
+
+        assert!(fork.flow(x_val, uid).is_ok());
+        assert!(!t.flows.ptr_eq(&fork.flows));
+        assert!(!t.direct.ptr_eq(&fork.direct));
+        assert!(t.labels.ptr_eq(&fork.labels));
+        assert!(t.named_vars.ptr_eq(&fork.named_vars));
+        assert!(fork.reaches(vid, uid));
+        assert!(!t.reaches(vid, uid));
+        assert_eq!(1, t.flows.len());
+    }
+
+    #[test]
+    fn worklist_orders_should_queue_each_flow_once() {
+        for &order in &[WorklistOrder::Lifo, WorklistOrder::Fifo] {
+            let config = GraphConfig {
+                worklist_order: order,
+                ..GraphConfig::default()
+            };
+            let mut t = TypeChecker::new_with_config(LiteralTypeSystem, config);
+            let vid = t.new_val(AbstractTypeValue::VBool);
+            let (x_val, x_use) = t.var();
+            let uid = t.new_use(AbstractTypeUse::UBool);
+
+            let session = t.begin_flow(vec![(vid, x_use), (x_val, uid), (vid, x_use)]);
+            let mut expected = vec![(vid, x_use), (x_val, uid)];
+            if order == WorklistOrder::Lifo {
+                expected.reverse();
+            }
+            assert_eq!(expected, session.pending_flows().collect::<Vec<_>>());
+            assert!(session.finish().is_ok());
+            assert_eq!(3, t.r.edges().count());
+            assert_eq!(1, t.stats().meets);
+        }
+    }
+
+    #[test]
+    fn queueing_duplicates_should_keep_work_queued_twice() {
+        let config = GraphConfig {
+            queue_duplicates: true,
+            ..GraphConfig::default()
+        };
+        let mut t = TypeChecker::new_with_config(LiteralTypeSystem, config);
+        let vid = t.new_val(AbstractTypeValue::VBool);
+        let (x_val, x_use) = t.var();
+        let uid = t.new_use(AbstractTypeUse::UBool);
+
+        let session = t.begin_flow(vec![(vid, x_use), (x_val, uid), (vid, x_use)]);
+        assert_eq!(3, session.pending_flows().count());
+        assert!(session.finish().is_ok());
+        assert_eq!(3, t.r.edges().count());
+        assert_eq!(1, t.stats().meets);
+    }
+
+    #[test]
+    fn graph_configs_should_skip_self_edges_and_warn_on_fan_out() {
+        let config = GraphConfig {
+            skip_self_edges: true,
+            max_out_degree: Some(1),
+            ..GraphConfig::default()
+        };
+        let mut t = TypeChecker::new_with_config(LiteralTypeSystem, config);
+        let (x_val, x_use) = t.var();
+        let (y_val, y_use) = t.var();
+        assert!(t.flow(x_val, x_use).is_ok());
+        assert_eq!(0, t.r.edges().count());
+        assert!(t.warnings().is_empty());
+
+        assert!(t.flow(x_val, y_use).is_ok());
+        assert!(t.flow(y_val, x_use).is_ok());
+        assert_eq!(2, t.r.edges().count());
+        assert_eq!(vec![y_val, x_val], t.cycles_through(x_val));
+
+        // x and y both reach the new use, so both pass the maximum.
+        let uid = t.new_use(AbstractTypeUse::UBool);
+        assert!(t.flow(x_val, uid).is_ok());
+        let warned: Vec<EntityId> = t
+            .warnings()
+            .iter()
+            .map(|warning| match *warning {
+                FlowWarning::HighOutDegree { entity, degree } => {
+                    assert_eq!(2, degree);
+                    entity
+                }
+            })
+            .collect();
+        assert_eq!(vec![x_val.0, y_val.0], warned);
+    }
+
+    #[test]
+    fn repeated_flows_should_only_be_logged_once() {
+        let mut t = TypeChecker::new(LiteralTypeSystem);
+        let vid = t.new_val(AbstractTypeValue::VBool);
+        let (x_val, x_use) = t.var();
+        let uid = t.new_use(AbstractTypeUse::UBool);
+        for _ in 0..100 {
+            assert!(t.flow(vid, x_use).is_ok());
+            assert!(t.flow(x_val, uid).is_ok());
+        }
+        assert_eq!(2, t.flows.len());
+        assert_eq!(2, t.direct.len());
+        assert_eq!(3, t.r.edges().count());
+    }
+
+    /// A type system configured to let integers flow into floats.
+    #[derive(Debug)]
+    struct CoercingTypeSystem {
+        int_to_float: bool,
+    }
+
+    impl AbstractTypes<AbstractTypeValue, AbstractTypeUse> for CoercingTypeSystem {
+        type Error = TypeError;
+
+        fn meet(
+            &self,
+            lhs: &AbstractTypeValue,
+            rhs: &AbstractTypeUse,
+        ) -> Result<Vec<(Value, Use)>, Self::Error> {
+            match (lhs, rhs) {
+                (&AbstractTypeValue::VInteger, &AbstractTypeUse::UFloat) if self.int_to_float => {
+                    Ok(vec![])
+                }
+                _ => LiteralTypeSystem.meet(lhs, rhs),
+            }
+        }
+    }
+
+    #[test]
+    fn meets_should_see_the_type_system_configuration() {
+        for &int_to_float in &[true, false] {
+            let mut t = TypeChecker::new(CoercingTypeSystem { int_to_float });
+            let int = t.new_val(AbstractTypeValue::VInteger);
+            let float_use = t.new_use(AbstractTypeUse::UFloat);
+            assert_eq!(int_to_float, t.flow(int, float_use).is_ok());
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Lattice {
+        Any,
+        Never,
+        Int,
+        Bool,
+    }
+
+    /// Never satisfies every use and every value satisfies Any, so neither
+    /// is ever met.
+    #[derive(Debug)]
+    struct LatticeTypeSystem;
+
+    impl AbstractTypes<Lattice, Lattice> for LatticeTypeSystem {
+        type Error = TypeError;
+
+        fn meet(&self, lhs: &Lattice, rhs: &Lattice) -> Result<Vec<(Value, Use)>, Self::Error> {
+            match (lhs, rhs) {
+                (Lattice::Never, _) | (_, Lattice::Any) => unreachable!("trivial heads are met"),
+                _ if lhs == rhs => Ok(vec![]),
+                _ => Err(TypeError::Converge),
+            }
+        }
+
+        fn is_bottom(&self, lhs: &Lattice) -> bool {
+            *lhs == Lattice::Never
+        }
+
+        fn is_top(&self, rhs: &Lattice) -> bool {
+            *rhs == Lattice::Any
+        }
+    }
+
+    #[test]
+    fn trivially_satisfied_heads_should_not_be_met() {
+        let mut t = TypeChecker::new(LatticeTypeSystem);
+        let never = t.new_val(Lattice::Never);
+        let int = t.new_val(Lattice::Int);
+        let (x_val, x_use) = t.var();
+        let any = t.new_use(Lattice::Any);
+        let int_use = t.new_use(Lattice::Int);
+        assert!(t.flow(never, x_use).is_ok());
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, any).is_ok());
+        assert!(t.flow(x_val, int_use).is_ok());
+        assert!(t.reaches(never, any));
+        assert_eq!(1, t.meets);
+
+        // a trivial head doesn't excuse the other heads of its node.
+        assert!(t.add_use_head(any, Lattice::Bool).is_err());
+    }
+
     #[test]
     fn type_mismatch() {
         let mut t = TypeChecker::new(LiteralTypeSystem);
@@ -155,4 +1365,73 @@ mod tests {
         let uid = t.new_use(AbstractTypeUse::UFloat);
         assert!(t.flow(vid, uid).is_err());
     }
+
+    #[derive(Debug, Clone)]
+    enum Boxed {
+        Boxed(Value),
+        Unboxed(Use),
+    }
+
+    #[derive(Debug)]
+    struct BoxTypeSystem;
+
+    impl AbstractTypes<Boxed, Boxed> for BoxTypeSystem {
+        type Error = TypeError;
+
+        fn meet(&self, _: &Boxed, _: &Boxed) -> Result<Vec<(Value, Use)>, Self::Error> {
+            unreachable!("meet_with is overridden")
+        }
+
+        fn meet_with(
+            &self,
+            ctx: &mut MeetContext<Boxed, Boxed>,
+            lhs: &Boxed,
+            rhs: &Boxed,
+        ) -> Result<Vec<(Value, Use)>, Self::Error> {
+            match (lhs, rhs) {
+                // route the contents through a fresh intermediate var.
+                (Boxed::Boxed(contents), Boxed::Unboxed(target)) => {
+                    let (mid_val, mid_use) = ctx.var();
+                    Ok(vec![(*contents, mid_use), (mid_val, *target)])
+                }
+                _ => Err(TypeError::Converge),
+            }
+        }
+    }
+
+    #[test]
+    fn meets_should_be_able_to_refine_value_heads() {
+        let mut t = TypeChecker::new(RangeTypeSystem);
+        let (x_val, x_use) = t.var();
+        let number = t.new_val(Range(0, 10));
+        assert!(t.flow(number, x_use).is_ok());
+
+        let small = t.new_use(Range(-5, 3));
+        assert!(t.flow(x_val, small).is_ok());
+        assert_eq!(Some(&Range(0, 3)), t.head_of_value(number));
+
+        let large = t.new_use(Range(5, 20));
+        assert_eq!(Err(TypeError::Converge), t.flow(x_val, large));
+    }
+
+    #[test]
+    fn meets_should_be_able_to_create_nodes() {
+        let mut t = TypeChecker::new(BoxTypeSystem);
+        let (contents, _) = t.var();
+        let (_, target) = t.var();
+        let boxed = t.new_val(Boxed::Boxed(contents));
+        let unboxed = t.new_use(Boxed::Unboxed(target));
+
+        assert!(t.flow(boxed, unboxed).is_ok());
+        assert_eq!(5, t.types.len());
+        assert!(t.reaches(contents, target));
+
+        // retracting the flow discards the var its meet created.
+        assert!(t.retract(boxed, unboxed).is_ok());
+        assert!(t.is_removed(4));
+        assert!(!t.reaches(contents, target));
+        assert!(t.flow(boxed, unboxed).is_ok());
+        assert!(t.reaches(contents, target));
+        assert_eq!(1, t.meet_nodes.len());
+    }
 }
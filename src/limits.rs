@@ -0,0 +1,308 @@
+use crate::{
+    AbstractTypes, EntityId, GraphConfig, NodeIndex, TypeChecker, TypeError, TypeNode, Use, Value,
+};
+
+/// Limits caps the resources a checker may use, so that degenerate or
+/// adversarial input fails with a diagnostic rather than exhausting memory.
+/// Every limit is unset by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// the most nodes the checker may hold.
+    pub max_nodes: Option<usize>,
+    /// the most edges the graph may hold, including derived ones.
+    pub max_edges: Option<usize>,
+    /// the most edges a single call to `flow` may add.
+    pub max_derived_per_flow: Option<usize>,
+    /// the most pairs of heads a single call to `flow` may meet. This bounds
+    /// type systems whose meets can keep creating new heads forever, which
+    /// then fail with `TypeError::Diverged` rather than looping.
+    pub max_meets_per_flow: Option<usize>,
+}
+
+/// LimitConfig pairs the limits of a checker with the conversion used to
+/// report them as the type system's error.
+pub(crate) struct LimitConfig<V, U, AT, I>
+where
+    AT: AbstractTypes<V, U, I>,
+{
+    limits: Limits,
+    error: fn(TypeError) -> AT::Error,
+}
+
+impl<V, U, AT, I> Clone for LimitConfig<V, U, AT, I>
+where
+    AT: AbstractTypes<V, U, I>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            limits: self.limits,
+            error: self.error,
+        }
+    }
+}
+
+impl<V, U, AT, I> core::fmt::Debug for LimitConfig<V, U, AT, I>
+where
+    AT: AbstractTypes<V, U, I>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.limits.fmt(f)
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+    AT::Error: From<TypeError>,
+{
+    /// Creates a checker enforcing resource limits. Exceeding an edge limit
+    /// fails the flow responsible with `TypeError::LimitExceeded`, converted
+    /// into the type system's error, and discards the flow's remaining work.
+    /// Node limits are enforced by `try_new_val`, `try_new_use` and
+    /// `try_var`, and on the nodes meets create, while the infallible
+    /// constructors ignore them.
+    pub fn with_limits(abstract_type_mapper: AT, limits: Limits) -> Self {
+        let mut checker = Self::new_with_index(abstract_type_mapper, GraphConfig::default());
+        checker.set_limits(limits);
+        checker
+    }
+
+    /// Enforces resource limits on an existing checker, as `with_limits`
+    /// does, so that limits can be combined with any other constructor.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = Some(LimitConfig {
+            limits,
+            error: AT::Error::from,
+        });
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns the configured limits, if any.
+    pub fn limits(&self) -> Option<&Limits> {
+        self.limits.as_ref().map(|config| &config.limits)
+    }
+
+    /// Fails once the configured node limit is reached, or once the node
+    /// index can't number another node.
+    fn check_node_limit(&self) -> Result<(), TypeError> {
+        let nodes = self.types.len();
+        match self.limits().and_then(|limits| limits.max_nodes) {
+            Some(limit) if nodes >= limit => Err(TypeError::LimitExceeded {
+                resource: "nodes",
+                limit,
+            }),
+            _ if EntityId::<I>::from_index(nodes).is_none() => Err(TypeError::LimitExceeded {
+                resource: "nodes",
+                limit: nodes,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Creates a value unless the node limit has been reached.
+    pub fn try_new_val(&mut self, val_type: V) -> Result<Value<I>, TypeError> {
+        self.check_node_limit()?;
+        Ok(self.new_val(val_type))
+    }
+
+    /// Creates a use unless the node limit has been reached.
+    pub fn try_new_use(&mut self, constraint: U) -> Result<Use<I>, TypeError> {
+        self.check_node_limit()?;
+        Ok(self.new_use(constraint))
+    }
+
+    /// Creates a var unless the node limit has been reached.
+    pub fn try_var(&mut self) -> Result<(Value<I>, Use<I>), TypeError> {
+        self.check_node_limit()?;
+        let i = self.alloc_node(TypeNode::Var);
+        Ok((Value(i), Use(i)))
+    }
+
+    /// Fails if adding the nodes a meet created would outgrow the node limit.
+    pub(crate) fn check_created_nodes(&self, created: usize) -> Result<(), AT::Error> {
+        match &self.limits {
+            Some(config) => match config.limits.max_nodes {
+                Some(limit) if created > 0 && self.types.len() + created > limit => {
+                    Err((config.error)(TypeError::LimitExceeded {
+                        resource: "nodes",
+                        limit,
+                    }))
+                }
+                _ => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Fails if the graph has outgrown the edge limits.
+    pub(crate) fn check_edge_limits(&self) -> Result<(), AT::Error> {
+        let (limits, error) = match &self.limits {
+            Some(config) => (&config.limits, config.error),
+            None => return Ok(()),
+        };
+
+        let exceeded = |resource, limit: Option<usize>, used| match limit {
+            Some(limit) if used > limit => Err(error(TypeError::LimitExceeded { resource, limit })),
+            _ => Ok(()),
+        };
+        exceeded("edges", limits.max_edges, self.r.edge_count())?;
+        exceeded(
+            "derived edges",
+            limits.max_derived_per_flow,
+            self.worklist.derived,
+        )
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Fails if the current flow has used up its budget of meets.
+    pub(crate) fn check_meet_limit(&self) -> Result<(), AT::Error> {
+        match &self.limits {
+            Some(config) => match config.limits.max_meets_per_flow {
+                Some(limit) if self.worklist.meets >= limit => {
+                    Err((config.error)(TypeError::Diverged { meets: limit }))
+                }
+                _ => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::Permissive;
+    use crate::*;
+
+    #[test]
+    fn limits_should_fail_gracefully() {
+        let limits = Limits {
+            max_nodes: Some(4),
+            max_derived_per_flow: Some(2),
+            ..Limits::default()
+        };
+        let mut t = TypeChecker::with_limits(Permissive, limits);
+        let value = t.try_new_val(()).unwrap();
+        let (a_val, a_use) = t.try_var().unwrap();
+        let (b_val, b_use) = t.try_var().unwrap();
+        let sink = t.try_new_use(()).unwrap();
+        assert_eq!(
+            Err(TypeError::LimitExceeded {
+                resource: "nodes",
+                limit: 4
+            }),
+            t.try_var()
+        );
+
+        assert!(t.flow(value, a_use).is_ok());
+        assert!(t.flow(b_val, sink).is_ok());
+        // joining the two chains derives four edges at once.
+        assert_eq!(
+            Err(TypeError::LimitExceeded {
+                resource: "derived edges",
+                limit: 2
+            }),
+            t.flow(a_val, b_use)
+        );
+    }
+
+    /// Relates any heads, over any node index.
+    #[derive(Debug)]
+    struct Any;
+
+    impl<I> AbstractTypes<(), (), I> for Any {
+        type Error = TypeError;
+
+        fn meet(&self, _: &(), _: &()) -> Result<Vec<(Value<I>, Use<I>)>, Self::Error> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn nodes_past_the_index_should_fail_gracefully() {
+        let mut t = TypeChecker::<(), (), _, u16>::new_with_index(Any, GraphConfig::default());
+        let (first, _) = t.try_var().unwrap();
+        for _ in 1..u16::MAX {
+            assert!(t.try_var().is_ok());
+        }
+        let (_, last) = t.try_var().unwrap();
+        assert_eq!(usize::from(u16::MAX), last.index());
+        assert_eq!(
+            Err(TypeError::LimitExceeded {
+                resource: "nodes",
+                limit: 1 << 16
+            }),
+            t.try_var().map(|_| ())
+        );
+        assert!(t.flow(first, last).is_ok());
+    }
+
+    /// Counts up forever: every number meeting the sink creates its
+    /// successor and flows it into the sink too.
+    #[derive(Debug)]
+    struct Nat;
+
+    impl AbstractTypes<usize, Use> for Nat {
+        type Error = TypeError;
+
+        fn meet(&self, _: &usize, _: &Use) -> Result<Vec<(Value, Use)>, Self::Error> {
+            unreachable!("meet_with is overridden")
+        }
+
+        fn meet_with(
+            &self,
+            ctx: &mut MeetContext<usize, Use>,
+            lhs: &usize,
+            rhs: &Use,
+        ) -> Result<Vec<(Value, Use)>, Self::Error> {
+            let next = ctx.new_val(lhs + 1);
+            Ok(vec![(next, *rhs)])
+        }
+    }
+
+    #[test]
+    fn divergent_meets_should_exhaust_their_budget() {
+        let limits = Limits {
+            max_meets_per_flow: Some(50),
+            ..Limits::default()
+        };
+        let mut t = TypeChecker::with_limits(Nat, limits);
+        let zero = t.new_val(0);
+        let (x_val, x_use) = t.var();
+        let sink = t.new_use(x_use);
+        assert!(t.flow(x_val, sink).is_ok());
+        assert_eq!(Err(TypeError::Diverged { meets: 50 }), t.flow(zero, x_use));
+        assert!(t.types.len() < 60);
+    }
+
+    #[test]
+    fn nodes_created_by_meets_should_count_against_the_limit() {
+        let mut t = TypeChecker::new_with_config(Nat, GraphConfig::default());
+        t.set_limits(Limits {
+            max_nodes: Some(40),
+            ..Limits::default()
+        });
+        let zero = t.new_val(0);
+        let (x_val, x_use) = t.var();
+        let sink = t.new_use(x_use);
+        assert!(t.flow(x_val, sink).is_ok());
+        assert_eq!(
+            Err(TypeError::LimitExceeded {
+                resource: "nodes",
+                limit: 40
+            }),
+            t.flow(zero, x_use)
+        );
+        assert_eq!(40, t.types.len());
+    }
+}
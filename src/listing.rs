@@ -0,0 +1,204 @@
+use crate::collections::HashSet;
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, TypeNode};
+
+/// TypeNodeRef borrows the head of a node, or marks it as a var.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeNodeRef<'a, V, U> {
+    Var,
+    Value(&'a V),
+    Use(&'a U),
+}
+
+/// EdgeKind tells an edge added directly, by the caller or by a meet, apart
+/// from one only added to close over the direct ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    Direct,
+    Derived,
+}
+
+/// GraphListing is the flow graph as plain lists of nodes and edges, in the
+/// shape graph libraries such as petgraph build their graphs from, so that
+/// standard algorithms can be run over it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphListing<'a, V, U, I = u32> {
+    /// every node that hasn't been removed, in index order.
+    pub nodes: Vec<(EntityId<I>, TypeNodeRef<'a, V, U>)>,
+    /// every edge of the closed graph, ordered by source node.
+    pub edges: Vec<(EntityId<I>, EntityId<I>, EdgeKind)>,
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Lists the nodes and edges of the flow graph. The `petgraph` feature
+    /// builds a petgraph `DiGraph` from the listing with `to_petgraph`.
+    pub fn graph_listing(&self) -> GraphListing<'_, V, U, I> {
+        let nodes = self
+            .r
+            .nodes()
+            .map(|id| {
+                let node = match &self.types[usize::from(id)] {
+                    TypeNode::Var => TypeNodeRef::Var,
+                    TypeNode::Value(head) => TypeNodeRef::Value(head),
+                    TypeNode::Use(head) => TypeNodeRef::Use(head),
+                };
+                (id, node)
+            })
+            .collect();
+        let direct: HashSet<(EntityId<I>, EntityId<I>)> = self.direct.iter().copied().collect();
+        let edges = self
+            .r
+            .edges()
+            .map(|edge| {
+                let kind = if direct.contains(&edge) {
+                    EdgeKind::Direct
+                } else {
+                    EdgeKind::Derived
+                };
+                (edge.0, edge.1, kind)
+            })
+            .collect();
+        GraphListing { nodes, edges }
+    }
+}
+
+#[cfg(feature = "petgraph")]
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Builds a petgraph `DiGraph` of the flow graph from its listing. Nodes
+    /// are added in index order, so each keeps its index as long as no node
+    /// was removed.
+    pub fn to_petgraph(&self) -> petgraph::graph::DiGraph<TypeNodeRef<'_, V, U>, EdgeKind> {
+        let listing = self.graph_listing();
+        let mut graph =
+            petgraph::graph::DiGraph::with_capacity(listing.nodes.len(), listing.edges.len());
+        let index: crate::collections::HashMap<EntityId<I>, petgraph::graph::NodeIndex> = listing
+            .nodes
+            .into_iter()
+            .map(|(id, node)| (id, graph.add_node(node)))
+            .collect();
+        for (lhs, rhs, kind) in listing.edges {
+            graph.add_edge(index[&lhs], index[&rhs], kind);
+        }
+        graph
+    }
+
+    /// Builds a checker from a petgraph `DiGraph` such as one returned by
+    /// `to_petgraph`, creating a node for each of its nodes in index order
+    /// and adding a flow for each direct edge, so that the derived edges are
+    /// closed over again. Meets that create nodes create them anew.
+    ///
+    /// Panics if a direct edge leaves a use or enters a value.
+    pub fn from_petgraph(
+        abstract_type_mapper: AT,
+        graph: &petgraph::graph::DiGraph<TypeNodeRef<'_, V, U>, EdgeKind>,
+    ) -> Result<Self, AT::Error>
+    where
+        V: Clone,
+        U: Clone,
+    {
+        let mut checker = Self::new_with_index(abstract_type_mapper, crate::GraphConfig::default());
+        for node in graph.node_weights() {
+            match *node {
+                TypeNodeRef::Var => {
+                    checker.var();
+                }
+                TypeNodeRef::Value(head) => {
+                    checker.new_val(head.clone());
+                }
+                TypeNodeRef::Use(head) => {
+                    checker.new_use(head.clone());
+                }
+            }
+        }
+        for edge in graph.raw_edges() {
+            if edge.weight == EdgeKind::Direct {
+                let lhs = checker
+                    .value_at(edge.source().index())
+                    .expect("direct edges leave values or vars");
+                let rhs = checker
+                    .use_at(edge.target().index())
+                    .expect("direct edges enter uses or vars");
+                checker.flow(lhs, rhs)?;
+            }
+        }
+        Ok(checker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn listings_should_tell_direct_edges_from_derived_ones() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let (x_val, x_use) = t.var();
+        let sink = t.new_use(Prim::Int);
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+
+        let listing = t.graph_listing();
+        let id = EntityId::at;
+        assert_eq!(
+            vec![
+                (id(0), TypeNodeRef::Value(&Prim::Int)),
+                (id(1), TypeNodeRef::Var),
+                (id(2), TypeNodeRef::Use(&Prim::Int)),
+            ],
+            listing.nodes
+        );
+        assert_eq!(
+            vec![
+                (id(0), id(1), EdgeKind::Direct),
+                (id(0), id(2), EdgeKind::Derived),
+                (id(1), id(2), EdgeKind::Direct),
+            ],
+            listing.edges
+        );
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn checkers_should_round_trip_through_petgraph() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let (x_val, x_use) = t.var();
+        let sink = t.new_use(Prim::Int);
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+
+        let graph = t.to_petgraph();
+        assert_eq!(3, graph.node_count());
+        assert_eq!(3, graph.edge_count());
+        let (int_idx, sink_idx) = (
+            petgraph::graph::NodeIndex::new(int.index()),
+            petgraph::graph::NodeIndex::new(sink.index()),
+        );
+        assert!(petgraph::algo::has_path_connecting(
+            &graph, int_idx, sink_idx, None
+        ));
+        let derived = graph.find_edge(int_idx, sink_idx).unwrap();
+        assert_eq!(EdgeKind::Derived, graph[derived]);
+
+        let rebuilt = TypeChecker::from_petgraph(Strict, &graph).unwrap();
+        assert!(t.snapshot().diff(&rebuilt.snapshot()).is_empty());
+        assert!(rebuilt.snapshot().diff(&t.snapshot()).is_empty());
+
+        // a bool added to the graph can't flow into the sink.
+        let mut graph = graph;
+        let boolean = graph.add_node(TypeNodeRef::Value(&Prim::Bool));
+        graph.add_edge(boolean, sink_idx, EdgeKind::Direct);
+        let rebuilt = TypeChecker::from_petgraph(Strict, &graph).map(|_| ());
+        assert_eq!(Err(TypeError::Converge), rebuilt);
+    }
+}
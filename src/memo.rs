@@ -0,0 +1,136 @@
+use crate::collections::{HashMap, Key};
+use crate::prelude::*;
+use crate::{AbstractTypes, MeetContext, NodeIndex, Use, Value};
+use core::cell::{Cell, RefCell};
+
+/// The flows returned by every meet remembered so far.
+type Cache<V, U, I> = HashMap<(V, U), Vec<(Value<I>, Use<I>)>>;
+
+/// Memoized wraps a type system, remembering the flows returned by every
+/// successful meet so that later meets of equal heads reuse them instead of
+/// being checked again. This pays off when many distinct nodes share the
+/// same heads and meeting them is expensive. It relies on meets returning
+/// the same flows for the same heads, which the checker already assumes.
+/// Meets that create nodes, and meets that fail, are never remembered.
+#[derive(Debug)]
+pub struct Memoized<V, U, AT, I = u32> {
+    inner: AT,
+    cache: RefCell<Cache<V, U, I>>,
+    hits: Cell<usize>,
+}
+
+impl<V, U, AT, I> Memoized<V, U, AT, I> {
+    pub fn new(inner: AT) -> Self {
+        Self {
+            inner,
+            cache: RefCell::new(HashMap::new()),
+            hits: Cell::new(0),
+        }
+    }
+
+    /// Returns the wrapped type system.
+    pub fn inner(&self) -> &AT {
+        &self.inner
+    }
+
+    /// Returns the number of meets answered from the cache so far.
+    pub fn hits(&self) -> usize {
+        self.hits.get()
+    }
+}
+
+impl<V, U, AT: Default, I> Default for Memoized<V, U, AT, I> {
+    fn default() -> Self {
+        Self::new(AT::default())
+    }
+}
+
+impl<V, U, AT, I> AbstractTypes<V, U, I> for Memoized<V, U, AT, I>
+where
+    I: NodeIndex,
+    V: Clone + Key,
+    U: Clone + Key,
+    AT: AbstractTypes<V, U, I>,
+{
+    type Error = AT::Error;
+
+    fn meet(&self, lhs: &V, rhs: &U) -> Result<Vec<(Value<I>, Use<I>)>, Self::Error> {
+        self.inner.meet(lhs, rhs)
+    }
+
+    fn meet_with(
+        &self,
+        ctx: &mut MeetContext<V, U, I>,
+        lhs: &V,
+        rhs: &U,
+    ) -> Result<Vec<(Value<I>, Use<I>)>, Self::Error> {
+        let key = (lhs.clone(), rhs.clone());
+        if let Some(flows) = self.cache.borrow().get(&key) {
+            self.hits.set(self.hits.get() + 1);
+            return Ok(flows.clone());
+        }
+
+        let created = ctx.nodes.len();
+        let flows = self.inner.meet_with(ctx, lhs, rhs)?;
+        if ctx.nodes.len() == created {
+            self.cache.borrow_mut().insert(key, flows.clone());
+        }
+        Ok(flows)
+    }
+
+    fn refine(&self, lhs: &V, rhs: &U) -> Option<V> {
+        self.inner.refine(lhs, rhs)
+    }
+
+    fn is_bottom(&self, lhs: &V) -> bool {
+        self.inner.is_bottom(lhs)
+    }
+
+    fn is_top(&self, rhs: &U) -> bool {
+        self.inner.is_top(rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TypeChecker, TypeError};
+
+    #[derive(Debug, Default)]
+    struct Counted {
+        meets: Cell<usize>,
+    }
+
+    impl AbstractTypes<u8, u8> for Counted {
+        type Error = TypeError;
+
+        fn meet(&self, lhs: &u8, rhs: &u8) -> Result<Vec<(Value, Use)>, Self::Error> {
+            self.meets.set(self.meets.get() + 1);
+            if lhs <= rhs {
+                Ok(vec![])
+            } else {
+                Err(TypeError::Converge)
+            }
+        }
+    }
+
+    #[test]
+    fn equal_heads_should_only_be_met_once() {
+        let mut t = TypeChecker::new(Memoized::new(Counted::default()));
+        let (x_val, x_use) = t.var();
+        for _ in 0..3 {
+            let small = t.new_val(1);
+            assert!(t.flow(small, x_use).is_ok());
+        }
+        let sink = t.new_use(2);
+        assert!(t.flow(x_val, sink).is_ok());
+        assert_eq!(3, t.stats().meets);
+
+        let large = t.new_val(3);
+        assert_eq!(Err(TypeError::Converge), t.flow(large, x_use));
+        // idempotence checking in tests repeats meets, which also hit.
+        let mapper = &t.abstract_type_mapper;
+        assert_eq!(2, mapper.inner().meets.get());
+        assert!(mapper.hits() >= 2);
+    }
+}
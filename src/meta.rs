@@ -0,0 +1,118 @@
+use crate::collections::HashMap;
+use crate::{AbstractTypes, EntityId, FlowError, TypeChecker, Use, Value};
+
+/// MetaFailure reports a failed flow along with the metadata of the value
+/// and use heads that couldn't be met, such as their source spans.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaFailure<E, M> {
+    pub failure: FlowError<E>,
+    pub lhs_meta: Option<M>,
+    pub rhs_meta: Option<M>,
+}
+
+impl<E: core::fmt::Display, M> core::fmt::Display for MetaFailure<E, M> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.failure)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error, M: core::fmt::Debug> std::error::Error for MetaFailure<E, M> {}
+
+/// MetaChecker wraps a TypeChecker, attaching user metadata to the nodes it
+/// creates so that failures can be reported against them without a side
+/// table in the frontend.
+#[derive(Debug, Clone)]
+pub struct MetaChecker<V, U, AT, M>
+where
+    AT: AbstractTypes<V, U>,
+{
+    checker: TypeChecker<V, U, AT>,
+    meta: HashMap<EntityId, M>,
+}
+
+impl<V, U, AT, M> MetaChecker<V, U, AT, M>
+where
+    AT: AbstractTypes<V, U>,
+    M: Clone,
+{
+    pub fn new(abstract_type_mapper: AT) -> Self {
+        Self {
+            checker: TypeChecker::new(abstract_type_mapper),
+            meta: HashMap::new(),
+        }
+    }
+
+    /// Returns the wrapped checker.
+    pub fn checker(&self) -> &TypeChecker<V, U, AT> {
+        &self.checker
+    }
+
+    /// Returns the wrapped checker mutably. Nodes created through it have no
+    /// metadata.
+    pub fn checker_mut(&mut self) -> &mut TypeChecker<V, U, AT> {
+        &mut self.checker
+    }
+
+    pub fn new_val_with_meta(&mut self, val_type: V, meta: M) -> Value {
+        let value = self.checker.new_val(val_type);
+        self.meta.insert(value.0, meta);
+        value
+    }
+
+    pub fn new_use_with_meta(&mut self, constraint: U, meta: M) -> Use {
+        let rhs = self.checker.new_use(constraint);
+        self.meta.insert(rhs.0, meta);
+        rhs
+    }
+
+    pub fn var_with_meta(&mut self, meta: M) -> (Value, Use) {
+        let (value, var_use) = self.checker.var();
+        self.meta.insert(value.0, meta);
+        (value, var_use)
+    }
+
+    /// Returns the metadata of the node behind a value.
+    pub fn value_meta(&self, value: Value) -> Option<&M> {
+        self.meta.get(&value.0)
+    }
+
+    /// Returns the metadata of the node behind a use.
+    pub fn use_meta(&self, rhs: Use) -> Option<&M> {
+        self.meta.get(&rhs.0)
+    }
+
+    /// Adds a flow like `TypeChecker::flow_traced`, attaching the metadata
+    /// of the heads that couldn't be met to any failure.
+    pub fn flow(&mut self, lhs: Value, rhs: Use) -> Result<(), MetaFailure<AT::Error, M>> {
+        self.checker.flow_traced(lhs, rhs).map_err(|failure| {
+            let lhs_meta = self.value_meta(failure.lhs).cloned();
+            let rhs_meta = self.use_meta(failure.rhs).cloned();
+            MetaFailure {
+                failure,
+                lhs_meta,
+                rhs_meta,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::Strict;
+
+    #[test]
+    fn failures_should_carry_the_metadata_of_their_heads() {
+        let mut t = MetaChecker::new(Strict);
+        let truthy = t.new_val_with_meta(true, 1..5);
+        let (x_val, x_use) = t.var_with_meta(8..9);
+        let falsy = t.new_use_with_meta(false, 12..20);
+        assert_eq!(Some(&(8..9)), t.value_meta(x_val));
+
+        assert!(t.flow(truthy, x_use).is_ok());
+        let failure = t.flow(x_val, falsy).unwrap_err();
+        assert_eq!(Some(1..5), failure.lhs_meta);
+        assert_eq!(Some(12..20), failure.rhs_meta);
+    }
+}
@@ -0,0 +1,167 @@
+//! Observers of the checker's work, for tools such as step-by-step
+//! debuggers that need to keep state of their own between events, which a
+//! `Tracer` can't. Enabled by the `trace` feature along with tracing.
+
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, Flow, NodeIndex, TypeChecker};
+
+/// FlowObserver is told about every edge the checker adds and every pair of
+/// heads it meets, as it happens. Every method does nothing unless
+/// overridden, so an observer only implements the events it needs.
+/// Observers are `Send` and `Sync` so that the checkers holding them are.
+pub trait FlowObserver<E, I = u32>: Send + Sync {
+    /// An edge was added to the graph by the caller or by a meet.
+    fn on_direct_edge(&mut self, lhs: EntityId<I>, rhs: EntityId<I>) {
+        let _ = (lhs, rhs);
+    }
+
+    /// An edge was added to the graph only to close over a direct one.
+    fn on_derived_edge(&mut self, lhs: EntityId<I>, rhs: EntityId<I>) {
+        let _ = (lhs, rhs);
+    }
+
+    /// The heads of two nodes were met, yielding the flows between their
+    /// children or the error they failed with.
+    fn on_meet(&mut self, lhs: EntityId<I>, rhs: EntityId<I>, result: Result<&[Flow<I>], &E>) {
+        let _ = (lhs, rhs, result);
+    }
+}
+
+/// Observer holds the observer attached to a checker, if any. Clones of a
+/// checker aren't observed, as observers can't be cloned.
+pub(crate) struct Observer<V, U, AT, I>(pub(crate) Option<Box<dyn FlowObserver<AT::Error, I>>>)
+where
+    AT: AbstractTypes<V, U, I>;
+
+impl<V, U, AT, I> Default for Observer<V, U, AT, I>
+where
+    AT: AbstractTypes<V, U, I>,
+{
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<V, U, AT, I> Clone for Observer<V, U, AT, I>
+where
+    AT: AbstractTypes<V, U, I>,
+{
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<V, U, AT, I> core::fmt::Debug for Observer<V, U, AT, I>
+where
+    AT: AbstractTypes<V, U, I>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "Some(FlowObserver)"),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Tells the observer about every event from now on, or detaches the
+    /// current observer if it is None, returning the one it replaces.
+    pub fn set_observer(
+        &mut self,
+        observer: Option<Box<dyn FlowObserver<AT::Error, I>>>,
+    ) -> Option<Box<dyn FlowObserver<AT::Error, I>>> {
+        core::mem::replace(&mut self.observer.0, observer)
+    }
+
+    /// Tells the observer about an edge added directly and the pairs of
+    /// nodes it newly connected.
+    pub(crate) fn observe_edges(
+        &mut self,
+        lhs: EntityId<I>,
+        rhs: EntityId<I>,
+        added: bool,
+        new_pairs: &[(EntityId<I>, EntityId<I>)],
+    ) {
+        if let Some(observer) = self.observer.0.as_mut() {
+            if added {
+                observer.on_direct_edge(lhs, rhs);
+            }
+            for &(from, to) in new_pairs.iter().filter(|&&pair| pair != (lhs, rhs)) {
+                observer.on_derived_edge(from, to);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl FlowObserver<TypeError> for Recorder {
+        fn on_direct_edge(&mut self, lhs: EntityId, rhs: EntityId) {
+            let (lhs, rhs) = (usize::from(lhs), usize::from(rhs));
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("direct {} -> {}", lhs, rhs));
+        }
+
+        fn on_derived_edge(&mut self, lhs: EntityId, rhs: EntityId) {
+            let (lhs, rhs) = (usize::from(lhs), usize::from(rhs));
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("derived {} -> {}", lhs, rhs));
+        }
+
+        fn on_meet(
+            &mut self,
+            lhs: EntityId,
+            rhs: EntityId,
+            result: Result<&[(Value, Use)], &TypeError>,
+        ) {
+            let (lhs, rhs) = (usize::from(lhs), usize::from(rhs));
+            let ok = result.is_ok();
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("meet {} -> {}: {}", lhs, rhs, ok));
+        }
+    }
+
+    #[test]
+    fn observers_should_see_edges_and_meets() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut t = TypeChecker::new(Strict);
+        assert!(t
+            .set_observer(Some(Box::new(Recorder(events.clone()))))
+            .is_none());
+        let int = t.new_val(Prim::Int);
+        let (x_val, x_use) = t.var();
+        let sink = t.new_use(Prim::Bool);
+        assert!(t.flow(int, x_use).is_ok());
+        assert_eq!(Err(TypeError::Converge), t.flow(x_val, sink));
+
+        assert!(t.set_observer(None).is_some());
+        let boolean = t.new_val(Prim::Bool);
+        assert!(t.flow(boolean, sink).is_ok());
+        assert_eq!(
+            vec![
+                "direct 0 -> 1",
+                "direct 1 -> 2",
+                "derived 0 -> 2",
+                "meet 0 -> 2: false",
+            ],
+            *events.lock().unwrap()
+        );
+    }
+}
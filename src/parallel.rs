@@ -0,0 +1,372 @@
+//! Parallel checking of large batches of flows. The batch is split into
+//! the connected components of the graph it touches, following edges in
+//! either direction and the handles nested inside heads, and each component
+//! is checked on its own copy of its nodes on rayon's thread pool before the
+//! results are merged back. Enabled by the `rayon` feature.
+
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::scheme::Instantiate;
+use crate::union_find::UnionFind;
+use crate::{AbstractTypes, EntityId, MeetContext, NodeIndex, TypeChecker, TypeNode, Use, Value};
+use rayon::prelude::*;
+
+/// Shared lets the checkers of each component meet heads through the one
+/// type system, borrowed from the checker being merged into.
+struct Shared<'a, AT>(&'a AT);
+
+impl<V, U, AT, I> AbstractTypes<V, U, I> for Shared<'_, AT>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    type Error = AT::Error;
+
+    fn meet(&self, lhs: &V, rhs: &U) -> Result<Vec<(Value<I>, Use<I>)>, Self::Error> {
+        self.0.meet(lhs, rhs)
+    }
+
+    fn meet_with(
+        &self,
+        ctx: &mut MeetContext<V, U, I>,
+        lhs: &V,
+        rhs: &U,
+    ) -> Result<Vec<(Value<I>, Use<I>)>, Self::Error> {
+        self.0.meet_with(ctx, lhs, rhs)
+    }
+
+    fn refine(&self, lhs: &V, rhs: &U) -> Option<V> {
+        self.0.refine(lhs, rhs)
+    }
+
+    fn is_bottom(&self, lhs: &V) -> bool {
+        self.0.is_bottom(lhs)
+    }
+
+    fn is_top(&self, rhs: &U) -> bool {
+        self.0.is_top(rhs)
+    }
+}
+
+/// Component is one connected component of a batch: the live nodes in it,
+/// in ascending order, and the flows of the batch that touch it.
+struct Component<I> {
+    members: Vec<EntityId<I>>,
+    flows: Vec<(Value<I>, Use<I>)>,
+}
+
+/// Checked is a component once it has been checked on its own, holding the
+/// checker it was checked on, or the error it failed with.
+type Checked<'a, V, U, AT, I> = (
+    TypeChecker<V, U, Shared<'a, AT>, I>,
+    Result<(), <AT as AbstractTypes<V, U, I>>::Error>,
+);
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Instantiate<I> + Clone + PartialEq + Send + Sync,
+    U: Instantiate<I> + Clone + Send + Sync,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I> + Sync,
+    AT::Error: Send,
+{
+    /// Adds a batch of flows, checking the flows of each connected component
+    /// of the graph in parallel. The result matches adding the flows with
+    /// `flow_many` apart from the numbering of nodes created by meets, as
+    /// long as meets only return flows between the handles in their heads
+    /// and the nodes they create.
+    ///
+    /// Every component that checks is merged back. A component that fails
+    /// has its flows added one at a time instead, keeping those before the
+    /// failure. The first error in batch order is returned. The batch is
+    /// checked in order on the calling thread if it touches one component,
+    /// or if guards, limits, shuffling or tracing are in use, as they depend
+    /// on the order of the whole batch.
+    pub fn par_flow_batch(&mut self, pairs: Vec<(Value<I>, Use<I>)>) -> Result<(), AT::Error> {
+        let components = self.components(&pairs);
+        if components.len() < 2 || self.sequential() {
+            return self.flow_many(pairs);
+        }
+
+        let checker = &*self;
+        let checked: Vec<Checked<'_, V, U, AT, I>> = components
+            .par_iter()
+            .map(|component| checker.check_component(component))
+            .collect();
+
+        // the local checkers borrow the type system, so they are taken apart
+        // before anything is merged back.
+        let merges: Vec<_> = checked
+            .into_iter()
+            .map(|(local, result)| (Merge::from_local(local), result))
+            .collect();
+        let mut failure = None;
+        for (component, (merge, result)) in components.iter().zip(merges) {
+            match result {
+                Ok(()) => self.merge_component(component, merge),
+                Err(_) => {
+                    if let Err(e) = self.flow_many(component.flows.iter().copied()) {
+                        failure.get_or_insert(e);
+                    }
+                }
+            }
+        }
+        failure.map_or(Ok(()), Err)
+    }
+
+    /// Returns true if the checker is configured in a way that makes the
+    /// work of one component depend on that of another.
+    fn sequential(&self) -> bool {
+        #[cfg(feature = "trace")]
+        let tracing = self.tracer.is_some() || self.observer.0.is_some();
+        #[cfg(not(feature = "trace"))]
+        let tracing = false;
+        tracing
+            || self.limits.is_some()
+            || self.shuffle.is_some()
+            || !self.guarded.is_empty()
+            || !self.worklist.edges.is_empty()
+            || !self.worklist.pairs.is_empty()
+    }
+
+    /// Splits the live nodes into the connected components the batch
+    /// touches, in the order the batch first touches them.
+    fn components(&self, pairs: &[(Value<I>, Use<I>)]) -> Vec<Component<I>> {
+        let mut sets = UnionFind::default();
+        sets.reserve(self.types.len());
+        for _ in 0..self.types.len() {
+            sets.make_set();
+        }
+        let live: Vec<EntityId<I>> = self
+            .r
+            .nodes()
+            .filter(|&id| self.aliases.root(id) == id)
+            .collect();
+        for &id in &live {
+            for other in self.r.downstream(id).chain(self.nested(id)) {
+                sets.union(id, self.aliases.root(other));
+            }
+        }
+
+        let mut index: HashMap<EntityId<I>, usize> = HashMap::new();
+        let mut components: Vec<Component<I>> = Vec::new();
+        for &(lhs, rhs) in pairs {
+            let (lhs_root, rhs_root) = (self.aliases.root(lhs.0), self.aliases.root(rhs.0));
+            sets.union(lhs_root, rhs_root);
+        }
+        for &(lhs, rhs) in pairs {
+            let set = sets.find(self.aliases.root(lhs.0));
+            let idx = *index.entry(set).or_insert_with(|| {
+                components.push(Component {
+                    members: Vec::new(),
+                    flows: Vec::new(),
+                });
+                components.len() - 1
+            });
+            components[idx].flows.push((lhs, rhs));
+        }
+        for id in live {
+            if let Some(&idx) = index.get(&sets.find(id)) {
+                components[idx].members.push(id);
+            }
+        }
+        components
+    }
+
+    /// Copies the nodes and edges of a component into a checker of its own
+    /// and adds its flows there.
+    fn check_component(&self, component: &Component<I>) -> Checked<'_, V, U, AT, I> {
+        let local_ids: HashMap<EntityId<I>, EntityId<I>> = component
+            .members
+            .iter()
+            .enumerate()
+            .map(|(idx, &id)| (id, EntityId::at(idx)))
+            .collect();
+        let local_id = |id: EntityId<I>| local_ids[&self.aliases.root(id)];
+        let values = |value: Value<I>| Value(local_id(value.0));
+        let uses = |rhs: Use<I>| Use(local_id(rhs.0));
+
+        let config = crate::GraphConfig {
+            max_out_degree: None,
+            ..self.graph_config
+        };
+        let mut local = TypeChecker::new_with_index(Shared(&self.abstract_type_mapper), config);
+        let copy = |node: &TypeNode<V, U>| match node {
+            TypeNode::Var => TypeNode::Var,
+            TypeNode::Value(head) => TypeNode::Value(head.instantiate(&values, &uses)),
+            TypeNode::Use(head) => TypeNode::Use(head.instantiate(&values, &uses)),
+        };
+        for &id in &component.members {
+            local.add_node(copy(&self.types[usize::from(id)]));
+            if let Some(heads) = self.extra_heads.get(&id) {
+                local
+                    .extra_heads
+                    .insert(local_id(id), heads.iter().map(copy).collect());
+            }
+        }
+        for &id in &component.members {
+            for other in self.r.downstream(id) {
+                local.r.add_edge_mut(local_id(id), local_id(other));
+            }
+        }
+
+        let result = component
+            .flows
+            .iter()
+            .try_for_each(|&(lhs, rhs)| local.flow(values(lhs), uses(rhs)));
+        (local, result)
+    }
+
+    /// Merges a component checked on its own back in, adding the nodes its
+    /// meets created and the edges it added without meeting their heads
+    /// again.
+    fn merge_component(&mut self, component: &Component<I>, merge: Merge<V, U, I>) {
+        let base = self.types.len();
+        let global = |local: EntityId<I>| {
+            let idx = usize::from(local);
+            component
+                .members
+                .get(idx)
+                .copied()
+                .unwrap_or_else(|| EntityId::at(base + idx - component.members.len()))
+        };
+        let values = |value: Value<I>| Value(global(value.0));
+        let uses = |rhs: Use<I>| Use(global(rhs.0));
+        let remap = |node: &TypeNode<V, U>| match node {
+            TypeNode::Var => TypeNode::Var,
+            TypeNode::Value(head) => TypeNode::Value(head.instantiate(&values, &uses)),
+            TypeNode::Use(head) => TypeNode::Use(head.instantiate(&values, &uses)),
+        };
+
+        let (existing, created) = merge.types.split_at(component.members.len());
+        let created: Vec<TypeNode<V, U>> = created.iter().map(remap).collect();
+        let refined: Vec<(EntityId<I>, V)> = existing
+            .iter()
+            .zip(&component.members)
+            .filter_map(
+                |(node, &id)| match (remap(node), &self.types[usize::from(id)]) {
+                    (TypeNode::Value(head), TypeNode::Value(old)) if head != *old => {
+                        Some((id, head))
+                    }
+                    _ => None,
+                },
+            )
+            .collect();
+        let direct: Vec<(EntityId<I>, EntityId<I>)> = merge
+            .direct
+            .iter()
+            .map(|&(lhs, rhs)| (global(lhs), global(rhs)))
+            .collect();
+
+        for node in created {
+            self.add_node(node);
+        }
+        for (id, head) in refined {
+            self.refine_node(id, head);
+        }
+        for (lhs, rhs) in direct {
+            self.link(lhs, rhs);
+        }
+        for &(lhs, rhs) in &component.flows {
+            self.record(lhs, rhs);
+        }
+        self.meets += merge.meets;
+    }
+}
+
+/// Merge is what a component's checker found, taken out of the checker so
+/// that it no longer borrows the type system.
+struct Merge<V, U, I> {
+    types: Vec<TypeNode<V, U>>,
+    direct: Vec<(EntityId<I>, EntityId<I>)>,
+    meets: usize,
+}
+
+impl<V, U, I: NodeIndex> Merge<V, U, I> {
+    fn from_local<AT>(local: TypeChecker<V, U, Shared<'_, AT>, I>) -> Self
+    where
+        AT: AbstractTypes<V, U, I>,
+    {
+        Self {
+            types: local.types,
+            direct: local.direct.into_inner(),
+            meets: local.meets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Head, HeadTypeSystem, Prim, Strict};
+    use crate::*;
+
+    /// Builds a chain of vars from a value to a use for each of the heads,
+    /// returning the flows along each chain.
+    fn chains(
+        t: &mut TypeChecker<Prim, Prim, Strict>,
+        heads: &[(Prim, Prim)],
+    ) -> Vec<(Value, Use)> {
+        let mut flows = Vec::new();
+        for &(value, constraint) in heads {
+            let (x_val, x_use) = t.var();
+            let (y_val, y_use) = t.var();
+            let (value, sink) = (t.new_val(value), t.new_use(constraint));
+            flows.extend(vec![(value, x_use), (x_val, y_use), (y_val, sink)]);
+        }
+        flows
+    }
+
+    #[test]
+    fn parallel_batches_should_match_sequential_ones() {
+        let heads = vec![(Prim::Int, Prim::Int); 8];
+        let mut t = TypeChecker::new(Strict);
+        let flows = chains(&mut t, &heads);
+        let mut sequential = t.clone();
+
+        assert!(t.par_flow_batch(flows.clone()).is_ok());
+        assert!(sequential.flow_many(flows).is_ok());
+        let mut edges: Vec<_> = t.r.edges().collect();
+        let mut expected: Vec<_> = sequential.r.edges().collect();
+        edges.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(expected, edges);
+        assert_eq!(sequential.meets, t.meets);
+        assert_eq!(sequential.flows.len(), t.flows.len());
+    }
+
+    #[test]
+    fn failed_components_should_keep_their_earlier_flows() {
+        let heads = vec![(Prim::Int, Prim::Int), (Prim::Int, Prim::Bool)];
+        let mut t = TypeChecker::new(Strict);
+        let flows = chains(&mut t, &heads);
+
+        assert_eq!(Err(TypeError::Converge), t.par_flow_batch(flows.clone()));
+        assert!(t.reaches(flows[0].0, flows[2].1));
+        // the failing chain keeps the flows before the one that failed.
+        assert!(t.reaches(flows[3].0, flows[4].1));
+        assert_eq!(5, t.flows.len());
+    }
+
+    #[test]
+    fn parallel_batches_should_follow_handles_nested_in_heads() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let mut flows = Vec::new();
+        let mut sinks = Vec::new();
+        for _ in 0..4 {
+            let int = t.new_val(Head::Int);
+            let list = t.new_val(Head::List(int));
+            let (elem_val, elem_use) = t.var();
+            let list_use = t.new_use(Head::List(elem_val));
+            let sink = t.new_use(Head::Int);
+            let (x_val, x_use) = t.var();
+            flows.extend(vec![(list, x_use), (x_val, list_use), (elem_val, sink)]);
+            sinks.push((int, elem_use, sink));
+        }
+
+        assert!(t.par_flow_batch(flows).is_ok());
+        for (int, elem_use, sink) in sinks {
+            assert!(t.reaches(int, elem_use));
+            assert!(t.reaches(int, sink));
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! The parts of the `std` prelude that come from `alloc`, which a `no_std`
+//! build has to import by hand.
+
+pub(crate) use alloc::boxed::Box;
+pub(crate) use alloc::string::{String, ToString};
+pub(crate) use alloc::vec::Vec;
+pub(crate) use alloc::{format, vec};
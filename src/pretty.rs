@@ -0,0 +1,154 @@
+use crate::prelude::*;
+use crate::{heads, AbstractTypes, EntityId, NodeIndex, TypeChecker, TypeNode};
+use core::fmt;
+
+/// Pretty prints a checker as a table of its nodes followed by the direct
+/// edges out of each node.
+struct Pretty<'a, V, U, AT, I>(&'a TypeChecker<V, U, AT, I>)
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>;
+
+impl<V, U, AT, I> fmt::Display for Pretty<'_, V, U, AT, I>
+where
+    I: NodeIndex,
+    V: fmt::Debug,
+    U: fmt::Debug,
+    AT: AbstractTypes<V, U, I>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let checker = self.0;
+        let rows: Vec<(EntityId<I>, &str, String)> = checker
+            .r
+            .nodes()
+            .map(|id| match &checker.types[usize::from(id)] {
+                TypeNode::Var => {
+                    let name = checker.var_names.get(&id).cloned().unwrap_or_default();
+                    (id, "var", name)
+                }
+                TypeNode::Value(head) => {
+                    let heads =
+                        core::iter::once(head).chain(heads::extra_values(&checker.extra_heads, id));
+                    (
+                        id,
+                        "value",
+                        join(heads.map(|head| format!("{:?}", head)), " | "),
+                    )
+                }
+                TypeNode::Use(head) => {
+                    let heads =
+                        core::iter::once(head).chain(heads::extra_uses(&checker.extra_heads, id));
+                    (
+                        id,
+                        "use",
+                        join(heads.map(|head| format!("{:?}", head)), " & "),
+                    )
+                }
+            })
+            .collect();
+        let width = rows
+            .iter()
+            .map(|(id, _, _)| usize::from(*id).to_string().len())
+            .chain(core::iter::once("node".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f, "{:>width$}  kind   head", "node", width = width)?;
+        for (id, kind, head) in &rows {
+            let row = format!(
+                "{:>width$}  {:<5}  {}",
+                usize::from(*id),
+                kind,
+                head,
+                width = width
+            );
+            writeln!(f, "{}", row.trim_end())?;
+        }
+
+        let mut edges: Vec<(EntityId<I>, EntityId<I>)> = checker
+            .direct_edges()
+            .map(|(lhs, rhs)| (lhs.0, rhs.0))
+            .collect();
+        // a stable sort keeps each node's edges in the order they were added.
+        edges.sort_by_key(|&(lhs, _)| lhs);
+        writeln!(f, "direct edges:")?;
+        let mut rest = edges.as_slice();
+        while let Some(&(lhs, _)) = rest.first() {
+            let len = rest.iter().take_while(|&&(from, _)| from == lhs).count();
+            let (group, next) = rest.split_at(len);
+            let targets = group.iter().map(|&(_, rhs)| usize::from(rhs).to_string());
+            let lhs = usize::from(lhs);
+            writeln!(
+                f,
+                "{:>width$} -> {}",
+                lhs,
+                join(targets, ", "),
+                width = width
+            )?;
+            rest = next;
+        }
+        Ok(())
+    }
+}
+
+fn join(items: impl Iterator<Item = String>, sep: &str) -> String {
+    items.collect::<Vec<_>>().join(sep)
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: fmt::Debug,
+    U: fmt::Debug,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns a printer of the checker's state for test failures and bug
+    /// reports: a table of every node with its kind and heads, or its name
+    /// for a named var, followed by the direct edges out of each node, ordered
+    /// by source node and then by when each was added.
+    pub fn pretty(&self) -> impl fmt::Display + '_ {
+        Pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn pretty_should_print_a_table_of_nodes_and_direct_edges() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var_named("x");
+        let ints: Vec<Value> = (0..10).map(|_| t.new_val(Prim::Int)).collect();
+        let sink = t.new_use(Prim::Int);
+        let (y_val, y_use) = t.var();
+        assert!(t.add_use_head(sink, Prim::Int).is_ok());
+        assert!(t.flow(ints[9], x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+        assert!(t.flow(x_val, y_use).is_ok());
+        assert!(t.flow(y_val, sink).is_ok());
+
+        let expected = "\
+node  kind   head
+   0  var    x
+   1  value  Int
+   2  value  Int
+   3  value  Int
+   4  value  Int
+   5  value  Int
+   6  value  Int
+   7  value  Int
+   8  value  Int
+   9  value  Int
+  10  value  Int
+  11  use    Int & Int
+  12  var
+direct edges:
+   0 -> 11, 12
+  10 -> 0
+  12 -> 11
+";
+        assert_eq!(expected, t.pretty().to_string());
+    }
+}
@@ -0,0 +1,324 @@
+use crate::collections::HashSet;
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, Use, Value};
+
+/// FlowError wraps the error of a flow that failed, along with the value
+/// and use heads that couldn't be met, the direct constraint that brought
+/// them together and the chain of flows that brought the value to the use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowError<E, I = u32> {
+    pub error: E,
+    pub lhs: Value<I>,
+    pub rhs: Use<I>,
+    /// each hop from the value to the use, in order, as added by the caller
+    /// or by a meet.
+    pub path: Vec<(Value<I>, Use<I>)>,
+    /// the direct edge whose addition brought the heads together, whether
+    /// added by the caller or by a meet.
+    pub trigger: (Value<I>, Use<I>),
+    /// the Debug renderings of the value and use heads, once filled in by
+    /// `with_heads`.
+    pub heads: Option<(String, String)>,
+}
+
+impl<E, I: NodeIndex> FlowError<E, I> {
+    /// Fills in the renderings of the heads that couldn't be met, for
+    /// reporting the failure without access to the checker.
+    pub fn with_heads<V, U, AT>(mut self, checker: &TypeChecker<V, U, AT, I>) -> Self
+    where
+        V: core::fmt::Debug,
+        U: core::fmt::Debug,
+        AT: AbstractTypes<V, U, I>,
+    {
+        let var = || "var".to_string();
+        let value = checker.head_of_value(self.lhs);
+        let rhs = checker.head_of_use(self.rhs);
+        self.heads = Some((
+            value.map_or_else(var, |head| format!("{:?}", head)),
+            rhs.map_or_else(var, |head| format!("{:?}", head)),
+        ));
+        self
+    }
+}
+
+impl<E: core::fmt::Display, I: NodeIndex> core::fmt::Display for FlowError<E, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut nodes: Vec<String> = self
+            .path
+            .iter()
+            .map(|(lhs, _)| lhs.index().to_string())
+            .collect();
+        nodes.push(self.rhs.index().to_string());
+        write!(f, "{}", self.error)?;
+        if let Some((lhs, rhs)) = &self.heads {
+            write!(f, " {} against {}", lhs, rhs)?;
+        }
+        write!(f, " path: {}", nodes.join(" -> "))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static, I: NodeIndex> std::error::Error for FlowError<E, I> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Adds a flow like `flow`, but on failure reports the heads that
+    /// couldn't be met and the flows connecting them, which needn't involve
+    /// either end of the flow that was added.
+    pub fn flow_traced(
+        &mut self,
+        lhs: Value<I>,
+        rhs: Use<I>,
+    ) -> Result<(), FlowError<AT::Error, I>> {
+        self.failed = None;
+        self.flow(lhs, rhs).map_err(|error| {
+            let fallback = (self.aliases.root(lhs.0), self.aliases.root(rhs.0));
+            self.failure(error, fallback)
+        })
+    }
+
+    /// Adds a flow like `flow_traced`, filling in the renderings of the heads
+    /// that couldn't be met so the error can be reported on its own.
+    pub fn flow_checked(
+        &mut self,
+        lhs: Value<I>,
+        rhs: Use<I>,
+    ) -> Result<(), FlowError<AT::Error, I>>
+    where
+        V: core::fmt::Debug,
+        U: core::fmt::Debug,
+    {
+        self.flow_traced(lhs, rhs)
+            .map_err(|failure| failure.with_heads(self))
+    }
+
+    /// Adds every flow, carrying on past failed head checks rather than
+    /// stopping at the first, and reports every failure in the order it was
+    /// found. Exceeding a resource limit still discards the remaining work.
+    /// A flow whose own edge brought together heads that couldn't be met
+    /// isn't recorded, as with `flow`.
+    pub fn flow_all(
+        &mut self,
+        pairs: impl IntoIterator<Item = (Value<I>, Use<I>)>,
+    ) -> Result<(), Vec<FlowError<AT::Error, I>>> {
+        let recorded = self.enqueue_all(pairs);
+
+        let mut failures = Vec::new();
+        self.worklist.accumulate = true;
+        loop {
+            self.failed = None;
+            match self.settle() {
+                Ok(()) => break,
+                Err(error) => {
+                    // a limit is exceeded while adding an edge rather than
+                    // meeting heads, so blame the edge that was added last.
+                    let fallback = self.direct.last().copied().unwrap_or_default();
+                    failures.push(self.failure(error, fallback));
+                }
+            }
+        }
+        self.worklist.accumulate = false;
+
+        let failed: Vec<usize> = recorded
+            .into_iter()
+            .filter(|&idx| {
+                let flow = &self.flows[idx];
+                let edge = (
+                    Value(self.aliases.root(flow.lhs.0)),
+                    Use(self.aliases.root(flow.rhs.0)),
+                );
+                failures.iter().any(|failure| failure.trigger == edge)
+            })
+            .collect();
+        self.forget_flows(&failed);
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Builds the failure for the heads whose check failed last, or for the
+    /// fallback pair of nodes if no head check failed.
+    fn failure(
+        &self,
+        error: AT::Error,
+        fallback: (EntityId<I>, EntityId<I>),
+    ) -> FlowError<AT::Error, I> {
+        let (lhs, rhs) = self.failed.unwrap_or(fallback);
+        // heads are met as soon as the edge connecting them is added, so the
+        // last direct edge is the one that brought them together.
+        let trigger = self.direct.last().copied().unwrap_or(fallback);
+        FlowError {
+            error,
+            lhs: Value(lhs),
+            rhs: Use(rhs),
+            path: self.hops(lhs, rhs),
+            trigger: (Value(trigger.0), Use(trigger.1)),
+            heads: None,
+        }
+    }
+
+    /// Returns the shortest chain of direct edges from one node to another.
+    fn hops(&self, lhs: EntityId<I>, rhs: EntityId<I>) -> Vec<(Value<I>, Use<I>)> {
+        self.derivation(lhs, rhs)
+            .windows(2)
+            .map(|hop| (Value(hop[0]), Use(hop[1])))
+            .collect()
+    }
+
+    /// Returns every edge still in the graph that was added directly, by the
+    /// caller or by a meet, in the order each was first added.
+    pub fn direct_edges(&self) -> impl Iterator<Item = (Value<I>, Use<I>)> + '_ {
+        let mut seen = HashSet::new();
+        self.direct
+            .iter()
+            .filter(move |&&(lhs, rhs)| self.r.has_edge(lhs, rhs) && seen.insert((lhs, rhs)))
+            .map(|&(lhs, rhs)| (Value(lhs), Use(rhs)))
+    }
+
+    /// Returns every edge in the graph that was only added to close over the
+    /// direct ones, ordered by source node.
+    pub fn derived_edges(&self) -> impl Iterator<Item = (Value<I>, Use<I>)> + '_ {
+        let direct: HashSet<(EntityId<I>, EntityId<I>)> = self.direct.iter().copied().collect();
+        self.r
+            .edges()
+            .filter(move |edge| !direct.contains(edge))
+            .map(|(lhs, rhs)| (Value(lhs), Use(rhs)))
+    }
+
+    /// Returns the shortest chain of direct edges that an edge was derived
+    /// from, which for a direct edge is just the edge itself. Returns
+    /// nothing if the value doesn't flow into the use.
+    pub fn derivation_of(&self, lhs: Value<I>, rhs: Use<I>) -> Vec<(Value<I>, Use<I>)> {
+        let (lhs, rhs) = (self.aliases.root(lhs.0), self.aliases.root(rhs.0));
+        if !self.r.has_edge(lhs, rhs) {
+            return Vec::new();
+        }
+        self.hops(lhs, rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn failures_should_report_the_path_of_flows() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let boolean = t.new_use(Prim::Bool);
+        let (x_val, x_use) = t.var();
+        let (y_val, y_use) = t.var();
+        assert!(t.flow_traced(int, x_use).is_ok());
+        assert!(t.flow_traced(y_val, boolean).is_ok());
+
+        let failure = t.flow_traced(x_val, y_use).unwrap_err();
+        assert_eq!(
+            FlowError {
+                error: TypeError::Converge,
+                lhs: int,
+                rhs: boolean,
+                path: vec![(int, x_use), (x_val, y_use), (y_val, boolean)],
+                trigger: (x_val, y_use),
+                heads: None,
+            },
+            failure
+        );
+        assert_eq!(
+            "unable to converge types. path: 0 -> 2 -> 3 -> 1",
+            failure.to_string()
+        );
+        assert_eq!(
+            "unable to converge types. Int against Bool path: 0 -> 2 -> 3 -> 1",
+            failure.with_heads(&t).to_string()
+        );
+    }
+
+    #[test]
+    fn checked_flows_should_wrap_the_error() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let boolean = t.new_use(Prim::Bool);
+        let (x_val, x_use) = t.var();
+        assert!(t.flow_checked(int, x_use).is_ok());
+
+        let error = t.flow_checked(x_val, boolean).unwrap_err();
+        assert_eq!(TypeError::Converge, error.error);
+        assert_eq!((int, boolean), (error.lhs, error.rhs));
+        assert_eq!((x_val, boolean), error.trigger);
+        assert_eq!(
+            Some(("Int".to_string(), "Bool".to_string())),
+            error.heads.clone()
+        );
+        assert_eq!(
+            "unable to converge types. Int against Bool path: 0 -> 2 -> 1",
+            error.to_string()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn flow_errors_should_expose_their_source() {
+        use std::error::Error;
+
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let boolean = t.new_use(Prim::Bool);
+        let error: Box<dyn Error> = Box::new(t.flow_checked(int, boolean).unwrap_err());
+        assert_eq!(
+            TypeError::Converge.to_string(),
+            error.source().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn flow_all_should_report_every_failure() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let boolean = t.new_val(Prim::Bool);
+        let int_use = t.new_use(Prim::Int);
+        let bool_use = t.new_use(Prim::Bool);
+
+        let failures = t
+            .flow_all(vec![(int, bool_use), (int, int_use), (boolean, int_use)])
+            .unwrap_err();
+        let failed: Vec<(Value, Use)> = failures.iter().map(|f| (f.lhs, f.rhs)).collect();
+        assert_eq!(vec![(boolean, int_use), (int, bool_use)], failed);
+        assert!(t.reaches(int, int_use));
+        let recorded: Vec<(Value, Use)> = t.flows.iter().map(|f| (f.lhs, f.rhs)).collect();
+        assert_eq!(vec![(int, int_use)], recorded);
+        assert!(t.flow_all(vec![(boolean, bool_use)]).is_ok());
+    }
+
+    #[test]
+    fn edges_should_be_split_into_direct_and_derived() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        let sink = t.new_use(Prim::Int);
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+        assert!(t.flow(int, x_use).is_ok());
+
+        assert_eq!(
+            vec![(int, x_use), (x_val, sink)],
+            t.direct_edges().collect::<Vec<_>>()
+        );
+        assert_eq!(vec![(int, sink)], t.derived_edges().collect::<Vec<_>>());
+        assert_eq!(
+            vec![(int, x_use), (x_val, sink)],
+            t.derivation_of(int, sink)
+        );
+        assert!(t.derivation_of(x_val, x_use).is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{AbstractTypes, NodeIndex, TypeChecker, TypeNode, Use, Value};
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns every value that flows into a use, whether directly or
+    /// through other nodes, in the order the flows were derived.
+    pub fn flows_from(&self, rhs: Use<I>) -> impl Iterator<Item = Value<I>> + '_ {
+        self.r.upstream(self.aliases.root(rhs.0)).map(Value)
+    }
+
+    /// Returns every use that a value flows into, whether directly or
+    /// through other nodes, in the order the flows were derived.
+    pub fn flows_to(&self, value: Value<I>) -> impl Iterator<Item = Use<I>> + '_ {
+        self.r.downstream(self.aliases.root(value.0)).map(Use)
+    }
+
+    /// Returns the head of a value, or None if it is the value side of a
+    /// var.
+    pub fn head_of_value(&self, value: Value<I>) -> Option<&V> {
+        match self.types.get(value.index()) {
+            Some(TypeNode::Value(head)) => Some(head),
+            _ => None,
+        }
+    }
+
+    /// Returns the head of a use, or None if it is the use side of a var.
+    pub fn head_of_use(&self, rhs: Use<I>) -> Option<&U> {
+        match self.types.get(rhs.index()) {
+            Some(TypeNode::Use(head)) => Some(head),
+            _ => None,
+        }
+    }
+
+    /// Returns true if a value flows into a use, whether directly or
+    /// through other nodes.
+    pub fn contains_flow(&self, lhs: Value<I>, rhs: Use<I>) -> bool {
+        self.reaches(lhs, rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::Permissive;
+    use crate::*;
+
+    #[test]
+    fn queries_should_expose_flows_and_heads() {
+        let mut t = TypeChecker::new(Permissive);
+        let (x_val, x_use) = t.var();
+        let int = t.new_val("int");
+        let sink = t.new_use("sink");
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+
+        assert_eq!(vec![x_val, int], t.flows_from(sink).collect::<Vec<_>>());
+        assert_eq!(vec![x_use, sink], t.flows_to(int).collect::<Vec<_>>());
+        assert_eq!(Some(&"int"), t.head_of_value(int));
+        assert_eq!(None, t.head_of_value(x_val));
+        assert_eq!(Some(&"sink"), t.head_of_use(sink));
+        assert!(t.contains_flow(int, sink) && !t.contains_flow(x_val, x_use));
+    }
+}
@@ -0,0 +1,339 @@
+//! A deliberately naive reference implementation of the flow checker along
+//! with a differential harness that runs it side by side with
+//! `TypeChecker` over generated workloads.
+//!
+//! The reference checker stores only the direct constraints and recomputes
+//! the full transitive closure, and every head meet, from scratch after each
+//! flow. It is far too slow for real use but simple enough to be obviously
+//! correct, which makes it a useful oracle for the incremental engine.
+
+use super::*;
+use alloc::collections::{BTreeMap, BTreeSet};
+
+/// NaiveChecker mirrors the public surface of `TypeChecker` while deriving
+/// all state from its list of direct edges.
+struct NaiveChecker<V, U, AT>
+where
+    AT: AbstractTypes<V, U>,
+{
+    types: Vec<TypeNode<V, U>>,
+    /// the heads given to nodes after the one they were created with.
+    extra: BTreeMap<usize, Vec<TypeNode<V, U>>>,
+    direct: BTreeSet<(usize, usize)>,
+    abstract_types: AT,
+}
+
+impl<V, U, AT> NaiveChecker<V, U, AT>
+where
+    AT: AbstractTypes<V, U>,
+{
+    fn new(abstract_types: AT) -> Self {
+        Self {
+            types: Vec::new(),
+            extra: BTreeMap::new(),
+            direct: BTreeSet::new(),
+            abstract_types,
+        }
+    }
+
+    fn new_val(&mut self, val_type: V) -> usize {
+        self.types.push(TypeNode::Value(val_type));
+        self.types.len() - 1
+    }
+
+    fn new_use(&mut self, constraint: U) -> usize {
+        self.types.push(TypeNode::Use(constraint));
+        self.types.len() - 1
+    }
+
+    fn var(&mut self) -> usize {
+        self.types.push(TypeNode::Var);
+        self.types.len() - 1
+    }
+
+    /// Computes every edge reachable through one or more direct edges.
+    fn closure(&self) -> BTreeSet<(usize, usize)> {
+        let mut closure = BTreeSet::new();
+        for start in 0..self.types.len() {
+            let mut stack: Vec<usize> = self
+                .direct
+                .iter()
+                .filter(|(lhs, _)| *lhs == start)
+                .map(|&(_, rhs)| rhs)
+                .collect();
+
+            while let Some(node) = stack.pop() {
+                if closure.insert((start, node)) {
+                    stack.extend(
+                        self.direct
+                            .iter()
+                            .filter(|(lhs, _)| *lhs == node)
+                            .map(|&(_, rhs)| rhs),
+                    );
+                }
+            }
+        }
+        closure
+    }
+
+    /// Gives a node another head alongside the one it was created with, and
+    /// then meets every pair in the closure again.
+    fn add_head(&mut self, id: usize, head: TypeNode<V, U>) -> Result<(), AT::Error> {
+        self.extra.entry(id).or_default().push(head);
+        self.settle()
+    }
+
+    /// Returns every head of a node, the one it was created with first.
+    fn heads(&self, id: usize) -> impl Iterator<Item = &TypeNode<V, U>> {
+        core::iter::once(&self.types[id]).chain(self.extra.get(&id).into_iter().flatten())
+    }
+
+    /// Adds a direct edge and then meets every value/use pair in the closure
+    /// until no new direct edges are produced.
+    fn flow(&mut self, lhs: usize, rhs: usize) -> Result<(), AT::Error> {
+        self.direct.insert((lhs, rhs));
+        self.settle()
+    }
+
+    /// Meets every head of every value/use pair in the closure until no new
+    /// direct edges are produced.
+    fn settle(&mut self) -> Result<(), AT::Error> {
+        loop {
+            let mut new_edges = Vec::new();
+            for (lhs, rhs) in self.closure() {
+                for lhs_head in self.heads(lhs) {
+                    for rhs_head in self.heads(rhs) {
+                        if let (TypeNode::Value(lhs_head), TypeNode::Use(rhs_head)) =
+                            (lhs_head, rhs_head)
+                        {
+                            if self.abstract_types.is_bottom(lhs_head)
+                                || self.abstract_types.is_top(rhs_head)
+                            {
+                                continue;
+                            }
+                            new_edges.extend(self.abstract_types.meet(lhs_head, rhs_head)?);
+                        }
+                    }
+                }
+            }
+
+            let mut changed = false;
+            for (val, use_) in new_edges {
+                changed |= self.direct.insert((val.0.into(), use_.0.into()));
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Collects every edge currently held by a `TypeChecker`'s graph.
+fn checker_edges<V, U, AT>(checker: &TypeChecker<V, U, AT>) -> BTreeSet<(usize, usize)>
+where
+    AT: AbstractTypes<V, U>,
+{
+    checker
+        .r
+        .edges()
+        .map(|(lhs, rhs)| (lhs.into(), rhs.into()))
+        .collect()
+}
+
+/// A minimal xorshift generator so workloads are reproducible from a seed
+/// without pulling in an external crate.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // xorshift gets stuck on zero, so mix the seed before use.
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    enum PairValue {
+        Bool,
+        Int,
+        Pair(Value, Value),
+    }
+
+    #[derive(Debug, Clone)]
+    enum PairUse {
+        Bool,
+        Int,
+        Pair(Use, Use),
+    }
+
+    #[derive(Debug)]
+    struct PairTypeSystem;
+
+    impl AbstractTypes<PairValue, PairUse> for PairTypeSystem {
+        type Error = TypeError;
+
+        fn meet(&self, lhs: &PairValue, rhs: &PairUse) -> Result<Vec<(Value, Use)>, Self::Error> {
+            match (lhs, rhs) {
+                (PairValue::Bool, PairUse::Bool) | (PairValue::Int, PairUse::Int) => Ok(vec![]),
+                (PairValue::Pair(l1, l2), PairUse::Pair(r1, r2)) => {
+                    Ok(vec![(*l1, *r1), (*l2, *r2)])
+                }
+                _ => Err(TypeError::Converge),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Kind {
+        Value,
+        Use,
+        Var,
+    }
+
+    /// Asserts that both checkers reported the same result and, if it was a
+    /// success, hold the same edges, returning false once they failed.
+    fn agree(
+        checker: &TypeChecker<PairValue, PairUse, PairTypeSystem>,
+        naive: &NaiveChecker<PairValue, PairUse, PairTypeSystem>,
+        real: Result<(), TypeError>,
+        expected: Result<(), TypeError>,
+        step: usize,
+    ) -> bool {
+        assert_eq!(expected, real, "diverged on diagnostics at step {}", step);
+        // a failed flow leaves the incremental checker partially updated, so
+        // the workloads can only be compared up to the first error.
+        if real.is_err() {
+            return false;
+        }
+        assert_eq!(
+            naive.closure(),
+            checker_edges(checker),
+            "diverged on edges at step {}",
+            step
+        );
+        true
+    }
+
+    /// Drives both checkers through the same randomly generated workload,
+    /// asserting that edge sets and diagnostics agree after every flow and
+    /// every head added.
+    fn run_differential(seed: u64, steps: usize) {
+        let mut rng = XorShift::new(seed);
+        let mut checker = TypeChecker::new(PairTypeSystem);
+        let mut naive = NaiveChecker::<PairValue, PairUse, PairTypeSystem>::new(PairTypeSystem);
+        let mut kinds: Vec<Kind> = Vec::new();
+
+        let sources = |kinds: &[Kind]| -> Vec<usize> {
+            (0..kinds.len())
+                .filter(|&i| kinds[i] != Kind::Use)
+                .collect()
+        };
+        let sinks = |kinds: &[Kind]| -> Vec<usize> {
+            (0..kinds.len())
+                .filter(|&i| kinds[i] != Kind::Value)
+                .collect()
+        };
+
+        for step in 0..steps {
+            let (values, uses) = (sources(&kinds), sinks(&kinds));
+            match rng.below(6) {
+                0 => {
+                    let head = match rng.below(3) {
+                        0 => PairValue::Bool,
+                        1 if values.len() > 1 => PairValue::Pair(
+                            Value(EntityId::at(values[rng.below(values.len())])),
+                            Value(EntityId::at(values[rng.below(values.len())])),
+                        ),
+                        _ => PairValue::Int,
+                    };
+                    let Value(id) = checker.new_val(head.clone());
+                    assert_eq!(usize::from(id), naive.new_val(head));
+                    kinds.push(Kind::Value);
+                }
+                1 => {
+                    let head = match rng.below(3) {
+                        0 => PairUse::Bool,
+                        1 if uses.len() > 1 => PairUse::Pair(
+                            Use(EntityId::at(uses[rng.below(uses.len())])),
+                            Use(EntityId::at(uses[rng.below(uses.len())])),
+                        ),
+                        _ => PairUse::Int,
+                    };
+                    let Use(id) = checker.new_use(head.clone());
+                    assert_eq!(usize::from(id), naive.new_use(head));
+                    kinds.push(Kind::Use);
+                }
+                2 => {
+                    let (Value(id), _) = checker.var();
+                    assert_eq!(usize::from(id), naive.var());
+                    kinds.push(Kind::Var);
+                }
+                3 if kinds.contains(&Kind::Value) => {
+                    let heads: Vec<usize> = (0..kinds.len())
+                        .filter(|&i| kinds[i] == Kind::Value)
+                        .collect();
+                    let id = heads[rng.below(heads.len())];
+                    let head = match rng.below(2) {
+                        0 => PairValue::Bool,
+                        _ => PairValue::Int,
+                    };
+                    let real = checker.add_val_head(Value(EntityId::at(id)), head.clone());
+                    let expected = naive.add_head(id, TypeNode::Value(head));
+                    if !agree(&checker, &naive, real, expected, step) {
+                        return;
+                    }
+                }
+                _ if !values.is_empty() && !uses.is_empty() => {
+                    let lhs = values[rng.below(values.len())];
+                    let rhs = uses[rng.below(uses.len())];
+
+                    let real = checker.flow(Value(EntityId::at(lhs)), Use(EntityId::at(rhs)));
+                    let expected = naive.flow(lhs, rhs);
+                    if !agree(&checker, &naive, real, expected, step) {
+                        return;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn naive_checker_should_compute_transitive_closure() {
+        let mut naive = NaiveChecker::<PairValue, PairUse, PairTypeSystem>::new(PairTypeSystem);
+        let v = naive.new_val(PairValue::Int);
+        let x = naive.var();
+        let u = naive.new_use(PairUse::Int);
+
+        assert!(naive.flow(v, x).is_ok());
+        assert!(naive.flow(x, u).is_ok());
+        assert_eq!(
+            [(v, x), (v, u), (x, u)]
+                .iter()
+                .copied()
+                .collect::<BTreeSet<_>>(),
+            naive.closure()
+        );
+    }
+
+    #[test]
+    fn checker_should_agree_with_reference_on_generated_workloads() {
+        for seed in 0..256 {
+            run_differential(seed, 64);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{AbstractTypes, EntityId, FlowWarning, NodeIndex, TypeChecker, TypeNode, Use, Value};
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Removes the node behind a value, freeing its slot for a later node.
+    /// For a var, removing either of its handles removes the var.
+    ///
+    /// Every edge to or from the node is removed along with any record of
+    /// it, but flows derived through it are kept, so they should be
+    /// retracted first if they shouldn't outlive it. Handles to the node,
+    /// including any nested inside other heads, mustn't be used again, as
+    /// its slot may be handed to a new node.
+    ///
+    /// Panics if a checkpoint or binding level is open, or if the node was
+    /// merged with another.
+    pub fn remove_value(&mut self, value: Value<I>) {
+        self.remove_node(value.0)
+    }
+
+    /// Removes the node behind a use, as `remove_value` does for a value.
+    pub fn remove_use(&mut self, rhs: Use<I>) {
+        self.remove_node(rhs.0)
+    }
+
+    /// Returns true if the node at a raw index was removed and its slot
+    /// hasn't been reused.
+    pub fn is_removed(&self, idx: usize) -> bool {
+        self.r.is_removed(EntityId::at(idx))
+    }
+
+    pub(crate) fn remove_node(&mut self, id: EntityId<I>) {
+        assert!(self.levels.is_empty(), "can't remove a node inside a level");
+        assert!(
+            self.aliases.is_singleton(id),
+            "can't remove a node merged with another"
+        );
+        self.r.remove_node_mut(id);
+        self.types[usize::from(id)] = TypeNode::Var;
+
+        self.unrefined.remove(&id);
+        self.meet_nodes.remove(&id);
+        self.extra_heads.remove(&id);
+        self.added_heads.retain(|&node| node != id);
+        self.holes.retain(|hole| hole.0 != id);
+        self.labels.remove(&id);
+        self.var_names.remove(&id);
+        self.named_vars.retain(|_, &mut named| named != id);
+        self.groups.nodes.remove(&id);
+        self.flows
+            .retain(|flow| flow.lhs.0 != id && flow.rhs.0 != id);
+        self.sync_recorded();
+        self.direct.retain(|&(lhs, rhs)| lhs != id && rhs != id);
+        self.forbidden
+            .retain(|(lhs, rhs)| lhs.0 != id && rhs.0 != id);
+        self.violations
+            .retain(|violation| !violation.path.contains(&id));
+        self.guarded
+            .retain(|guarded| guarded.guard.0 != id && guarded.lhs.0 != id && guarded.rhs.0 != id);
+        self.coerced
+            .retain(|(lhs, rhs, _)| lhs.0 != id && rhs.0 != id);
+        self.warnings.retain(|warning| match *warning {
+            FlowWarning::HighOutDegree { entity, .. } => entity != id,
+        });
+        if self.failed.is_some_and(|(lhs, rhs)| lhs == id || rhs == id) {
+            self.failed = None;
+        }
+    }
+
+    /// Adds a node, reusing the slot of a removed one where possible. Nodes
+    /// are only ever appended while a checkpoint or binding level is open,
+    /// as both rely on new nodes coming after existing ones.
+    pub(crate) fn alloc_node(&mut self, node: TypeNode<V, U>) -> EntityId<I> {
+        if self.levels.is_empty() {
+            if let Some(i) = self.r.reuse_node_mut() {
+                if let Some(group) = self.groups.open {
+                    self.groups.nodes.insert(i, group);
+                }
+                self.types[usize::from(i)] = node;
+                return i;
+            }
+        }
+        self.add_node(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn removed_slots_should_be_reused() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        let int_use = t.new_use(Prim::Int);
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, int_use).is_ok());
+
+        t.remove_value(x_val);
+        assert!(t.is_removed(x_val.index()));
+        assert_eq!(2, t.stats().values + t.stats().uses);
+        assert_eq!(0, t.stats().vars);
+        assert_eq!(
+            Err(TypeError::HandleInvalid {
+                index: x_val.index()
+            }),
+            t.value_at(x_val.index())
+        );
+        assert!(t.use_at(x_use.index()).is_err());
+
+        let boolean = t.new_val(Prim::Bool);
+        assert_eq!(x_val.index(), boolean.index());
+        assert!(!t.is_removed(boolean.index()));
+        assert_eq!(3, t.types.len());
+        assert!(t.flow(boolean, int_use).is_err());
+
+        t.remove_value(boolean);
+        let (y_val, _) = t.try_var().unwrap();
+        assert_eq!(boolean.index(), y_val.index());
+        assert_eq!(3, t.types.len());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::collections::HashSet;
+use crate::prelude::*;
+use crate::{
+    checkpoint, heads, AbstractTypes, EntityId, MeetContext, NodeIndex, TypeChecker, TypeNode,
+    Value,
+};
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns every node connected to `id` by edges in either direction,
+    /// or related by the meet of two connected nodes, such as the children
+    /// of a pair of constructors. Nothing outside of this region can depend
+    /// on a head inside it.
+    pub(crate) fn region(&self, id: EntityId<I>) -> Vec<EntityId<I>> {
+        let mut seen: HashSet<EntityId<I>> = core::iter::once(id).collect();
+        let mut region = vec![id];
+        let mut work = vec![id];
+
+        while let Some(node) = work.pop() {
+            let related = self.related_children(node);
+            for next in self
+                .r
+                .upstream(node)
+                .chain(self.r.downstream(node))
+                .chain(related)
+            {
+                if seen.insert(next) {
+                    region.push(next);
+                    work.push(next);
+                }
+            }
+        }
+        region
+    }
+
+    /// Returns the existing nodes the meets of a value with the uses it
+    /// flows into relate, by meeting their heads again. Nodes the meets
+    /// would create are new on every call, so they are left out.
+    fn related_children(&self, id: EntityId<I>) -> Vec<EntityId<I>> {
+        let value = match &self.types[usize::from(id)] {
+            TypeNode::Value(head) => head,
+            _ => return Vec::new(),
+        };
+        let types = &self.abstract_type_mapper;
+        let existing = |node: EntityId<I>| usize::from(node) < self.types.len();
+        let mut related = Vec::new();
+        for rhs in self.r.downstream(id) {
+            let constraint = match &self.types[usize::from(rhs)] {
+                TypeNode::Use(head) => head,
+                _ => continue,
+            };
+            let values = core::iter::once(value).chain(heads::extra_values(&self.extra_heads, id));
+            for value in values {
+                let uses =
+                    core::iter::once(constraint).chain(heads::extra_uses(&self.extra_heads, rhs));
+                for constraint in uses {
+                    if types.is_bottom(value) || types.is_top(constraint) {
+                        continue;
+                    }
+                    let mut ctx = MeetContext::new(self.types.len());
+                    if let Ok(flows) = types.meet_with(&mut ctx, value, constraint) {
+                        let nodes = flows.into_iter().flat_map(|(lhs, rhs)| [lhs.0, rhs.0]);
+                        related.extend(
+                            nodes
+                                .filter(|&node| existing(node))
+                                .map(|node| self.aliases.root(node)),
+                        );
+                    }
+                }
+            }
+        }
+        related
+    }
+
+    /// Replaces the head of a value, such as when a literal is edited, and
+    /// re-checks only the region of the graph connected to it. Every edge in
+    /// that region is discarded and the flows added into it are replayed
+    /// against the new head, so edges derived from the old head don't
+    /// linger.
+    ///
+    /// Panics if the value is the value side of a var, which has no head.
+    pub fn replace_value_head(&mut self, value: Value<I>, head: V) -> Result<(), AT::Error> {
+        let id = self.aliases.find(value.0);
+        assert!(
+            matches!(self.types[usize::from(id)], TypeNode::Value(_)),
+            "only value nodes have a head to replace"
+        );
+        self.forget_unrefined(id);
+        self.set_node(id, TypeNode::Value(head));
+
+        let region = self.region(id).into_iter().collect();
+        self.recheck(&region)
+    }
+
+    /// Discards every edge within a region and replays the recorded flows
+    /// starting in it. Every flow is replayed even if an earlier one fails,
+    /// so that a failure doesn't lose the edges of the flows after it, and
+    /// the first failure is returned.
+    ///
+    /// Values refined by the discarded meets get back the heads they had
+    /// before, and the nodes those meets created are removed, unless a
+    /// checkpoint or binding level is open, in which case they are left
+    /// detached until it is restored.
+    pub(crate) fn recheck(&mut self, region: &HashSet<EntityId<I>>) -> Result<(), AT::Error> {
+        // detaching in hash order would leave rolled back sets in hash order.
+        let mut nodes: Vec<EntityId<I>> = region.iter().copied().collect();
+        nodes.sort_unstable();
+        for &node in &nodes {
+            self.r.detach_mut(node);
+            self.unrefine_node(node);
+        }
+        if !self.r.is_snapshotting() && self.levels.is_empty() {
+            for node in nodes {
+                if self.meet_nodes.contains(&node) && self.aliases.is_singleton(node) {
+                    self.remove_node(node);
+                }
+            }
+        }
+        if self.r.is_snapshotting() {
+            self.overwritten.push(checkpoint::Overwritten::Detached {
+                direct: self.direct.clone(),
+                violations: self.violations.clone(),
+            });
+        }
+        self.direct
+            .retain(|(lhs, rhs)| !region.contains(lhs) && !region.contains(rhs));
+        let aliases = &self.aliases;
+        self.violations
+            .retain(|violation| !region.contains(&aliases.root(violation.lhs.0)));
+
+        let replayed: Vec<_> = self
+            .flows
+            .iter()
+            .filter(|flow| region.contains(&self.aliases.root(flow.lhs.0)))
+            .map(|flow| (flow.lhs, flow.rhs))
+            .collect();
+        self.worklist
+            .extend_edges(replayed, self.graph_config.queue_duplicates);
+
+        let accumulate = core::mem::replace(&mut self.worklist.accumulate, true);
+        let mut failure = None;
+        while let Err(error) = self.settle() {
+            failure.get_or_insert(error);
+        }
+        self.worklist.accumulate = accumulate;
+        failure.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn replacing_a_head_should_recheck_its_region_only() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        let literal = t.new_val(Prim::Int);
+        let int_use = t.new_use(Prim::Int);
+        assert!(t.flow(literal, x_use).is_ok());
+        assert!(t.flow(x_val, int_use).is_ok());
+
+        let (y_val, y_use) = t.var();
+        let other = t.new_val(Prim::Bool);
+        let bool_use = t.new_use(Prim::Bool);
+        assert!(t.flow(other, y_use).is_ok());
+        assert!(t.flow(y_val, bool_use).is_ok());
+        let unrelated: Vec<_> = t.r.downstream(other.0).collect();
+
+        assert_eq!(
+            Err(TypeError::Converge),
+            t.replace_value_head(literal, Prim::Bool)
+        );
+        assert!(t.replace_value_head(literal, Prim::Int).is_ok());
+        assert_eq!(2, t.r.edges().filter(|&(_, rhs)| rhs == int_use.0).count());
+        assert_eq!(unrelated, t.r.downstream(other.0).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn failed_replays_should_keep_the_remaining_flows() {
+        let mut t = TypeChecker::new(Strict);
+        let literal = t.new_val(Prim::Int);
+        let (x_val, x_use) = t.var();
+        let (_, y_use) = t.var();
+        let int_use = t.new_use(Prim::Int);
+        assert!(t.flow(literal, x_use).is_ok());
+        assert!(t.flow(x_val, int_use).is_ok());
+        assert!(t.flow(x_val, y_use).is_ok());
+
+        assert_eq!(
+            Err(TypeError::Converge),
+            t.replace_value_head(literal, Prim::Bool)
+        );
+        assert!(t.reaches(x_val, y_use) && t.reaches(literal, y_use));
+
+        // a flow that failed when added isn't replayed by later rechecks.
+        assert!(t.replace_value_head(literal, Prim::Int).is_ok());
+        let boolean = t.new_val(Prim::Bool);
+        assert_eq!(Err(TypeError::Converge), t.flow(boolean, x_use));
+        assert!(t.replace_value_head(literal, Prim::Int).is_ok());
+        assert!(!t.reaches(boolean, y_use));
+    }
+}
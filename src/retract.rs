@@ -0,0 +1,79 @@
+use crate::collections::HashSet;
+use crate::{AbstractTypes, NodeIndex, TypeChecker, Use, Value};
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Removes a flow added by the caller, along with every edge that was
+    /// only justified by it. The region of the graph the flow belongs to is
+    /// re-derived from the remaining flows into it, so edges still implied
+    /// by other flows survive. A flow whose check failed was never recorded,
+    /// but retracting it still discards the edges it left behind.
+    /// Retracting a flow that was never added, or was derived by a meet,
+    /// does nothing.
+    pub fn retract(&mut self, lhs: Value<I>, rhs: Use<I>) -> Result<(), AT::Error> {
+        let before = self.flows.len();
+        self.retain_flows(|flow| (flow.lhs, flow.rhs) != (lhs, rhs));
+        let (lhs_root, rhs_root) = (self.aliases.root(lhs.0), self.aliases.root(rhs.0));
+        if self.flows.len() == before && !self.direct.contains(&(lhs_root, rhs_root)) {
+            return Ok(());
+        }
+
+        let region: HashSet<_> = self.region(self.aliases.root(lhs.0)).into_iter().collect();
+        self.recheck(&region)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Range, RangeTypeSystem, Strict};
+    use crate::*;
+
+    #[test]
+    fn retracting_a_flow_should_keep_edges_justified_otherwise() {
+        let mut t = TypeChecker::new(Strict);
+        let literal = t.new_val(Prim::Int);
+        let (x_val, x_use) = t.var();
+        let (y_val, y_use) = t.var();
+        let int_use = t.new_use(Prim::Int);
+        assert!(t.flow(literal, x_use).is_ok());
+        assert!(t.flow(x_val, y_use).is_ok());
+        assert!(t.flow(literal, y_use).is_ok());
+        assert!(t.flow(y_val, int_use).is_ok());
+
+        assert!(t.retract(x_val, y_use).is_ok());
+        assert!(!t.reaches(x_val, int_use));
+        assert!(t.reaches(literal, int_use));
+        assert!(t.reaches(literal, x_use));
+
+        let boolean = t.new_val(Prim::Bool);
+        assert_eq!(Err(TypeError::Converge), t.flow(boolean, y_use));
+        assert!(t.retract(boolean, y_use).is_ok());
+        assert!(!t.reaches(boolean, int_use));
+        assert!(t.flow(x_val, int_use).is_ok());
+    }
+
+    #[test]
+    fn retracting_a_flow_should_undo_the_refinements_it_caused() {
+        let mut t = TypeChecker::new(RangeTypeSystem);
+        let number = t.new_val(Range(0, 10));
+        let small = t.new_use(Range(0, 3));
+        assert!(t.flow(number, small).is_ok());
+        assert_eq!(Some(&Range(0, 3)), t.head_of_value(number));
+
+        assert!(t.retract(number, small).is_ok());
+        assert_eq!(Some(&Range(0, 10)), t.head_of_value(number));
+        let large = t.new_use(Range(5, 20));
+        assert!(t.flow(number, large).is_ok());
+        assert_eq!(Some(&Range(5, 10)), t.head_of_value(number));
+
+        // a checkpoint puts the refined head back along with the flow.
+        let checkpoint = t.save();
+        assert!(t.retract(number, large).is_ok());
+        assert_eq!(Some(&Range(0, 10)), t.head_of_value(number));
+        t.restore(checkpoint);
+        assert_eq!(Some(&Range(5, 10)), t.head_of_value(number));
+    }
+}
@@ -0,0 +1,236 @@
+//! Copying of subgraphs for let-polymorphism, where every use of a
+//! let-bound name needs its own copy of the name's constraints rather than
+//! sharing its nodes.
+
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, TypeNode, Use, Value};
+use core::cell::RefCell;
+
+/// Instantiate rebuilds a head around fresh handles, so that a head can be
+/// copied into a new instance of a scheme.
+pub trait Instantiate<I = u32>: Sized {
+    /// Returns a copy of the head with every handle nested inside it
+    /// replaced by the given mappings.
+    fn instantiate(
+        &self,
+        values: &dyn Fn(Value<I>) -> Value<I>,
+        uses: &dyn Fn(Use<I>) -> Use<I>,
+    ) -> Self;
+}
+
+/// Member is a node of a scheme, either one that every instance gets its
+/// own copy of or one that every instance shares.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Member<I> {
+    /// a copied node, by its position in the scheme's nodes.
+    Copied(usize),
+    Shared(EntityId<I>),
+}
+
+/// Scheme is a frozen copy of the subgraph reachable from a set of roots,
+/// from which fresh instances can be made.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheme<I = u32> {
+    /// the nodes of the subgraph that are copied, in the order they are
+    /// copied.
+    nodes: Vec<EntityId<I>>,
+    /// the edges between copied nodes, by their position in `nodes`.
+    edges: Vec<(usize, usize)>,
+    /// the edges between a copied node and a shared one.
+    links: Vec<(Member<I>, Member<I>)>,
+    roots: Vec<Member<I>>,
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Instantiate<I>,
+    U: Instantiate<I>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns the handles nested inside a node's heads.
+    pub(crate) fn nested(&self, id: EntityId<I>) -> Vec<EntityId<I>> {
+        let nested = RefCell::new(Vec::new());
+        let values = |value: Value<I>| {
+            nested.borrow_mut().push(value.0);
+            value
+        };
+        let uses = |rhs: Use<I>| {
+            nested.borrow_mut().push(rhs.0);
+            rhs
+        };
+        let extra = self.extra_heads.get(&id).into_iter().flatten();
+        for node in core::iter::once(&self.types[usize::from(id)]).chain(extra) {
+            match node {
+                TypeNode::Value(head) => drop(head.instantiate(&values, &uses)),
+                TypeNode::Use(head) => drop(head.instantiate(&values, &uses)),
+                TypeNode::Var => (),
+            }
+        }
+        if let Some(head) = self.unrefined.get(&id) {
+            drop(head.instantiate(&values, &uses));
+        }
+        nested.into_inner()
+    }
+
+    /// Freezes the subgraph reachable from the roots, following edges in
+    /// either direction and the handles nested inside heads, into a scheme.
+    /// The scheme keeps the edges as they are now, so flows added to the
+    /// roots later aren't part of it.
+    pub fn freeze_scheme(&self, roots: &[Value<I>]) -> Scheme<I> {
+        self.freeze(roots, &|_| false)
+    }
+
+    /// Freezes the subgraph reachable from the roots like `freeze_scheme`,
+    /// but stops at the nodes that are to be shared by every instance.
+    pub(crate) fn freeze(
+        &self,
+        roots: &[Value<I>],
+        shared: &dyn Fn(EntityId<I>) -> bool,
+    ) -> Scheme<I> {
+        let mut positions: HashMap<EntityId<I>, usize> = HashMap::new();
+        let mut nodes = Vec::new();
+        let mut work: Vec<EntityId<I>> = roots.iter().map(|root| root.0).collect();
+
+        while let Some(node) = work.pop() {
+            let node = self.aliases.root(node);
+            if positions.contains_key(&node) || shared(node) {
+                continue;
+            }
+            positions.insert(node, nodes.len());
+            nodes.push(node);
+            work.extend(self.r.upstream(node));
+            work.extend(self.r.downstream(node));
+            work.extend(self.nested(node));
+        }
+
+        let member = |id: EntityId<I>| match positions.get(&id) {
+            Some(&position) => Member::Copied(position),
+            None => Member::Shared(id),
+        };
+        let mut edges = Vec::new();
+        let mut links = Vec::new();
+        for (position, &node) in nodes.iter().enumerate() {
+            for rhs in self.r.downstream(node) {
+                match member(rhs) {
+                    Member::Copied(rhs) => edges.push((position, rhs)),
+                    shared => links.push((Member::Copied(position), shared)),
+                }
+            }
+            for lhs in self.r.upstream(node) {
+                if let shared @ Member::Shared(_) = member(lhs) {
+                    links.push((shared, Member::Copied(position)));
+                }
+            }
+        }
+        let roots = roots
+            .iter()
+            .map(|root| member(self.aliases.root(root.0)))
+            .collect();
+        Scheme {
+            nodes,
+            edges,
+            links,
+            roots,
+        }
+    }
+
+    /// Creates a fresh copy of a scheme's subgraph, returning the copies of
+    /// its roots. The copied edges were already checked when the scheme was
+    /// frozen, so no heads are met again.
+    pub fn instantiate(&mut self, scheme: &Scheme<I>) -> Vec<Value<I>> {
+        let base = self.types.len();
+        let positions: HashMap<EntityId<I>, usize> = scheme
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(position, &node)| (node, position))
+            .collect();
+        let aliases = &self.aliases;
+        let fresh = |id: EntityId<I>| match positions.get(&aliases.root(id)) {
+            Some(&position) => EntityId::at(base + position),
+            None => id,
+        };
+
+        let copies: Vec<TypeNode<V, U>> = scheme
+            .nodes
+            .iter()
+            .map(|&node| match &self.types[usize::from(node)] {
+                TypeNode::Var => TypeNode::Var,
+                TypeNode::Value(head) => TypeNode::Value(
+                    head.instantiate(&|value| Value(fresh(value.0)), &|rhs| Use(fresh(rhs.0))),
+                ),
+                TypeNode::Use(head) => TypeNode::Use(
+                    head.instantiate(&|value| Value(fresh(value.0)), &|rhs| Use(fresh(rhs.0))),
+                ),
+            })
+            .collect();
+        for copy in copies {
+            self.add_node(copy);
+        }
+
+        for &(lhs, rhs) in &scheme.edges {
+            let (lhs, rhs) = (EntityId::at(base + lhs), EntityId::at(base + rhs));
+            self.direct.push((lhs, rhs));
+            self.r.add_edge_mut(lhs, rhs);
+        }
+
+        // the nodes on the far side of a link aren't copied, so the pairs of
+        // heads linking them to the copies are new and must still be met.
+        let resolve = |member: Member<I>| match member {
+            Member::Copied(position) => EntityId::at(base + position),
+            Member::Shared(id) => id,
+        };
+        let duplicates = self.graph_config.queue_duplicates;
+        for &(lhs, rhs) in &scheme.links {
+            self.worklist
+                .push_edge((Value(resolve(lhs)), Use(resolve(rhs))), duplicates);
+        }
+        if self.settle().is_err() {
+            panic!("instance of a scheme failed to meet the nodes it shares");
+        }
+
+        scheme
+            .roots
+            .iter()
+            .map(|&root| Value(resolve(root)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::{Head, HeadTypeSystem};
+
+    fn call(t: &mut TypeChecker<Head, Head, HeadTypeSystem>, func: Value, arg: Value) -> Value {
+        let (ret_val, _) = t.var();
+        let call = t.new_use(Head::Func(Use(arg.0), ret_val));
+        assert!(t.flow(func, call).is_ok());
+        ret_val
+    }
+
+    #[test]
+    fn instances_of_a_scheme_should_not_share_nodes() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let (x_val, x_use) = t.var();
+        let identity = t.new_val(Head::Func(x_use, x_val));
+        let scheme = t.freeze_scheme(&[identity]);
+
+        let first = t.instantiate(&scheme)[0];
+        let second = t.instantiate(&scheme)[0];
+        assert_eq!(6, t.types.len());
+
+        let int = t.new_val(Head::Int);
+        let boolean = t.new_val(Head::Bool);
+        let int_result = call(&mut t, first, int);
+        let bool_result = call(&mut t, second, boolean);
+
+        let int_use = t.new_use(Head::Int);
+        let bool_use = t.new_use(Head::Bool);
+        assert!(t.flow(int_result, int_use).is_ok());
+        assert!(t.flow(bool_result, bool_use).is_ok());
+        assert!(!t.reaches(x_val, int_use));
+    }
+}
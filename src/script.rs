@@ -0,0 +1,220 @@
+//! A small textual constraint language, for writing regression tests as
+//! golden files and for feeding external corpora to the checker. A script
+//! is a series of statements, each ended by a `;`:
+//!
+//! ```text
+//! # comments run to the end of the line
+//! var x;          # a var, whose name stands for both of its sides
+//! v0 = Int;       # a value with the head `Int`
+//! u1 : Int;       # a use with the head `Int`
+//! flow v0 x;      # a flow from a value to a use
+//! flow x u1;
+//! ```
+//!
+//! Heads are parsed by a `HeadParser`, which can refer to the nodes bound
+//! so far by name.
+
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::{AbstractTypes, TypeChecker, Use, Value};
+
+/// HeadParser turns the text after `=` or `:` into a head.
+pub trait HeadParser<V, U> {
+    fn parse_value(&self, text: &str, names: &Names) -> Result<V, String>;
+    fn parse_use(&self, text: &str, names: &Names) -> Result<U, String>;
+}
+
+/// Names holds the handles bound by a script, by name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Names {
+    values: HashMap<String, Value>,
+    uses: HashMap<String, Use>,
+}
+
+impl Names {
+    /// Returns the value bound to a name, or the value side of a var.
+    pub fn value(&self, name: &str) -> Option<Value> {
+        self.values.get(name).copied()
+    }
+
+    /// Returns the use bound to a name, or the use side of a var.
+    pub fn use_of(&self, name: &str) -> Option<Use> {
+        self.uses.get(name).copied()
+    }
+}
+
+/// ScriptError reports the line of a script that couldn't be run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptError<E> {
+    /// The statement couldn't be parsed.
+    Parse { line: usize, message: String },
+    /// The statement's flow failed.
+    Flow { line: usize, error: E },
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for ScriptError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            Self::Flow { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error> std::error::Error for ScriptError<E> {}
+
+/// Splits a script into its statements, each with the line it starts on.
+fn statements(source: &str) -> Vec<(usize, String)> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut start = None;
+    for (idx, line) in source.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or_default();
+        for c in code.chars() {
+            if c == ';' {
+                statements.push((start.take().unwrap_or(idx + 1), current.trim().to_string()));
+                current.clear();
+            } else {
+                if start.is_none() && !c.is_whitespace() {
+                    start = Some(idx + 1);
+                }
+                current.push(c);
+            }
+        }
+        current.push(' ');
+    }
+    if let Some(line) = start {
+        statements.push((line, current.trim().to_string()));
+    }
+    statements
+}
+
+impl<V, U, AT> TypeChecker<V, U, AT>
+where
+    AT: AbstractTypes<V, U>,
+{
+    /// Runs a script against the checker, creating its nodes and adding its
+    /// flows in order, and returns the names it bound. Stops at the first
+    /// statement that can't be parsed or whose flow fails.
+    pub fn load_script(
+        &mut self,
+        source: &str,
+        parser: &impl HeadParser<V, U>,
+    ) -> Result<Names, ScriptError<AT::Error>> {
+        let mut names = Names::default();
+        for (line, statement) in statements(source) {
+            let parse = |message: String| ScriptError::Parse { line, message };
+            let words: Vec<&str> = statement.split_whitespace().collect();
+            match words.as_slice() {
+                [] => (),
+                ["var", name] => {
+                    let (value, var_use) = self.var();
+                    names.values.insert(name.to_string(), value);
+                    names.uses.insert(name.to_string(), var_use);
+                }
+                ["flow", lhs, rhs] => {
+                    let lhs = names
+                        .value(lhs)
+                        .ok_or_else(|| parse(format!("unknown value `{}`", lhs)))?;
+                    let rhs = names
+                        .use_of(rhs)
+                        .ok_or_else(|| parse(format!("unknown use `{}`", rhs)))?;
+                    self.flow(lhs, rhs)
+                        .map_err(|error| ScriptError::Flow { line, error })?;
+                }
+                _ => {
+                    let idx = statement
+                        .find(['=', ':'])
+                        .ok_or_else(|| parse(format!("unknown statement `{}`", statement)))?;
+                    let (name, head) = (statement[..idx].trim(), statement[idx + 1..].trim());
+                    if name.is_empty() || name.contains(char::is_whitespace) {
+                        return Err(parse(format!("invalid name `{}`", name)));
+                    }
+                    if statement[idx..].starts_with('=') {
+                        let head = parser.parse_value(head, &names).map_err(parse)?;
+                        let value = self.new_val(head);
+                        names.values.insert(name.to_string(), value);
+                    } else {
+                        let head = parser.parse_use(head, &names).map_err(parse)?;
+                        let rhs = self.new_use(head);
+                        names.uses.insert(name.to_string(), rhs);
+                    }
+                }
+            }
+        }
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::{Head, HeadTypeSystem};
+    use crate::TypeError;
+
+    struct Heads;
+
+    impl Heads {
+        fn parse(&self, text: &str, names: &Names) -> Result<Head, String> {
+            match text {
+                "Int" => Ok(Head::Int),
+                "Bool" => Ok(Head::Bool),
+                _ => {
+                    let elem = text
+                        .strip_prefix("List ")
+                        .ok_or_else(|| format!("unknown head `{}`", text))?;
+                    names
+                        .value(elem)
+                        .map(Head::List)
+                        .ok_or_else(|| format!("unknown value `{}`", elem))
+                }
+            }
+        }
+    }
+
+    impl HeadParser<Head, Head> for Heads {
+        fn parse_value(&self, text: &str, names: &Names) -> Result<Head, String> {
+            self.parse(text, names)
+        }
+
+        fn parse_use(&self, text: &str, names: &Names) -> Result<Head, String> {
+            self.parse(text, names)
+        }
+    }
+
+    #[test]
+    fn scripts_should_build_the_graph_they_describe() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let names = t
+            .load_script(
+                "var x; var elem;\n\
+                 v0 = Int;\n\
+                 list = List v0; # a list of ints\n\
+                 sink : List elem;\n\
+                 flow list x; flow x sink;",
+                &Heads,
+            )
+            .unwrap();
+        let (int, elem) = (names.value("v0").unwrap(), names.use_of("elem").unwrap());
+        assert!(t.reaches(int, elem));
+
+        let result = t.load_script("var y;\nu : Bool;\nflow v0 u;", &Heads);
+        assert_eq!(
+            Err(ScriptError::Parse {
+                line: 3,
+                message: "unknown value `v0`".to_string()
+            }),
+            result
+        );
+
+        let result = t.load_script("b = Bool;\n\nu : Int;\nflow b\n  u;", &Heads);
+        assert_eq!(
+            Err(ScriptError::Flow {
+                line: 4,
+                error: TypeError::Converge
+            }),
+            result
+        );
+    }
+}
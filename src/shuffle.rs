@@ -0,0 +1,97 @@
+//! A testing mode that takes work off the worklist in a seeded random order
+//! rather than in the configured `WorklistOrder`, so that a type system can be fuzzed for
+//! meets that depend on the order they are called in. Changing the order
+//! changes the order edges are derived in and which failure is hit first
+//! when more than one flow would fail. A type system that refines heads can
+//! also end up with different heads, as each refinement sees the ones
+//! before it.
+
+use crate::{AbstractTypes, NodeIndex, TypeChecker, WorklistOrder};
+use alloc::collections::VecDeque;
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Takes work off the worklist in an order drawn from the seed, or in
+    /// the usual order if the seed is None. The same seed always gives the
+    /// same order for the same flows.
+    pub fn shuffle_worklist(&mut self, seed: Option<u64>) {
+        self.shuffle = seed;
+    }
+}
+
+/// Takes the next item off the work in the given order, or a random one if
+/// a shuffle state is given, advancing it.
+pub(crate) fn pop<T>(
+    state: &mut Option<u64>,
+    order: WorklistOrder,
+    work: &mut VecDeque<T>,
+) -> Option<T> {
+    match (state, order) {
+        (Some(state), _) if !work.is_empty() => {
+            let idx = (next(state) % work.len() as u64) as usize;
+            work.swap_remove_back(idx)
+        }
+        (_, WorklistOrder::Lifo) => work.pop_back(),
+        (_, WorklistOrder::Fifo) => work.pop_front(),
+    }
+}
+
+/// Advances a splitmix64 generator, returning its next output.
+pub(crate) fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Head, HeadTypeSystem};
+    use crate::*;
+
+    type Edges = Vec<(Value, Use)>;
+
+    /// Builds a pair of pairs flowing through a chain of vars, returning the
+    /// order its direct edges were added in and its sorted edges.
+    fn check(seed: Option<u64>) -> (Edges, Edges) {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        t.shuffle_worklist(seed);
+        let ints: Vec<Value> = (0..4).map(|_| t.new_val(Head::Int)).collect();
+        let (inner1, inner2) = (
+            t.new_val(Head::Pair(ints[0], ints[1])),
+            t.new_val(Head::Pair(ints[2], ints[3])),
+        );
+        let outer = t.new_val(Head::Pair(inner1, inner2));
+        let vars: Vec<(Value, Use)> = (0..6).map(|_| t.var()).collect();
+        let sink = t.new_use(Head::Pair(vars[0].0, vars[1].0));
+        let targets: Vec<Use> = vars.iter().map(|&(_, var_use)| var_use).collect();
+        let (left, right) = (
+            t.new_use(Head::Pair(vars[2].0, vars[3].0)),
+            t.new_use(Head::Pair(vars[4].0, vars[5].0)),
+        );
+        assert!(t.flow(outer, sink).is_ok());
+        assert!(t.flow(vars[0].0, left).is_ok());
+        assert!(t.flow(vars[1].0, right).is_ok());
+        assert!(t.reaches(ints[3], targets[5]));
+
+        let mut edges: Vec<_> = t.r.edges().collect();
+        edges.sort_unstable();
+        let edges = edges.into_iter().map(|(lhs, rhs)| (Value(lhs), Use(rhs)));
+        (t.direct_edges().collect(), edges.collect())
+    }
+
+    #[test]
+    fn shuffled_worklists_should_build_the_same_graph() {
+        let (order, edges) = check(None);
+        assert_eq!((order.clone(), edges.clone()), check(None));
+
+        let shuffled: Vec<_> = (0..8).map(|seed| check(Some(seed))).collect();
+        assert!(shuffled.iter().all(|(_, shuffled)| *shuffled == edges));
+        assert!(shuffled.iter().any(|(shuffled, _)| *shuffled != order));
+        assert_eq!(shuffled[3], check(Some(3)));
+    }
+}
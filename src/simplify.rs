@@ -0,0 +1,218 @@
+use crate::collections::HashMap;
+use crate::prelude::*;
+use crate::scheme::Instantiate;
+use crate::{AbstractTypes, EntityId, NodeIndex, Remap, TypeChecker, TypeNode, Use, Value};
+
+/// The sorted upstream and downstream sets of a node.
+type Neighbours<I> = (Vec<EntityId<I>>, Vec<EntityId<I>>);
+
+/// A head and the neighbours of its node, along with every node sharing
+/// both, which can be merged into one.
+type Class<V, U, I> = (TypeNode<V, U>, Neighbours<I>, Vec<EntityId<I>>);
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Merges var nodes that have identical upstream and downstream sets,
+    /// which includes vars that mutually flow into each other. Every merged
+    /// var is detached from the graph and any later flow through its handles
+    /// is redirected to the var standing in for it. Returns the number of
+    /// vars that were merged away.
+    pub fn merge_equivalent_vars(&mut self) -> usize {
+        let mut classes: HashMap<Neighbours<I>, Vec<EntityId<I>>> = HashMap::new();
+        let mut order = Vec::new();
+
+        for (idx, node) in self.types.iter().enumerate() {
+            let id = EntityId::at(idx);
+            if !matches!(node, TypeNode::Var)
+                || self.aliases.find(id) != id
+                || self.r.is_removed(id)
+            {
+                continue;
+            }
+
+            let mut upstream: Vec<EntityId<I>> = self.r.upstream(id).collect();
+            let mut downstream: Vec<EntityId<I>> = self.r.downstream(id).collect();
+            upstream.sort_unstable();
+            downstream.sort_unstable();
+
+            let key = (upstream, downstream);
+            if !classes.contains_key(&key) {
+                order.push(key.clone());
+            }
+            classes.entry(key).or_default().push(id);
+        }
+
+        let mut merged = 0;
+        for key in order {
+            let members = &classes[&key];
+            let root = members
+                .iter()
+                .fold(members[0], |root, &member| self.aliases.union(root, member));
+
+            for &member in members.iter().filter(|&&member| member != root) {
+                self.r.detach_mut(member);
+                merged += 1;
+            }
+        }
+        merged
+    }
+
+    /// Returns the value handle standing in for `value` after any merges.
+    pub fn canonical_value(&mut self, value: Value<I>) -> Value<I> {
+        Value(self.aliases.find(value.0))
+    }
+
+    /// Returns the use handle standing in for `rhs` after any merges.
+    pub fn canonical_use(&mut self, rhs: Use<I>) -> Use<I> {
+        Use(self.aliases.find(rhs.0))
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Instantiate<I> + PartialEq,
+    U: Instantiate<I> + PartialEq,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Shrinks the graph down to what the roots need, condensing cycles,
+    /// then merging equivalent vars and duplicated heads until no more
+    /// merges are possible and then compacting away every node the roots
+    /// can't reach. Two heads are duplicates when they are equal once the
+    /// handles nested inside them are replaced by the nodes standing in for
+    /// them, and they share the same upstream and downstream sets. As with
+    /// `compact`, every handle created before the call must be passed
+    /// through the returned Remap.
+    ///
+    /// Panics if a checkpoint or a binding level is open.
+    pub fn simplify(&mut self, values: &[Value<I>], uses: &[Use<I>]) -> Remap<I> {
+        self.condense_cycles();
+        while self.merge_equivalent_vars() + self.merge_equivalent_heads() > 0 {}
+        self.compact(values, uses)
+    }
+
+    /// Merges head nodes that are duplicates of one another, returning the
+    /// number of heads that were merged away.
+    fn merge_equivalent_heads(&mut self) -> usize {
+        let values = |value: Value<I>| Value(self.aliases.root(value.0));
+        let uses = |rhs: Use<I>| Use(self.aliases.root(rhs.0));
+        let mut classes: Vec<Class<V, U, I>> = Vec::new();
+
+        for (idx, node) in self.types.iter().enumerate() {
+            let id = EntityId::at(idx);
+            // heads given more heads are left alone, as only the first
+            // would be compared.
+            if self.aliases.root(id) != id || self.extra_heads.contains_key(&id) {
+                continue;
+            }
+            let head = match node {
+                TypeNode::Var => continue,
+                TypeNode::Value(head) => TypeNode::Value(head.instantiate(&values, &uses)),
+                TypeNode::Use(head) => TypeNode::Use(head.instantiate(&values, &uses)),
+            };
+
+            let mut upstream: Vec<EntityId<I>> = self.r.upstream(id).collect();
+            let mut downstream: Vec<EntityId<I>> = self.r.downstream(id).collect();
+            upstream.sort_unstable();
+            downstream.sort_unstable();
+
+            let neighbours = (upstream, downstream);
+            match classes
+                .iter_mut()
+                .find(|class| class.0 == head && class.1 == neighbours)
+            {
+                Some(class) => class.2.push(id),
+                None => classes.push((head, neighbours, vec![id])),
+            }
+        }
+
+        let mut merged = 0;
+        for (_, _, members) in classes {
+            let root = members
+                .iter()
+                .fold(members[0], |root, &member| self.aliases.union(root, member));
+
+            for &member in members.iter().filter(|&&member| member != root) {
+                self.r.detach_mut(member);
+                merged += 1;
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Head, HeadTypeSystem, Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn should_merge_mutually_flowing_vars() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let (a_val, a_use) = t.var();
+        let (b_val, b_use) = t.var();
+
+        assert!(t.flow(int, a_use).is_ok());
+        assert!(t.flow(a_val, b_use).is_ok());
+        assert!(t.flow(b_val, a_use).is_ok());
+        let edges_before = t.r.edges().count();
+
+        assert_eq!(1, t.merge_equivalent_vars());
+        assert!(t.r.edges().count() < edges_before);
+        assert_eq!(t.canonical_value(a_val), t.canonical_value(b_val));
+
+        // flows through the merged var still reach the survivor.
+        let bool_use = t.new_use(Prim::Bool);
+        assert_eq!(Err(TypeError::Converge), t.flow(b_val, bool_use));
+    }
+
+    #[test]
+    fn should_merge_vars_with_identical_neighbours() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let int_use = t.new_use(Prim::Int);
+        let vars: Vec<(Value, Use)> = (0..3).map(|_| t.var()).collect();
+        for &(val, use_) in &vars {
+            assert!(t.flow(int, use_).is_ok());
+            assert!(t.flow(val, int_use).is_ok());
+        }
+
+        // a var with different neighbours is left alone.
+        let (lone, _) = t.var();
+        assert!(t.flow(lone, int_use).is_ok());
+
+        assert_eq!(2, t.merge_equivalent_vars());
+        assert_eq!(0, t.merge_equivalent_vars());
+        let attached = vars
+            .iter()
+            .filter(|(_, var_use)| t.r.upstream(var_use.0).count() > 0)
+            .count();
+        assert_eq!(1, attached);
+    }
+
+    #[test]
+    fn simplify_should_merge_duplicate_heads_and_drop_the_rest() {
+        let mut t = TypeChecker::new(HeadTypeSystem);
+        let int = t.new_val(Head::Int);
+        let (x_val, x_use) = t.var();
+        for _ in 0..2 {
+            let list = t.new_val(Head::List(int));
+            assert!(t.flow(list, x_use).is_ok());
+        }
+        let (unused, _) = t.var();
+
+        let remap = t.simplify(&[x_val], &[]);
+        assert_eq!(3, t.types.len());
+        assert_eq!(None, remap.map_value(unused));
+
+        let x_val = remap.map_value(x_val).unwrap();
+        assert_eq!(1, t.flows_from(Use(x_val.0)).count());
+        let int_use = t.new_use(Head::Int);
+        let list_use = t.new_use(Head::List(Value(int_use.0)));
+        assert!(t.flow(x_val, list_use).is_ok());
+    }
+}
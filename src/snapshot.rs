@@ -0,0 +1,99 @@
+use crate::collections::HashSet;
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, Use, Value};
+
+/// Snapshot is a copy of which nodes and edges a checker held at one point
+/// in time, for asserting on exactly what later calls added. Unlike a
+/// `Checkpoint` it can't be restored, but it holds no borrow of the checker
+/// and any two snapshots can be compared.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot<I = u32> {
+    nodes: Vec<EntityId<I>>,
+    direct: Vec<(Value<I>, Use<I>)>,
+    derived: Vec<(Value<I>, Use<I>)>,
+}
+
+/// GraphDiff lists what a later snapshot holds that an earlier one doesn't,
+/// each in the order the later snapshot lists them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphDiff<I = u32> {
+    pub added_nodes: Vec<EntityId<I>>,
+    /// the edges added by the caller or by a meet.
+    pub added_direct: Vec<(Value<I>, Use<I>)>,
+    /// the edges added only to close over the direct ones.
+    pub added_derived: Vec<(Value<I>, Use<I>)>,
+}
+
+impl<I> GraphDiff<I> {
+    /// Returns true if nothing was added.
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty() && self.added_direct.is_empty() && self.added_derived.is_empty()
+    }
+}
+
+impl<I: NodeIndex> Snapshot<I> {
+    /// Returns what `other` holds that this snapshot doesn't, so that
+    /// diffing an earlier snapshot against a later one gives what was added
+    /// in between.
+    pub fn diff(&self, other: &Snapshot<I>) -> GraphDiff<I> {
+        fn added<T: Copy + crate::Key>(before: &[T], after: &[T]) -> Vec<T> {
+            let before: HashSet<T> = before.iter().copied().collect();
+            after
+                .iter()
+                .copied()
+                .filter(|item| !before.contains(item))
+                .collect()
+        }
+
+        GraphDiff {
+            added_nodes: added(&self.nodes, &other.nodes),
+            added_direct: added(&self.direct, &other.direct),
+            added_derived: added(&self.derived, &other.derived),
+        }
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Takes a snapshot of the nodes and edges the checker holds now.
+    pub fn snapshot(&self) -> Snapshot<I> {
+        Snapshot {
+            nodes: self.r.nodes().collect(),
+            direct: self.direct_edges().collect(),
+            derived: self.derived_edges().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn diffs_should_list_what_each_flow_added() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let (x_val, x_use) = t.var();
+        assert!(t.flow(int, x_use).is_ok());
+
+        let before = t.snapshot();
+        let sink = t.new_use(Prim::Int);
+        assert!(t.flow(x_val, sink).is_ok());
+        let after = t.snapshot();
+
+        assert_eq!(
+            GraphDiff {
+                added_nodes: vec![sink.0],
+                added_direct: vec![(x_val, sink)],
+                added_derived: vec![(int, sink)],
+            },
+            before.diff(&after)
+        );
+        assert!(after.diff(&before).is_empty());
+        assert!(after.diff(&t.snapshot()).is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{AbstractTypes, NodeIndex, TypeChecker, TypeNode};
+
+/// Stats summarizes the size of a checker's graph and the work done to
+/// build it, for finding out why a program checks slowly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub vars: usize,
+    pub values: usize,
+    pub uses: usize,
+    /// the edges added by the caller or by a meet that are still in the
+    /// graph.
+    pub direct_edges: usize,
+    /// the edges added only to close over the direct ones.
+    pub transitive_edges: usize,
+    /// the pairs of heads met so far.
+    pub meets: usize,
+    /// the most nodes any single node flows into.
+    pub max_downstream: usize,
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns statistics on the graph and the work done to build it.
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats {
+            meets: self.meets,
+            max_downstream: self.r.max_downstream(),
+            ..Stats::default()
+        };
+        for node in self.r.nodes().map(|id| &self.types[usize::from(id)]) {
+            match node {
+                TypeNode::Var => stats.vars += 1,
+                TypeNode::Value(_) => stats.values += 1,
+                TypeNode::Use(_) => stats.uses += 1,
+            }
+        }
+
+        stats.direct_edges = self.direct_edges().count();
+        stats.transitive_edges = self.r.edge_count() - stats.direct_edges;
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::Permissive;
+    use crate::*;
+
+    #[test]
+    fn stats_should_count_nodes_edges_and_meets() {
+        let mut t = TypeChecker::new(Permissive);
+        let (x_val, x_use) = t.var();
+        let value = t.new_val(());
+        let first = t.new_use(());
+        let second = t.new_use(());
+        assert!(t.flow(value, x_use).is_ok());
+        assert!(t.flow(x_val, first).is_ok());
+        assert!(t.flow(x_val, second).is_ok());
+
+        assert_eq!(
+            Stats {
+                vars: 1,
+                values: 1,
+                uses: 2,
+                direct_edges: 3,
+                transitive_edges: 2,
+                meets: 2,
+                max_downstream: 3,
+            },
+            t.stats()
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use crate::prelude::*;
+use crate::{AbstractTypes, NodeIndex, TypeChecker, Use, Value};
+
+const GREEK: &[char] = &[
+    'α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ', 'ν', 'ξ', 'ο', 'π', 'ρ', 'σ', 'τ',
+    'υ', 'φ', 'χ', 'ψ', 'ω',
+];
+
+/// VarSupply creates vars along with the names they are displayed with.
+/// Without a prefix vars are named `α` through `ω` and then `α1` and so on,
+/// while a prefix names them by number, which lets a frontend give related
+/// groups of vars, such as those of a single definition, recognizable
+/// names.
+#[derive(Debug, Clone, Default)]
+pub struct VarSupply {
+    prefix: Option<String>,
+    next: usize,
+}
+
+impl VarSupply {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a supply naming its vars `prefix0`, `prefix1` and so on.
+    pub fn with_prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: Some(prefix.into()),
+            next: 0,
+        }
+    }
+
+    fn next_name(&mut self) -> String {
+        let n = self.next;
+        self.next += 1;
+
+        match &self.prefix {
+            Some(prefix) => format!("{}{}", prefix, n),
+            None => match n / GREEK.len() {
+                0 => GREEK[n].to_string(),
+                round => format!("{}{}", GREEK[n % GREEK.len()], round),
+            },
+        }
+    }
+
+    /// Creates a new var in the checker, naming it with the next name of
+    /// the supply.
+    pub fn fresh<V, U, AT, I>(
+        &mut self,
+        checker: &mut TypeChecker<V, U, AT, I>,
+    ) -> (Value<I>, Use<I>)
+    where
+        I: NodeIndex,
+        AT: AbstractTypes<V, U, I>,
+    {
+        let (value, var_use) = checker.var();
+        checker.var_names.insert(value.0, self.next_name());
+        (value, var_use)
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns the name given to a var by a VarSupply.
+    pub fn var_name(&self, value: Value<I>) -> Option<&str> {
+        self.var_names.get(&value.0).map(String::as_str)
+    }
+
+    /// Returns the var with a name, creating and naming it the first time
+    /// the name is asked for, so that a frontend can refer to its vars by
+    /// the names it already has for them, such as `ret_of_main`.
+    pub fn var_named(&mut self, name: &str) -> (Value<I>, Use<I>) {
+        if let Some(var) = self.var_by_name(name) {
+            return var;
+        }
+        let (value, var_use) = self.var();
+        self.var_names.insert(value.0, name.to_string());
+        self.named_vars.insert(name.to_string(), value.0);
+        (value, var_use)
+    }
+
+    /// Returns the var created by `var_named` with a name, if any.
+    pub fn var_by_name(&self, name: &str) -> Option<(Value<I>, Use<I>)> {
+        self.named_vars.get(name).map(|&id| (Value(id), Use(id)))
+    }
+
+    /// Returns the name of a var given by `var_named` or a VarSupply. A var
+    /// merged into another has the name of the one standing in for it, if
+    /// it has none of its own.
+    pub fn name_of(&self, value: Value<I>) -> Option<&str> {
+        self.var_names
+            .get(&value.0)
+            .or_else(|| self.var_names.get(&self.aliases.root(value.0)))
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Debug, Clone)]
+    struct Func(Use, Value);
+
+    impl Describe for Func {
+        fn label(&self) -> String {
+            "fn".to_string()
+        }
+
+        fn children(&self) -> Vec<Child> {
+            vec![Child::Use(self.0), Child::Value(self.1)]
+        }
+    }
+
+    #[derive(Debug)]
+    struct FuncTypeSystem;
+
+    impl AbstractTypes<Func, Func> for FuncTypeSystem {
+        type Error = TypeError;
+
+        fn meet(&self, _: &Func, _: &Func) -> Result<Vec<(Value, Use)>, Self::Error> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn supplied_vars_should_be_described_by_name() {
+        let mut t = TypeChecker::new(FuncTypeSystem);
+        let mut greek = VarSupply::new();
+        let mut prefixed = VarSupply::with_prefix("t");
+
+        let (a_val, a_use) = greek.fresh(&mut t);
+        let (b_val, _) = greek.fresh(&mut t);
+        let (_, c_use) = prefixed.fresh(&mut t);
+        let f = t.new_val(Func(a_use, a_val));
+        let g = t.new_val(Func(c_use, b_val));
+
+        assert_eq!("fn(α, α)", t.describe(f).to_string());
+        assert_eq!("fn(t0, β)", t.describe_simplified(g).to_string());
+        assert_eq!(Some("β"), t.var_name(b_val));
+    }
+
+    #[test]
+    fn named_vars_should_be_interned() {
+        let mut t = TypeChecker::new(FuncTypeSystem);
+        let (a_val, a_use) = t.var_named("a");
+        assert_eq!((a_val, a_use), t.var_named("a"));
+        assert_eq!(Some((a_val, a_use)), t.var_by_name("a"));
+        assert_eq!(Some("a"), t.name_of(a_val));
+        assert_eq!(None, t.var_by_name("b"));
+
+        let checkpoint = t.save();
+        let (ret, _) = t.var_named("ret_of_main");
+        assert_eq!(Some("ret_of_main"), t.name_of(ret));
+        t.restore(checkpoint);
+        assert_eq!(None, t.var_by_name("ret_of_main"));
+    }
+}
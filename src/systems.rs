@@ -0,0 +1,318 @@
+//! Ready-made type systems for small functional languages, so that a
+//! frontend can be type-checked without writing the lattice itself.
+
+use crate::prelude::*;
+use crate::{AbstractTypes, Child, Describe, Fields, MeetContext, Use, Value, Variance};
+use alloc::collections::BTreeMap;
+
+/// VTypeHead enumerates the values of the simple type system.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VTypeHead {
+    /// A function, taking its argument through `arg` and producing `ret`.
+    VFunc { arg: Use, ret: Value },
+    /// A record providing a value for each of its fields.
+    VRecord { fields: BTreeMap<String, Value> },
+    /// A single variant of a sum type, tagged with `tag`.
+    VCase { tag: String, value: Value },
+}
+
+/// UTypeHead enumerates the uses of the simple type system.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UTypeHead {
+    /// A call, passing `arg` and receiving the result through `ret`.
+    UFunc { arg: Value, ret: Use },
+    /// A record access, requiring each of the fields.
+    URecord { fields: BTreeMap<String, Use> },
+    /// A row-polymorphic record access, requiring the field `label` and
+    /// passing a record of every other field on to `rest`, so that chained
+    /// accesses can each take a field off the same row.
+    URecordField {
+        label: String,
+        field: Use,
+        rest: Use,
+    },
+    /// A match, handling each of the tags.
+    UCase { cases: BTreeMap<String, Use> },
+}
+
+impl VTypeHead {
+    fn name(&self) -> &'static str {
+        match self {
+            VTypeHead::VFunc { .. } => "function",
+            VTypeHead::VRecord { .. } => "record",
+            VTypeHead::VCase { .. } => "case",
+        }
+    }
+}
+
+impl UTypeHead {
+    fn name(&self) -> &'static str {
+        match self {
+            UTypeHead::UFunc { .. } => "function",
+            UTypeHead::URecord { .. } | UTypeHead::URecordField { .. } => "record",
+            UTypeHead::UCase { .. } => "case",
+        }
+    }
+}
+
+/// SimpleError enumerates the ways a value can fail to satisfy a use in the
+/// simple type system.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimpleError {
+    /// A value of one kind of head flowed into a use of another.
+    Mismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// A record was missing a field required of it.
+    MissingField(String),
+    /// A case reached a match that doesn't handle its tag.
+    UnhandledCase(String),
+}
+
+impl core::fmt::Display for SimpleError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Mismatch { expected, found } => {
+                write!(f, "expected {} but found {}.", expected, found)
+            }
+            Self::MissingField(name) => write!(f, "missing field {}.", name),
+            Self::UnhandledCase(tag) => write!(f, "unhandled case {}.", tag),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SimpleError {}
+
+/// SimpleTypeSystem relates functions, records and cases. Arguments flow
+/// contravariantly from the call into the function, while results, fields
+/// and case payloads flow covariantly out to their uses. Row accesses create
+/// the record of remaining fields they pass on, so they are only met through
+/// `meet_with`, as the checker does.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimpleTypeSystem;
+
+impl AbstractTypes<VTypeHead, UTypeHead> for SimpleTypeSystem {
+    type Error = SimpleError;
+
+    fn meet(&self, lhs: &VTypeHead, rhs: &UTypeHead) -> Result<Vec<(Value, Use)>, Self::Error> {
+        match (lhs, rhs) {
+            (
+                VTypeHead::VFunc { arg, ret },
+                UTypeHead::UFunc {
+                    arg: call_arg,
+                    ret: call_ret,
+                },
+            ) => Ok(vec![
+                Variance::Contravariant.relate(Child::Use(*arg), Child::Value(*call_arg)),
+                Variance::Covariant.relate(Child::Value(*ret), Child::Use(*call_ret)),
+            ]),
+            (VTypeHead::VRecord { fields }, UTypeHead::URecord { fields: required }) => required
+                .iter()
+                .map(|(name, &rhs)| match fields.get(name) {
+                    Some(&lhs) => Ok((lhs, rhs)),
+                    None => Err(SimpleError::MissingField(name.clone())),
+                })
+                .collect(),
+            (VTypeHead::VCase { tag, value }, UTypeHead::UCase { cases }) => match cases.get(tag) {
+                Some(&rhs) => Ok(vec![(*value, rhs)]),
+                None => Err(SimpleError::UnhandledCase(tag.clone())),
+            },
+            (VTypeHead::VRecord { .. }, UTypeHead::URecordField { .. }) => {
+                unreachable!("row accesses are met through meet_with")
+            }
+            (lhs, rhs) => Err(SimpleError::Mismatch {
+                expected: rhs.name(),
+                found: lhs.name(),
+            }),
+        }
+    }
+
+    fn meet_with(
+        &self,
+        ctx: &mut MeetContext<VTypeHead, UTypeHead>,
+        lhs: &VTypeHead,
+        rhs: &UTypeHead,
+    ) -> Result<Vec<(Value, Use)>, Self::Error> {
+        match (lhs, rhs) {
+            (VTypeHead::VRecord { fields }, UTypeHead::URecordField { label, field, rest }) => {
+                let value = match fields.get(label) {
+                    Some(&value) => value,
+                    None => return Err(SimpleError::MissingField(label.clone())),
+                };
+                let mut others = fields.clone();
+                others.remove(label);
+                let row = ctx.new_val(VTypeHead::VRecord { fields: others });
+                Ok(vec![(value, *field), (row, *rest)])
+            }
+            _ => self.meet(lhs, rhs),
+        }
+    }
+}
+
+impl Describe for VTypeHead {
+    fn label(&self) -> String {
+        match self {
+            VTypeHead::VFunc { .. } => "fn".to_string(),
+            VTypeHead::VRecord { fields } => {
+                let names: Vec<&str> = fields.keys().map(String::as_str).collect();
+                format!("{{{}}}", names.join(", "))
+            }
+            VTypeHead::VCase { tag, .. } => format!("`{}", tag),
+        }
+    }
+
+    fn children(&self) -> Vec<Child> {
+        match self {
+            VTypeHead::VFunc { arg, ret } => vec![Child::Use(*arg), Child::Value(*ret)],
+            VTypeHead::VRecord { fields } => fields.values().map(|&v| Child::Value(v)).collect(),
+            VTypeHead::VCase { value, .. } => vec![Child::Value(*value)],
+        }
+    }
+}
+
+impl Describe for UTypeHead {
+    fn label(&self) -> String {
+        match self {
+            UTypeHead::UFunc { .. } => "fn".to_string(),
+            UTypeHead::URecord { fields } => {
+                let names: Vec<&str> = fields.keys().map(String::as_str).collect();
+                format!("{{{}}}", names.join(", "))
+            }
+            UTypeHead::URecordField { label, .. } => format!("{{{}, ..}}", label),
+            UTypeHead::UCase { cases } => {
+                let tags: Vec<String> = cases.keys().map(|tag| format!("`{}", tag)).collect();
+                format!("[{}]", tags.join(" | "))
+            }
+        }
+    }
+
+    fn children(&self) -> Vec<Child> {
+        match self {
+            UTypeHead::UFunc { arg, ret } => vec![Child::Value(*arg), Child::Use(*ret)],
+            UTypeHead::URecord { fields } => fields.values().map(|&u| Child::Use(u)).collect(),
+            UTypeHead::URecordField { field, rest, .. } => {
+                vec![Child::Use(*field), Child::Use(*rest)]
+            }
+            UTypeHead::UCase { cases } => cases.values().map(|&u| Child::Use(u)).collect(),
+        }
+    }
+}
+
+impl Fields for VTypeHead {
+    fn fields(&self) -> Vec<(String, Value)> {
+        match self {
+            VTypeHead::VRecord { fields } => fields
+                .iter()
+                .map(|(name, &value)| (name.clone(), value))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TypeChecker;
+
+    #[test]
+    fn calls_should_flow_arguments_in_and_results_out() {
+        let mut t = TypeChecker::new(SimpleTypeSystem);
+        let (x_val, x_use) = t.var();
+        let identity = t.new_val(VTypeHead::VFunc {
+            arg: x_use,
+            ret: x_val,
+        });
+
+        let record = t.new_val(VTypeHead::VRecord {
+            fields: BTreeMap::new(),
+        });
+        let (result, result_use) = t.var();
+        let call = t.new_use(UTypeHead::UFunc {
+            arg: record,
+            ret: result_use,
+        });
+        assert!(t.flow(identity, call).is_ok());
+        assert!(t.reaches(record, result_use));
+
+        let (_, field_use) = t.var();
+        let access = t.new_use(UTypeHead::URecord {
+            fields: vec![("x".to_string(), field_use)].into_iter().collect(),
+        });
+        assert_eq!(
+            Err(SimpleError::MissingField("x".to_string())),
+            t.flow(result, access)
+        );
+    }
+
+    #[test]
+    fn cases_should_require_a_handler_for_their_tag() {
+        let mut t = TypeChecker::new(SimpleTypeSystem);
+        let (payload, _) = t.var();
+        let (_, handler) = t.var();
+        let some = t.new_val(VTypeHead::VCase {
+            tag: "Some".to_string(),
+            value: payload,
+        });
+        let none = t.new_val(VTypeHead::VCase {
+            tag: "None".to_string(),
+            value: payload,
+        });
+        let matched = t.new_use(UTypeHead::UCase {
+            cases: vec![("Some".to_string(), handler)].into_iter().collect(),
+        });
+
+        assert!(t.flow(some, matched).is_ok());
+        assert!(t.reaches(payload, handler));
+        assert_eq!(
+            Err(SimpleError::UnhandledCase("None".to_string())),
+            t.flow(none, matched)
+        );
+    }
+
+    #[test]
+    fn row_accesses_should_pass_the_remaining_fields_on() {
+        let mut t = TypeChecker::new(SimpleTypeSystem);
+        let (x, _) = t.var();
+        let (y, _) = t.var();
+        let point = t.new_val(VTypeHead::VRecord {
+            fields: vec![("x".to_string(), x), ("y".to_string(), y)]
+                .into_iter()
+                .collect(),
+        });
+
+        let (_, x_use) = t.var();
+        let (rest, rest_use) = t.var();
+        let access = t.new_use(UTypeHead::URecordField {
+            label: "x".to_string(),
+            field: x_use,
+            rest: rest_use,
+        });
+        assert!(t.flow(point, access).is_ok());
+        assert!(t.reaches(x, x_use));
+
+        let (_, y_use) = t.var();
+        let (_, more) = t.var();
+        let next = t.new_use(UTypeHead::URecordField {
+            label: "y".to_string(),
+            field: y_use,
+            rest: more,
+        });
+        assert!(t.flow(rest, next).is_ok());
+        assert!(t.reaches(y, y_use));
+
+        // the row passed on no longer has the field taken off it.
+        let (_, again) = t.var();
+        let repeated = t.new_use(UTypeHead::URecordField {
+            label: "x".to_string(),
+            field: again,
+            rest: more,
+        });
+        assert_eq!(
+            Err(SimpleError::MissingField("x".to_string())),
+            t.flow(rest, repeated)
+        );
+    }
+}
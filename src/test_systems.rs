@@ -0,0 +1,146 @@
+//! Type systems shared by the crate's tests.
+
+use crate::prelude::*;
+use crate::{AbstractTypes, Child, Describe, Instantiate, TypeError, Use, Value};
+
+/// Prim is a primitive head, which only meets a use of the same primitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Prim {
+    Int,
+    Bool,
+    Str,
+}
+
+impl Describe for Prim {
+    fn label(&self) -> String {
+        format!("{:?}", self).to_lowercase()
+    }
+}
+
+impl Instantiate for Prim {
+    fn instantiate(&self, _: &dyn Fn(Value) -> Value, _: &dyn Fn(Use) -> Use) -> Self {
+        *self
+    }
+}
+
+/// Head is a primitive or a constructor over other nodes, for tests whose
+/// heads need children. It is used for both values and uses, so a child
+/// value of a use head stands for the use of the same node.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Head {
+    Int,
+    Bool,
+    /// a function from the use of its argument to the value of its result.
+    Func(Use, Value),
+    List(Value),
+    Pair(Value, Value),
+}
+
+impl Describe for Head {
+    fn label(&self) -> String {
+        match self {
+            Head::Int => "int",
+            Head::Bool => "bool",
+            Head::Func(..) => "fn",
+            Head::List(_) => "list",
+            Head::Pair(..) => "pair",
+        }
+        .to_string()
+    }
+
+    fn children(&self) -> Vec<Child> {
+        match *self {
+            Head::Func(arg, ret) => vec![Child::Use(arg), Child::Value(ret)],
+            Head::List(elem) => vec![Child::Value(elem)],
+            Head::Pair(left, right) => vec![Child::Value(left), Child::Value(right)],
+            Head::Int | Head::Bool => vec![],
+        }
+    }
+}
+
+impl Instantiate for Head {
+    fn instantiate(&self, values: &dyn Fn(Value) -> Value, uses: &dyn Fn(Use) -> Use) -> Self {
+        match *self {
+            Head::Func(arg, ret) => Head::Func(uses(arg), values(ret)),
+            Head::List(elem) => Head::List(values(elem)),
+            Head::Pair(left, right) => Head::Pair(values(left), values(right)),
+            ref prim => prim.clone(),
+        }
+    }
+}
+
+/// HeadTypeSystem meets equal primitives, and constructors of the same kind
+/// by flowing their children together: a function's argument backwards and
+/// everything else forwards.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HeadTypeSystem;
+
+impl AbstractTypes<Head, Head> for HeadTypeSystem {
+    type Error = TypeError;
+
+    fn meet(&self, lhs: &Head, rhs: &Head) -> Result<Vec<(Value, Use)>, Self::Error> {
+        match (lhs, rhs) {
+            (Head::Int, Head::Int) | (Head::Bool, Head::Bool) => Ok(vec![]),
+            (Head::Func(arg1, ret1), Head::Func(arg2, ret2)) => {
+                Ok(vec![(Value(arg2.0), *arg1), (*ret1, Use(ret2.0))])
+            }
+            (Head::List(elem1), Head::List(elem2)) => Ok(vec![(*elem1, Use(elem2.0))]),
+            (Head::Pair(left1, right1), Head::Pair(left2, right2)) => {
+                Ok(vec![(*left1, Use(left2.0)), (*right1, Use(right2.0))])
+            }
+            _ => Err(TypeError::Converge),
+        }
+    }
+}
+
+/// Strict only lets a head flow into a use of an equal head.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Strict;
+
+impl<H: PartialEq> AbstractTypes<H, H> for Strict {
+    type Error = TypeError;
+
+    fn meet(&self, lhs: &H, rhs: &H) -> Result<Vec<(Value, Use)>, Self::Error> {
+        if lhs == rhs {
+            Ok(vec![])
+        } else {
+            Err(TypeError::Converge)
+        }
+    }
+}
+
+/// Permissive lets every head flow into every use.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Permissive;
+
+impl<V, U> AbstractTypes<V, U> for Permissive {
+    type Error = TypeError;
+
+    fn meet(&self, _: &V, _: &U) -> Result<Vec<(Value, Use)>, Self::Error> {
+        Ok(vec![])
+    }
+}
+
+/// An integer known to lie within a range, checked against the range a
+/// use accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Range(pub(crate) i64, pub(crate) i64);
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RangeTypeSystem;
+
+impl AbstractTypes<Range, Range> for RangeTypeSystem {
+    type Error = TypeError;
+
+    fn meet(&self, lhs: &Range, rhs: &Range) -> Result<Vec<(Value, Use)>, Self::Error> {
+        if lhs.0.max(rhs.0) <= lhs.1.min(rhs.1) {
+            Ok(vec![])
+        } else {
+            Err(TypeError::Converge)
+        }
+    }
+
+    fn refine(&self, lhs: &Range, rhs: &Range) -> Option<Range> {
+        Some(Range(lhs.0.max(rhs.0), lhs.1.min(rhs.1)))
+    }
+}
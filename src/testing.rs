@@ -0,0 +1,393 @@
+//! Helpers for property-testing a type system against the checker, enabled
+//! by the `testing` feature. Generators build random graphs of flows from a
+//! seed, with heads drawn by the caller's own functions, and oracles check
+//! the invariants every checker should hold whatever its type system, so
+//! that a failing seed can be replayed and shrunk by hand or by a property
+//! testing crate driving the seed.
+
+use crate::prelude::*;
+use crate::{AbstractTypes, EntityId, GraphDiff, TypeChecker, Use, Value};
+
+/// Rng is a small seeded generator, so that the same seed always builds the
+/// same graph.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next number drawn.
+    pub fn next_u64(&mut self) -> u64 {
+        crate::shuffle::next(&mut self.state)
+    }
+
+    /// Returns a number drawn from `0..bound`, or 0 if the bound is 0.
+    pub fn below(&mut self, bound: usize) -> usize {
+        match bound {
+            0 => 0,
+            bound => (self.next_u64() % bound as u64) as usize,
+        }
+    }
+
+    /// Returns true with a chance of one in `odds`.
+    pub fn one_in(&mut self, odds: usize) -> bool {
+        self.below(odds) == 0
+    }
+}
+
+/// GraphShape is how many nodes and flows a generated graph has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphShape {
+    pub nodes: usize,
+    pub flows: usize,
+}
+
+impl Default for GraphShape {
+    fn default() -> Self {
+        Self {
+            nodes: 16,
+            flows: 32,
+        }
+    }
+}
+
+/// RandomGraph holds the nodes a generator created and the flows it drew
+/// between them, which aren't added until `flow_all` is called so that a
+/// test can add them however it needs to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RandomGraph {
+    /// the values of the values and vars created, in creation order.
+    pub values: Vec<Value>,
+    /// the uses of the uses and vars created, in creation order.
+    pub uses: Vec<Use>,
+    /// the flows drawn, each from a node created before the one it flows
+    /// into, so that the flows alone never form a cycle.
+    pub flows: Vec<(Value, Use)>,
+    /// the position each node was created at.
+    order: Vec<(EntityId, usize)>,
+}
+
+impl RandomGraph {
+    /// Adds every flow to the checker, stopping at the first that fails.
+    pub fn flow_all<V, U, AT>(&self, checker: &mut TypeChecker<V, U, AT>) -> Result<(), AT::Error>
+    where
+        AT: AbstractTypes<V, U>,
+    {
+        self.flows
+            .iter()
+            .try_for_each(|&(lhs, rhs)| checker.flow(lhs, rhs))
+    }
+
+    fn position(&self, id: EntityId) -> usize {
+        self.order
+            .iter()
+            .find(|&&(node, _)| node == id)
+            .map_or(0, |&(_, position)| position)
+    }
+}
+
+/// Creates a graph of the given shape in the checker, made of values, uses
+/// and vars in about equal numbers. Heads are drawn by `value` and
+/// `constraint`, which are passed the nodes created so far so that a head
+/// can refer to them as its children.
+pub fn random_graph<V, U, AT>(
+    checker: &mut TypeChecker<V, U, AT>,
+    rng: &mut Rng,
+    shape: GraphShape,
+    mut value: impl FnMut(&mut Rng, &RandomGraph) -> V,
+    mut constraint: impl FnMut(&mut Rng, &RandomGraph) -> U,
+) -> RandomGraph
+where
+    AT: AbstractTypes<V, U>,
+{
+    let mut graph = RandomGraph::default();
+    for position in 0..shape.nodes {
+        match rng.below(3) {
+            0 => {
+                let head = value(rng, &graph);
+                let node = checker.new_val(head);
+                graph.values.push(node);
+                graph.order.push((node.0, position));
+            }
+            1 => {
+                let head = constraint(rng, &graph);
+                let node = checker.new_use(head);
+                graph.uses.push(node);
+                graph.order.push((node.0, position));
+            }
+            _ => {
+                let (val, use_) = checker.var();
+                graph.values.push(val);
+                graph.uses.push(use_);
+                graph.order.push((val.0, position));
+            }
+        }
+    }
+
+    for _ in 0..shape.flows {
+        if graph.values.is_empty() {
+            break;
+        }
+        let lhs = graph.values[rng.below(graph.values.len())];
+        let after = graph.position(lhs.0);
+        let sinks: Vec<Use> = graph
+            .uses
+            .iter()
+            .copied()
+            .filter(|rhs| graph.position(rhs.0) > after)
+            .collect();
+        if !sinks.is_empty() {
+            let rhs = sinks[rng.below(sinks.len())];
+            graph.flows.push((lhs, rhs));
+        }
+    }
+    graph
+}
+
+/// OracleFailure is an invariant a checker was found to break.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OracleFailure<E> {
+    /// The graph holds an edge into `via` and one out of it, but not the
+    /// edge closing over the two.
+    NotClosed {
+        lhs: EntityId,
+        via: EntityId,
+        rhs: EntityId,
+    },
+    /// A recorded flow has no edge in the graph.
+    MissingFlow { lhs: Value, rhs: Use },
+    /// Adding a recorded flow again failed.
+    ReflowFailed { lhs: Value, rhs: Use, error: E },
+    /// Adding the recorded flows again changed the graph.
+    ReflowChanged(GraphDiff),
+    /// Restoring a checkpoint didn't give back the graph it was taken of.
+    RestoreChanged(GraphDiff),
+    /// Restoring a checkpoint didn't give back the named part of the
+    /// checker's bookkeeping, such as its recorded flows.
+    RestoreChangedState(&'static str),
+}
+
+/// Checks that the graph is transitively closed, so that every node flowing
+/// into another also flows into everything that one does, and that every
+/// recorded flow has its edge.
+pub fn check_closure<V, U, AT>(
+    checker: &TypeChecker<V, U, AT>,
+) -> Result<(), OracleFailure<AT::Error>>
+where
+    AT: AbstractTypes<V, U>,
+{
+    let skip_self_edges = checker.graph_config.skip_self_edges;
+    let has_edge = |lhs: EntityId, rhs: EntityId| {
+        checker.r.has_edge(lhs, rhs) || (lhs == rhs && skip_self_edges)
+    };
+    for (lhs, via) in checker.r.edges() {
+        if let Some(rhs) = checker.r.downstream(via).find(|&rhs| !has_edge(lhs, rhs)) {
+            return Err(OracleFailure::NotClosed { lhs, via, rhs });
+        }
+    }
+    for flow in checker.flows.iter() {
+        let (lhs, rhs) = (
+            checker.aliases.root(flow.lhs.0),
+            checker.aliases.root(flow.rhs.0),
+        );
+        if !has_edge(lhs, rhs) {
+            return Err(OracleFailure::MissingFlow {
+                lhs: flow.lhs,
+                rhs: flow.rhs,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks that adding every recorded flow again to a copy of the checker
+/// succeeds without adding a node or an edge, as a closed graph already
+/// holds everything they imply.
+pub fn check_reflow<V, U, AT>(
+    checker: &TypeChecker<V, U, AT>,
+) -> Result<(), OracleFailure<AT::Error>>
+where
+    V: Clone,
+    U: Clone,
+    AT: AbstractTypes<V, U> + Clone,
+{
+    let mut copy = checker.clone();
+    let before = copy.snapshot();
+    let flows: Vec<(Value, Use)> = copy.flows.iter().map(|f| (f.lhs, f.rhs)).collect();
+    for (lhs, rhs) in flows {
+        if let Err(error) = copy.flow(lhs, rhs) {
+            return Err(OracleFailure::ReflowFailed { lhs, rhs, error });
+        }
+    }
+    let diff = before.diff(&copy.snapshot());
+    if diff.is_empty() {
+        Ok(())
+    } else {
+        Err(OracleFailure::ReflowChanged(diff))
+    }
+}
+
+/// Checks that restoring a checkpoint taken before adding the flows and
+/// retracting the first flow recorded gives back the graph and bookkeeping
+/// the checker held, whether or not the flows succeed. The checker is left
+/// as it was before the call.
+pub fn check_restore<V, U, AT>(
+    checker: &mut TypeChecker<V, U, AT>,
+    flows: &[(Value, Use)],
+) -> Result<(), OracleFailure<AT::Error>>
+where
+    AT: AbstractTypes<V, U>,
+{
+    let before = checker.snapshot();
+    let kept = Bookkeeping::of(checker);
+    let checkpoint = checker.save();
+    for &(lhs, rhs) in flows {
+        let _ = checker.flow(lhs, rhs);
+    }
+    if let Some(flow) = checker.flows.first().copied() {
+        let _ = checker.retract(flow.lhs, flow.rhs);
+    }
+    checker.restore(checkpoint);
+    let after = checker.snapshot();
+    match (before.diff(&after), after.diff(&before)) {
+        (added, removed) if added.is_empty() && removed.is_empty() => kept
+            .changed(&Bookkeeping::of(checker))
+            .map_or(Ok(()), |part| Err(OracleFailure::RestoreChangedState(part))),
+        (added, removed) if added.is_empty() => Err(OracleFailure::RestoreChanged(removed)),
+        (added, _) => Err(OracleFailure::RestoreChanged(added)),
+    }
+}
+
+/// Bookkeeping is the state besides the graph that restoring a checkpoint
+/// gives back.
+#[derive(PartialEq)]
+struct Bookkeeping {
+    nodes: usize,
+    flows: Vec<(Value, Use, Option<usize>)>,
+    meets: usize,
+    failed: Option<(EntityId, EntityId)>,
+    groups: (usize, Option<usize>),
+    levels: Vec<usize>,
+}
+
+impl Bookkeeping {
+    fn of<V, U, AT>(checker: &TypeChecker<V, U, AT>) -> Self
+    where
+        AT: AbstractTypes<V, U>,
+    {
+        Self {
+            nodes: checker.types.len(),
+            flows: checker.flows.iter().map(|flow| flow.key()).collect(),
+            meets: checker.meets,
+            failed: checker.failed,
+            groups: (checker.groups.tags.len(), checker.groups.open),
+            levels: checker.levels.clone(),
+        }
+    }
+
+    /// Returns the first part that differs from the other's.
+    fn changed(&self, other: &Self) -> Option<&'static str> {
+        [
+            ("nodes", self.nodes == other.nodes),
+            ("flows", self.flows == other.flows),
+            ("meets", self.meets == other.meets),
+            ("failed", self.failed == other.failed),
+            ("groups", self.groups == other.groups),
+            ("levels", self.levels == other.levels),
+        ]
+        .iter()
+        .find(|(_, same)| !same)
+        .map(|&(part, _)| part)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::{Permissive, Prim};
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Val {
+        Int,
+        List(Value),
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Sink {
+        Any,
+        List(Use),
+    }
+
+    /// Lists relate their elements, and every other pair of heads agrees.
+    #[derive(Debug, Clone)]
+    struct Lists;
+
+    impl AbstractTypes<Val, Sink> for Lists {
+        type Error = crate::TypeError;
+
+        fn meet(&self, lhs: &Val, rhs: &Sink) -> Result<Vec<(Value, Use)>, Self::Error> {
+            match (lhs, rhs) {
+                (Val::List(elem), Sink::List(sink)) => Ok(vec![(*elem, *sink)]),
+                _ => Ok(vec![]),
+            }
+        }
+    }
+
+    fn lists(seed: u64) -> (TypeChecker<Val, Sink, Lists>, RandomGraph) {
+        let mut t = TypeChecker::new(Lists);
+        let mut rng = Rng::new(seed);
+        let graph = random_graph(
+            &mut t,
+            &mut rng,
+            GraphShape::default(),
+            |rng, graph| match graph.values.len() {
+                0 => Val::Int,
+                n if rng.one_in(2) => Val::List(graph.values[rng.below(n)]),
+                _ => Val::Int,
+            },
+            |rng, graph| match graph.uses.len() {
+                0 => Sink::Any,
+                n if rng.one_in(2) => Sink::List(graph.uses[rng.below(n)]),
+                _ => Sink::Any,
+            },
+        );
+        (t, graph)
+    }
+
+    #[test]
+    fn generated_graphs_should_hold_every_invariant() {
+        for seed in 0..32 {
+            let (mut t, graph) = lists(seed);
+            let (first, rest) = graph.flows.split_at(graph.flows.len() / 2);
+            for &(lhs, rhs) in first {
+                assert!(t.flow(lhs, rhs).is_ok());
+            }
+            assert_eq!(Ok(()), check_closure(&t), "seed {}", seed);
+            assert_eq!(Ok(()), check_reflow(&t), "seed {}", seed);
+            assert_eq!(Ok(()), check_restore(&mut t, rest), "seed {}", seed);
+            assert_eq!(Ok(()), check_closure(&t), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn generated_flows_should_follow_creation_order() {
+        let (_, graph) = lists(7);
+        let (_, again) = lists(7);
+        assert_eq!(graph, again);
+        assert!(!graph.flows.is_empty());
+        for &(lhs, rhs) in &graph.flows {
+            assert!(graph.position(lhs.0) < graph.position(rhs.0));
+        }
+
+        let mut t = TypeChecker::new(Permissive);
+        let mut rng = Rng::new(7);
+        let shape = GraphShape { nodes: 1, flows: 4 };
+        let graph = random_graph(&mut t, &mut rng, shape, |_, _| Prim::Int, |_, _| Prim::Int);
+        assert!(graph.flows.is_empty());
+        assert_eq!(Ok(()), graph.flow_all(&mut t));
+    }
+}
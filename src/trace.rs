@@ -0,0 +1,122 @@
+//! Tracing of the checker's work, for finding out why an unexpected edge
+//! appears without instrumenting a copy of the crate. Enabled by the `trace`
+//! feature, which has no dependencies: events are handed to a plain function
+//! that can print them or forward them to whichever logging crate is in use.
+//! The `tracing` feature instead opens spans around each flow, edge and meet
+//! for a subscriber of the `tracing` crate.
+
+use crate::{AbstractTypes, EntityId, NodeIndex, TypeChecker, Use, Value};
+
+/// TraceEvent is a single piece of the checker's work, reported as it
+/// happens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceEvent<'a, V, U, I = u32> {
+    /// The caller added a flow, which is about to be checked.
+    Flow { lhs: Value<I>, rhs: Use<I> },
+    /// An edge was added to the graph, whether by the caller or by a meet,
+    /// and closing over it connected `derived` pairs of nodes.
+    Edge {
+        lhs: EntityId<I>,
+        rhs: EntityId<I>,
+        derived: usize,
+    },
+    /// The heads of two newly connected nodes are about to be met.
+    Meet {
+        lhs: EntityId<I>,
+        rhs: EntityId<I>,
+        value: &'a V,
+        constraint: &'a U,
+    },
+}
+
+impl<V, U, I> core::fmt::Display for TraceEvent<'_, V, U, I>
+where
+    V: core::fmt::Debug,
+    U: core::fmt::Debug,
+    I: NodeIndex,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Flow { lhs, rhs } => write!(f, "flow {} -> {}", lhs.index(), rhs.index()),
+            Self::Edge { lhs, rhs, derived } => {
+                let (lhs, rhs) = (usize::from(*lhs), usize::from(*rhs));
+                write!(f, "edge {} -> {}, {} derived", lhs, rhs, derived)
+            }
+            Self::Meet {
+                lhs,
+                rhs,
+                value,
+                constraint,
+            } => write!(
+                f,
+                "meet {} -> {}: {:?} against {:?}",
+                usize::from(*lhs),
+                usize::from(*rhs),
+                value,
+                constraint
+            ),
+        }
+    }
+}
+
+/// Tracer receives every event the checker reports.
+pub type Tracer<V, U, I = u32> = fn(&TraceEvent<'_, V, U, I>);
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Hands every event to the tracer from now on, or stops tracing if it
+    /// is None. A closure that captures nothing can be passed, such as one
+    /// printing each event to stderr.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer<V, U, I>>) {
+        self.tracer = tracer;
+    }
+
+    /// Reports an event to the tracer, if there is one.
+    pub(crate) fn trace(&self, event: TraceEvent<'_, V, U, I>) {
+        if let Some(tracer) = self.tracer {
+            tracer(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(event: &TraceEvent<'_, Prim, Prim>) {
+        EVENTS.with(|events| events.borrow_mut().push(event.to_string()));
+    }
+
+    #[test]
+    fn tracers_should_see_flows_edges_and_meets() {
+        let mut t = TypeChecker::new(Strict);
+        t.set_tracer(Some(record));
+        let int = t.new_val(Prim::Int);
+        let (x_val, x_use) = t.var();
+        let sink = t.new_use(Prim::Int);
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+
+        t.set_tracer(None);
+        assert!(t.flow(int, sink).is_ok());
+        assert_eq!(
+            vec![
+                "flow 0 -> 1",
+                "edge 0 -> 1, 1 derived",
+                "flow 1 -> 2",
+                "edge 1 -> 2, 2 derived",
+                "meet 0 -> 2: Int against Int",
+            ],
+            EVENTS.with(|events| events.borrow().clone())
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{AbstractTypes, TypeChecker, Use, Value};
+use core::marker::PhantomData;
+
+/// Typed wraps a handle with the type system of the checker that created it,
+/// so that handles can't be passed to a checker over a different type system.
+///
+/// ```compile_fail
+/// # use typical::*;
+/// # #[derive(Debug)] struct A;
+/// # #[derive(Debug)] struct B;
+/// # impl AbstractTypes<(), ()> for A {
+/// #     type Error = TypeError;
+/// #     fn meet(&self, _: &(), _: &()) -> Result<Vec<(Value, Use)>, TypeError> { Ok(vec![]) }
+/// # }
+/// # impl AbstractTypes<(), ()> for B {
+/// #     type Error = TypeError;
+/// #     fn meet(&self, _: &(), _: &()) -> Result<Vec<(Value, Use)>, TypeError> { Ok(vec![]) }
+/// # }
+/// let mut a = TypedChecker::new(A);
+/// let mut b = TypedChecker::new(B);
+/// let value = a.new_val(());
+/// let sink = b.new_use(());
+/// b.flow(value, sink);
+/// ```
+pub struct Typed<H, AT> {
+    handle: H,
+    system: PhantomData<fn() -> AT>,
+}
+
+/// A Value created by a checker over the type system `AT`.
+pub type TypedValue<AT> = Typed<Value, AT>;
+/// A Use created by a checker over the type system `AT`.
+pub type TypedUse<AT> = Typed<Use, AT>;
+
+impl<H, AT> Typed<H, AT> {
+    fn new(handle: H) -> Self {
+        Self {
+            handle,
+            system: PhantomData,
+        }
+    }
+
+    /// Discards the type system, returning the underlying handle.
+    pub fn into_inner(self) -> H {
+        self.handle
+    }
+}
+
+impl<H: Copy, AT> Copy for Typed<H, AT> {}
+
+impl<H: Copy, AT> Clone for Typed<H, AT> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<H: PartialEq, AT> PartialEq for Typed<H, AT> {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl<H: Eq, AT> Eq for Typed<H, AT> {}
+
+impl<H: core::hash::Hash, AT> core::hash::Hash for Typed<H, AT> {
+    fn hash<S: core::hash::Hasher>(&self, state: &mut S) {
+        self.handle.hash(state)
+    }
+}
+
+impl<H: core::fmt::Debug, AT> core::fmt::Debug for Typed<H, AT> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Typed({:?})", self.handle)
+    }
+}
+
+/// TypedChecker wraps a TypeChecker, handing out handles branded with its
+/// type system.
+#[derive(Debug, Clone)]
+pub struct TypedChecker<V, U, AT>
+where
+    AT: AbstractTypes<V, U>,
+{
+    checker: TypeChecker<V, U, AT>,
+}
+
+impl<V, U, AT> TypedChecker<V, U, AT>
+where
+    AT: AbstractTypes<V, U>,
+{
+    pub fn new(abstract_type_mapper: AT) -> Self {
+        Self {
+            checker: TypeChecker::new(abstract_type_mapper),
+        }
+    }
+
+    /// Returns the wrapped checker.
+    pub fn checker(&self) -> &TypeChecker<V, U, AT> {
+        &self.checker
+    }
+
+    pub fn new_val(&mut self, val_type: V) -> TypedValue<AT> {
+        Typed::new(self.checker.new_val(val_type))
+    }
+
+    pub fn new_use(&mut self, constraint: U) -> TypedUse<AT> {
+        Typed::new(self.checker.new_use(constraint))
+    }
+
+    pub fn var(&mut self) -> (TypedValue<AT>, TypedUse<AT>) {
+        let (value, var_use) = self.checker.var();
+        (Typed::new(value), Typed::new(var_use))
+    }
+
+    pub fn flow(&mut self, lhs: TypedValue<AT>, rhs: TypedUse<AT>) -> Result<(), AT::Error> {
+        self.checker.flow(lhs.handle, rhs.handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_systems::Strict;
+    use crate::TypeError;
+
+    #[test]
+    fn typed_handles_should_flow_within_their_checker() {
+        let mut t = TypedChecker::new(Strict);
+        let (var_val, var_use) = t.var();
+        let truthy = t.new_val(true);
+        let falsy = t.new_use(false);
+
+        assert!(t.flow(truthy, var_use).is_ok());
+        assert_eq!(Err(TypeError::Converge), t.flow(var_val, falsy));
+        assert_eq!(var_val.into_inner().index(), var_use.into_inner().index());
+    }
+}
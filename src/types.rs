@@ -0,0 +1,246 @@
+use crate::prelude::*;
+use crate::Key;
+use core::convert::TryFrom;
+use core::fmt;
+
+/// NodeIndex is an unsigned integer that a checker stores the ids of its
+/// nodes as. Narrower indices make for smaller edge sets but cap the number
+/// of nodes a checker can hold.
+pub trait NodeIndex: Copy + Default + fmt::Debug + Key + Send + Sync + 'static {
+    /// Returns the index of the node at a position, or None if the position
+    /// doesn't fit in the index.
+    fn from_usize(idx: usize) -> Option<Self>;
+
+    /// Returns the position of the node the index refers to.
+    fn to_usize(self) -> usize;
+}
+
+macro_rules! node_index {
+    ($($ty:ty),*) => {
+        $(
+            impl NodeIndex for $ty {
+                fn from_usize(idx: usize) -> Option<Self> {
+                    <$ty>::try_from(idx).ok()
+                }
+
+                fn to_usize(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
+}
+
+node_index!(u16, u32, u64, usize);
+
+/// EntityId represents a strongly typed index into the nodes of a checker,
+/// wrapping its underlying representation so that ids can't be mixed up with
+/// arbitrary integers. Ids are `u32`s by default, which halves the size of
+/// the graph's edge sets over `usize` while still allowing four billion
+/// nodes, and a checker can be created over any other `NodeIndex`. `new`
+/// and `into_inner` convert to and from the raw index, for embedding ids in
+/// compact IRs, while `from_index` and `TryFrom<usize>` check that a
+/// position fits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct EntityId<T = u32>(T);
+
+impl<T> EntityId<T> {
+    /// Wraps a raw index as an EntityId.
+    pub fn new(id: T) -> Self {
+        Self(id)
+    }
+
+    /// Returns the underlying index.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: NodeIndex> EntityId<T> {
+    /// Returns the id of the node at a position, or None if the position
+    /// doesn't fit in the index.
+    pub fn from_index(idx: usize) -> Option<Self> {
+        T::from_usize(idx).map(Self)
+    }
+
+    /// Returns the id of a node the checker holds, whose position is known
+    /// to fit as the node was created with it.
+    pub(crate) fn at(idx: usize) -> Self {
+        match Self::from_index(idx) {
+            Some(id) => id,
+            None => panic!("node {} doesn't fit in the checker's index", idx),
+        }
+    }
+}
+
+/// IndexOverflow is the error converting a position too large for the index
+/// to an EntityId.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexOverflow {
+    pub index: usize,
+}
+
+impl fmt::Display for IndexOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node {} doesn't fit in the index.", self.index)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IndexOverflow {}
+
+impl<T: NodeIndex> TryFrom<usize> for EntityId<T> {
+    type Error = IndexOverflow;
+
+    fn try_from(index: usize) -> Result<Self, Self::Error> {
+        Self::from_index(index).ok_or(IndexOverflow { index })
+    }
+}
+
+impl<T: NodeIndex> From<EntityId<T>> for usize {
+    fn from(id: EntityId<T>) -> Self {
+        id.0.to_usize()
+    }
+}
+
+/// AbstractEntity defines a symmetric type system, where constraints state
+/// that two entities are equal rather than that one flows into the other.
+pub trait AbstractEntity: Sized {
+    /// Returns the type of an entity that nothing has constrained.
+    fn unconstrained_type() -> Self;
+
+    /// Returns the number of child entities the type is constructed from.
+    fn arity(&self) -> usize;
+
+    /// Converges two types into a single type, erroring if they are
+    /// incompatible. Children are converged separately by the checker.
+    fn converge(lhs: &Self, rhs: &Self) -> Result<Self, crate::TypeError>;
+}
+
+/// EntityTypes adapts an AbstractEntity type system to run on the flow-based
+/// TypeChecker, using the entity type for both value and use heads. Each meet
+/// converges the two heads and emits no further flows, so children are not
+/// related by the adapter.
+pub struct EntityTypes<E>(core::marker::PhantomData<E>);
+
+impl<E> EntityTypes<E> {
+    pub fn new() -> Self {
+        Self(core::marker::PhantomData)
+    }
+}
+
+impl<E> Default for EntityTypes<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> Clone for EntityTypes<E> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<E> core::fmt::Debug for EntityTypes<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "EntityTypes")
+    }
+}
+
+impl<E> crate::AbstractTypes<E, E> for EntityTypes<E>
+where
+    E: AbstractEntity,
+{
+    type Error = crate::TypeError;
+
+    fn meet(&self, lhs: &E, rhs: &E) -> Result<Vec<(crate::Value, crate::Use)>, Self::Error> {
+        if lhs.arity() != rhs.arity() {
+            return Err(crate::TypeError::ArityMismatch {
+                expected: lhs.arity(),
+                found: rhs.arity(),
+                position: Vec::new(),
+            });
+        }
+
+        E::converge(lhs, rhs).map(|_| Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TypeChecker, TypeError, UnificationChecker};
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Lit {
+        Int,
+        Bool,
+        Pair,
+    }
+
+    impl AbstractEntity for Lit {
+        fn unconstrained_type() -> Self {
+            Lit::Int
+        }
+
+        fn arity(&self) -> usize {
+            match self {
+                Lit::Pair => 2,
+                _ => 0,
+            }
+        }
+
+        fn converge(lhs: &Self, rhs: &Self) -> Result<Self, TypeError> {
+            if lhs == rhs {
+                Ok(lhs.clone())
+            } else {
+                Err(TypeError::Converge)
+            }
+        }
+    }
+
+    #[test]
+    fn entity_id_should_round_trip_through_usize() {
+        let id = EntityId::<u32>::try_from(5).unwrap();
+        assert_eq!(EntityId::new(5), id);
+        assert_eq!(5usize, usize::from(id));
+        assert_eq!(5u32, id.into_inner());
+        assert_eq!(7usize, usize::from(EntityId::<usize>::try_from(7).unwrap()));
+        assert_eq!(
+            Err(IndexOverflow { index: 1 << 16 }),
+            EntityId::<u16>::try_from(1 << 16)
+        );
+        assert_eq!(None, EntityId::<u16>::from_index(usize::MAX));
+    }
+
+    #[test]
+    fn entity_system_should_run_on_both_engines() {
+        let mut flow = TypeChecker::new(EntityTypes::<Lit>::new());
+        let int = flow.new_val(Lit::Int);
+        let (var_val, var_use) = flow.var();
+        let bool_use = flow.new_use(Lit::Bool);
+        assert!(flow.flow(int, var_use).is_ok());
+        assert_eq!(Err(TypeError::Converge), flow.flow(var_val, bool_use));
+
+        let pair = flow.new_val(Lit::Pair);
+        let int_use = flow.new_use(Lit::Int);
+        assert!(matches!(
+            flow.flow(pair, int_use),
+            Err(TypeError::ArityMismatch { .. })
+        ));
+
+        let mut unify = UnificationChecker::new();
+        let (int, var, bool_) = (
+            unify.new_entity(Lit::Int, &[]),
+            unify.var(),
+            unify.new_entity(Lit::Bool, &[]),
+        );
+        assert!(unify.unify(int, var).is_ok());
+        assert_eq!(Err(TypeError::Converge), unify.unify(var, bool_));
+    }
+}
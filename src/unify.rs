@@ -0,0 +1,398 @@
+use crate::prelude::*;
+use crate::union_find::UnionFind;
+use crate::{AbstractEntity, EntityId, TypeError};
+
+/// Term represents a single entity tracked by the UnificationChecker. A term
+/// without a head is an unconstrained variable.
+#[derive(Debug, Clone)]
+struct Term<E> {
+    head: Option<E>,
+    children: Vec<EntityId>,
+}
+
+/// Warning describes a non-fatal issue found during inference.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Warning {
+    /// The variable set rooted at `entity` was never constrained and had a
+    /// default type assigned.
+    Defaulted { entity: EntityId },
+}
+
+/// UnificationChecker solves equality constraints between entities using
+/// union-find, converging the heads of any two entities that are unified and
+/// then unifying their children pairwise. It is an alternative to the
+/// flow-based TypeChecker for type systems that have no need for subtyping.
+///
+/// By default a variable can't be unified with a type containing itself, as
+/// in `a = a -> a`. Equi-recursive mode lifts this restriction, treating such
+/// cycles as recursive types.
+#[derive(Debug, Clone)]
+pub struct UnificationChecker<E>
+where
+    E: AbstractEntity,
+{
+    /// partitions entities into sets known to be equal.
+    sets: UnionFind,
+    /// heads and children, only meaningful for the root of each set.
+    terms: Vec<Term<E>>,
+    /// permits cyclic types rather than failing the occurs check.
+    equi_recursive: bool,
+}
+
+impl<E> Default for UnificationChecker<E>
+where
+    E: AbstractEntity,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> UnificationChecker<E>
+where
+    E: AbstractEntity,
+{
+    pub fn new() -> Self {
+        Self {
+            sets: UnionFind::new(),
+            terms: Vec::new(),
+            equi_recursive: false,
+        }
+    }
+
+    /// Enables or disables equi-recursive mode, where unifying a variable
+    /// with a type containing itself produces a cyclic type instead of an
+    /// `InfiniteType` error.
+    pub fn with_equi_recursive_types(mut self, enabled: bool) -> Self {
+        self.equi_recursive = enabled;
+        self
+    }
+
+    fn add_term(&mut self, term: Term<E>) -> EntityId {
+        let id = self.sets.make_set();
+        self.terms.push(term);
+        id
+    }
+
+    /// Creates a new, unconstrained variable.
+    pub fn var(&mut self) -> EntityId {
+        self.add_term(Term {
+            head: None,
+            children: Vec::new(),
+        })
+    }
+
+    /// Creates a new entity constructed from a head and its children.
+    pub fn new_entity(&mut self, head: E, children: &[EntityId]) -> EntityId {
+        assert!(head.arity() == children.len());
+        self.add_term(Term {
+            head: Some(head),
+            children: children.to_vec(),
+        })
+    }
+
+    /// Returns the representative entity of the set containing `id`,
+    /// compressing the path walked along the way.
+    pub fn find(&mut self, id: EntityId) -> EntityId {
+        self.sets.find(id)
+    }
+
+    /// Returns the chain of roots leading from `term` to `var` if `var`
+    /// occurs anywhere within `term`.
+    fn occurs(&mut self, var: EntityId, term: EntityId) -> Option<Vec<EntityId>> {
+        let mut visited = crate::collections::HashSet::new();
+        // each entry holds a root along with the chain that reached it.
+        let mut pending = vec![vec![self.find(term)]];
+
+        while let Some(chain) = pending.pop() {
+            let root = *chain.last().unwrap();
+            if root == var {
+                return Some(chain);
+            }
+            if !visited.insert(root) {
+                continue;
+            }
+
+            let children = self.terms[usize::from(root)].children.clone();
+            for child in children {
+                let mut next = chain.clone();
+                next.push(self.find(child));
+                pending.push(next);
+            }
+        }
+
+        None
+    }
+
+    /// Binds the variable root `var` to the set rooted at `term`.
+    fn bind(&mut self, var: EntityId, term: EntityId) -> Result<(), TypeError> {
+        if !self.equi_recursive {
+            if let Some(chain) = self.occurs(var, term) {
+                return Err(TypeError::InfiniteType(chain));
+            }
+        }
+        // the term's head and children must end up on the merged root.
+        if self.sets.union(var, term) == var {
+            self.terms.swap(usize::from(var), usize::from(term));
+        }
+        Ok(())
+    }
+
+    /// Unifies two entities, converging their heads and recursively unifying
+    /// their children. Arities are compared before any children are visited,
+    /// reporting the path of child positions leading to a mismatch.
+    pub fn unify(&mut self, lhs: EntityId, rhs: EntityId) -> Result<(), TypeError> {
+        let mut pending = vec![(lhs, rhs, Vec::new())];
+
+        while let Some((lhs, rhs, position)) = pending.pop() {
+            let (lhs, rhs) = (self.find(lhs), self.find(rhs));
+            if lhs == rhs {
+                continue;
+            }
+
+            let (lhs_term, rhs_term) =
+                (&self.terms[usize::from(lhs)], &self.terms[usize::from(rhs)]);
+            let (lhs_head, rhs_head) = match (&lhs_term.head, &rhs_term.head) {
+                // a variable takes on whatever it is unified with.
+                (None, _) => {
+                    self.bind(lhs, rhs)?;
+                    continue;
+                }
+                (_, None) => {
+                    self.bind(rhs, lhs)?;
+                    continue;
+                }
+                (Some(lhs_head), Some(rhs_head)) => (lhs_head, rhs_head),
+            };
+
+            if lhs_head.arity() != rhs_head.arity() {
+                return Err(TypeError::ArityMismatch {
+                    expected: lhs_head.arity(),
+                    found: rhs_head.arity(),
+                    position,
+                });
+            }
+
+            let head = E::converge(lhs_head, rhs_head)?;
+            if head.arity() != lhs_term.children.len() {
+                return Err(TypeError::ArityMismatch {
+                    expected: head.arity(),
+                    found: lhs_term.children.len(),
+                    position,
+                });
+            }
+
+            for (idx, (&lhs_child, &rhs_child)) in lhs_term
+                .children
+                .iter()
+                .zip(rhs_term.children.iter())
+                .enumerate()
+            {
+                let mut child_position = position.clone();
+                child_position.push(idx);
+                pending.push((lhs_child, rhs_child, child_position));
+            }
+            let root = self.sets.union(lhs, rhs);
+            self.terms[usize::from(root)].head = Some(head);
+        }
+
+        Ok(())
+    }
+
+    /// Assigns `E::unconstrained_type()` to every variable that remains
+    /// unconstrained, returning a warning for each set that was defaulted.
+    pub fn resolve_defaults(&mut self) -> Vec<Warning> {
+        self.resolve_defaults_with(|_| E::unconstrained_type())
+    }
+
+    /// Assigns the type returned by `default` to every variable that remains
+    /// unconstrained, returning a warning for each set that was defaulted.
+    /// Defaults must not have any children.
+    pub fn resolve_defaults_with<F>(&mut self, mut default: F) -> Vec<Warning>
+    where
+        F: FnMut(EntityId) -> E,
+    {
+        let mut warnings = Vec::new();
+        for idx in 0..self.terms.len() {
+            let id = EntityId::at(idx);
+            if self.find(id) != id || self.terms[idx].head.is_some() {
+                continue;
+            }
+
+            let head = default(id);
+            assert!(head.arity() == 0);
+            self.terms[idx].head = Some(head);
+            warnings.push(Warning::Defaulted { entity: id });
+        }
+        warnings
+    }
+
+    /// Returns the children of the set containing `id`.
+    pub fn children(&mut self, id: EntityId) -> &[EntityId] {
+        let root = self.find(id);
+        &self.terms[usize::from(root)].children
+    }
+}
+
+impl<E> UnificationChecker<E>
+where
+    E: AbstractEntity + Clone,
+{
+    /// Returns the head the set containing `id` has been resolved to, or the
+    /// unconstrained type if nothing has constrained it.
+    pub fn resolve(&mut self, id: EntityId) -> E {
+        let root = self.find(id);
+        self.terms[usize::from(root)]
+            .head
+            .clone()
+            .unwrap_or_else(E::unconstrained_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Mono {
+        Unknown,
+        Int,
+        Bool,
+        Func,
+        Tuple(usize),
+    }
+
+    impl AbstractEntity for Mono {
+        fn unconstrained_type() -> Self {
+            Mono::Unknown
+        }
+
+        fn arity(&self) -> usize {
+            match self {
+                Mono::Func => 2,
+                Mono::Tuple(len) => *len,
+                _ => 0,
+            }
+        }
+
+        fn converge(lhs: &Self, rhs: &Self) -> Result<Self, TypeError> {
+            match (lhs, rhs) {
+                (Mono::Unknown, other) | (other, Mono::Unknown) => Ok(other.clone()),
+                (Mono::Tuple(_), Mono::Tuple(_)) => Ok(lhs.clone()),
+                (lhs, rhs) if lhs == rhs => Ok(lhs.clone()),
+                _ => Err(TypeError::Converge),
+            }
+        }
+    }
+
+    #[test]
+    fn should_resolve_unconstrained_var_to_unconstrained_type() {
+        let mut u = UnificationChecker::<Mono>::new();
+        let a = u.var();
+        let b = u.var();
+
+        assert!(u.unify(a, b).is_ok());
+        assert_eq!(Mono::Unknown, u.resolve(a));
+        assert_eq!(u.find(a), u.find(b));
+    }
+
+    #[test]
+    fn should_unify_children_of_converged_entities() {
+        let mut u = UnificationChecker::new();
+        let (a, b) = (u.var(), u.var());
+        let int = u.new_entity(Mono::Int, &[]);
+        let bool_ = u.new_entity(Mono::Bool, &[]);
+
+        // a -> b = int -> bool
+        let lhs = u.new_entity(Mono::Func, &[a, b]);
+        let rhs = u.new_entity(Mono::Func, &[int, bool_]);
+        assert!(u.unify(lhs, rhs).is_ok());
+        assert_eq!(Mono::Int, u.resolve(a));
+        assert_eq!(Mono::Bool, u.resolve(b));
+
+        // a = bool is now a conflict.
+        assert_eq!(Err(TypeError::Converge), u.unify(a, bool_));
+    }
+
+    #[test]
+    fn should_report_position_of_arity_mismatch() {
+        let mut u = UnificationChecker::new();
+        let int = u.new_entity(Mono::Int, &[]);
+
+        // int -> (int, int) = int -> (int, int, int)
+        let pair = u.new_entity(Mono::Tuple(2), &[int, int]);
+        let triple = u.new_entity(Mono::Tuple(3), &[int, int, int]);
+        let lhs = u.new_entity(Mono::Func, &[int, pair]);
+        let rhs = u.new_entity(Mono::Func, &[int, triple]);
+
+        assert_eq!(
+            Err(TypeError::ArityMismatch {
+                expected: 2,
+                found: 3,
+                position: vec![1]
+            }),
+            u.unify(lhs, rhs)
+        );
+    }
+
+    #[test]
+    fn should_default_and_warn_on_unconstrained_vars() {
+        let mut u = UnificationChecker::new();
+        let (a, b, c) = (u.var(), u.var(), u.var());
+        let int = u.new_entity(Mono::Int, &[]);
+        assert!(u.unify(a, int).is_ok());
+        assert!(u.unify(b, c).is_ok());
+
+        let root = u.find(b);
+        assert_eq!(
+            vec![Warning::Defaulted { entity: root }],
+            u.resolve_defaults()
+        );
+        assert_eq!(Mono::Unknown, u.resolve(c));
+
+        // defaulted vars are now constrained and must converge.
+        assert!(u.unify(b, int).is_ok());
+        assert!(u.resolve_defaults().is_empty());
+    }
+
+    #[test]
+    fn should_default_vars_with_user_supplied_type() {
+        let mut u = UnificationChecker::new();
+        let a = u.var();
+
+        assert_eq!(1, u.resolve_defaults_with(|_| Mono::Bool).len());
+        assert_eq!(Mono::Bool, u.resolve(a));
+    }
+
+    #[test]
+    fn occurs_check_should_reject_infinite_types() {
+        let mut u = UnificationChecker::new();
+        let (a, b) = (u.var(), u.var());
+        let int = u.new_entity(Mono::Int, &[]);
+
+        // a = (int -> a) -> b
+        let inner = u.new_entity(Mono::Func, &[int, a]);
+        let outer = u.new_entity(Mono::Func, &[inner, b]);
+        assert_eq!(
+            Err(TypeError::InfiniteType(vec![outer, inner, a])),
+            u.unify(a, outer)
+        );
+    }
+
+    #[test]
+    fn equi_recursive_mode_should_permit_and_unify_cyclic_types() {
+        let mut u = UnificationChecker::new().with_equi_recursive_types(true);
+        let (a, b) = (u.var(), u.var());
+
+        // a = a -> a and b = b -> b
+        let a_func = u.new_entity(Mono::Func, &[a, a]);
+        let b_func = u.new_entity(Mono::Func, &[b, b]);
+        assert!(u.unify(a, a_func).is_ok());
+        assert!(u.unify(b, b_func).is_ok());
+
+        assert!(u.unify(a, b).is_ok());
+        assert_eq!(u.find(a), u.find(b));
+        assert_eq!(Mono::Func, u.resolve(a));
+    }
+}
@@ -0,0 +1,241 @@
+//! A disjoint-set forest with path compression, union by rank and support
+//! for rolling back to snapshots, as needed for speculative unification.
+
+use crate::prelude::*;
+use crate::{EntityId, NodeIndex};
+
+/// UndoEntry records a single mutation to the forest so that it can be
+/// reverted when rolling back a snapshot.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum UndoEntry<I> {
+    MakeSet,
+    SetParent {
+        id: EntityId<I>,
+        parent: EntityId<I>,
+    },
+    SetRank {
+        id: EntityId<I>,
+        rank: u8,
+    },
+}
+
+/// Snapshot marks a point in time that a UnionFind can be rolled back to.
+#[derive(Debug)]
+pub struct Snapshot {
+    undo_len: usize,
+}
+
+/// UnionFind tracks a partition of elements into disjoint sets, each of which
+/// is identified by a representative root element. Elements are ids over
+/// the index `I`, as for the checker the forest belongs to.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnionFind<I = u32> {
+    parents: Vec<EntityId<I>>,
+    ranks: Vec<u8>,
+    undo_log: Vec<UndoEntry<I>>,
+    open_snapshots: usize,
+}
+
+impl UnionFind {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<I: NodeIndex> UnionFind<I> {
+    /// Reserves room for at least `additional` more elements.
+    pub fn reserve(&mut self, additional: usize) {
+        self.parents.reserve(additional);
+        self.ranks.reserve(additional);
+    }
+
+    /// Returns true if `id` is in a set of its own.
+    pub fn is_singleton(&self, id: EntityId<I>) -> bool {
+        self.parents[usize::from(id)] == id
+            && self
+                .parents
+                .iter()
+                .enumerate()
+                .all(|(other, &parent)| parent != id || other == usize::from(id))
+    }
+
+    /// Returns the number of elements in the forest.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Returns true if the forest has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    fn log(&mut self, entry: UndoEntry<I>) {
+        if self.open_snapshots > 0 {
+            self.undo_log.push(entry);
+        }
+    }
+
+    fn set_parent(&mut self, id: EntityId<I>, parent: EntityId<I>) {
+        let idx = usize::from(id);
+        self.log(UndoEntry::SetParent {
+            id,
+            parent: self.parents[idx],
+        });
+        self.parents[idx] = parent;
+    }
+
+    /// Adds a new element in a set of its own, returning its id.
+    pub fn make_set(&mut self) -> EntityId<I> {
+        let id = EntityId::at(self.parents.len());
+        self.parents.push(id);
+        self.ranks.push(0);
+        self.log(UndoEntry::MakeSet);
+        id
+    }
+
+    /// Returns the root of the set containing `id`, compressing the path
+    /// walked along the way.
+    pub fn find(&mut self, id: EntityId<I>) -> EntityId<I> {
+        let mut root = id;
+        while self.parents[usize::from(root)] != root {
+            root = self.parents[usize::from(root)];
+        }
+
+        let mut id = id;
+        while id != root {
+            let parent = self.parents[usize::from(id)];
+            self.set_parent(id, root);
+            id = parent;
+        }
+        root
+    }
+
+    /// Returns the root of the set containing `id` without compressing the
+    /// path, for use where the forest can't be mutated.
+    pub fn root(&self, id: EntityId<I>) -> EntityId<I> {
+        let mut root = id;
+        while self.parents[usize::from(root)] != root {
+            root = self.parents[usize::from(root)];
+        }
+        root
+    }
+
+    /// Returns true if both elements belong to the same set.
+    pub fn same_set(&mut self, lhs: EntityId<I>, rhs: EntityId<I>) -> bool {
+        self.find(lhs) == self.find(rhs)
+    }
+
+    /// Merges the sets containing both elements, returning the root of the
+    /// merged set.
+    pub fn union(&mut self, lhs: EntityId<I>, rhs: EntityId<I>) -> EntityId<I> {
+        let (lhs, rhs) = (self.find(lhs), self.find(rhs));
+        if lhs == rhs {
+            return lhs;
+        }
+
+        let (lhs_rank, rhs_rank) = (self.ranks[usize::from(lhs)], self.ranks[usize::from(rhs)]);
+        let (root, child) = if lhs_rank < rhs_rank {
+            (rhs, lhs)
+        } else {
+            (lhs, rhs)
+        };
+
+        self.set_parent(child, root);
+        if lhs_rank == rhs_rank {
+            self.log(UndoEntry::SetRank {
+                id: root,
+                rank: lhs_rank,
+            });
+            self.ranks[usize::from(root)] += 1;
+        }
+        root
+    }
+
+    /// Begins recording mutations so they can be rolled back.
+    pub fn snapshot(&mut self) -> Snapshot {
+        self.open_snapshots += 1;
+        Snapshot {
+            undo_len: self.undo_log.len(),
+        }
+    }
+
+    /// Reverts every mutation made since the snapshot was taken, including
+    /// elements that were added.
+    pub fn rollback_to(&mut self, snapshot: Snapshot) {
+        while self.undo_log.len() > snapshot.undo_len {
+            match self.undo_log.pop().unwrap() {
+                UndoEntry::MakeSet => {
+                    self.parents.pop();
+                    self.ranks.pop();
+                }
+                UndoEntry::SetParent { id, parent } => self.parents[usize::from(id)] = parent,
+                UndoEntry::SetRank { id, rank } => self.ranks[usize::from(id)] = rank,
+            }
+        }
+        self.open_snapshots -= 1;
+    }
+
+    /// Keeps every mutation made since the snapshot was taken.
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        self.open_snapshots -= 1;
+        if self.open_snapshots == 0 {
+            self.undo_log.truncate(snapshot.undo_len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_should_merge_sets() {
+        let mut uf = UnionFind::new();
+        let ids: Vec<EntityId> = (0..4).map(|_| uf.make_set()).collect();
+
+        uf.union(ids[0], ids[1]);
+        uf.union(ids[2], ids[3]);
+        assert!(uf.same_set(ids[0], ids[1]));
+        assert!(!uf.same_set(ids[1], ids[2]));
+
+        let root = uf.union(ids[1], ids[3]);
+        assert!(ids.iter().all(|&id| uf.find(id) == root));
+    }
+
+    #[test]
+    fn rollback_should_undo_unions_and_new_elements() {
+        let mut uf = UnionFind::new();
+        let (a, b, c) = (uf.make_set(), uf.make_set(), uf.make_set());
+        uf.union(a, b);
+
+        let snapshot = uf.snapshot();
+        let d = uf.make_set();
+        uf.union(b, c);
+        uf.union(c, d);
+        assert!(uf.same_set(a, d));
+
+        uf.rollback_to(snapshot);
+        assert_eq!(3, uf.len());
+        assert!(uf.same_set(a, b));
+        assert!(!uf.same_set(a, c));
+    }
+
+    #[test]
+    fn nested_snapshots_should_roll_back_independently() {
+        let mut uf = UnionFind::new();
+        let (a, b, c) = (uf.make_set(), uf.make_set(), uf.make_set());
+
+        let outer = uf.snapshot();
+        uf.union(a, b);
+        let inner = uf.snapshot();
+        uf.union(b, c);
+        uf.rollback_to(inner);
+        assert!(uf.same_set(a, b));
+        assert!(!uf.same_set(a, c));
+
+        uf.commit(outer);
+        assert!(uf.same_set(a, b));
+    }
+}
@@ -0,0 +1,184 @@
+//! Structural checks of a checker's state, for catching misuse such as a
+//! head registered on the wrong side or a meet returning swapped pairs.
+//! They take time proportional to the size of the graph, so they are meant
+//! for debug builds, as in `debug_assert!(checker.validate().is_empty())`.
+
+use crate::prelude::*;
+use crate::scheme::Instantiate;
+use crate::{AbstractTypes, EntityId, NodeIndex, Polarity, TypeChecker, TypeNode, Use, Value};
+
+/// Inconsistency describes a broken invariant of a checker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inconsistency<I = u32> {
+    /// The checker holds a different number of nodes than its graph.
+    NodeCount { types: usize, graph: usize },
+    /// A node with a use head flows into another node, so a use was passed
+    /// where a value was expected.
+    EdgeFromUse { lhs: EntityId<I>, rhs: EntityId<I> },
+    /// A node flows into a node with a value head, so a value was passed
+    /// where a use was expected.
+    EdgeIntoValue { lhs: EntityId<I>, rhs: EntityId<I> },
+    /// An edge is only recorded at one of its ends.
+    AsymmetricEdge { lhs: EntityId<I>, rhs: EntityId<I> },
+    /// A head holds a handle to a node of the wrong kind: a value handle to
+    /// a use, with the `Positive` polarity, or a use handle to a value.
+    MisplacedHandle {
+        node: EntityId<I>,
+        handle: EntityId<I>,
+        polarity: Polarity,
+    },
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Checks that the graph has a node for every head, that every edge
+    /// goes from a value or var into a use or var, and that every edge is
+    /// recorded at both of its ends. Returns every inconsistency found,
+    /// which is nothing for a checker only used through its API.
+    pub fn validate(&self) -> Vec<Inconsistency<I>> {
+        let mut found = Vec::new();
+        if self.types.len() != self.r.len() {
+            found.push(Inconsistency::NodeCount {
+                types: self.types.len(),
+                graph: self.r.len(),
+            });
+            return found;
+        }
+
+        for lhs in self.r.nodes() {
+            for rhs in self.r.downstream(lhs) {
+                if let TypeNode::Use(_) = self.types[usize::from(lhs)] {
+                    found.push(Inconsistency::EdgeFromUse { lhs, rhs });
+                }
+                if let TypeNode::Value(_) = self.types[usize::from(rhs)] {
+                    found.push(Inconsistency::EdgeIntoValue { lhs, rhs });
+                }
+                if !self.r.upstream(rhs).any(|other| other == lhs) {
+                    found.push(Inconsistency::AsymmetricEdge { lhs, rhs });
+                }
+            }
+            for other in self.r.upstream(lhs) {
+                if !self.r.has_edge(other, lhs) {
+                    found.push(Inconsistency::AsymmetricEdge {
+                        lhs: other,
+                        rhs: lhs,
+                    });
+                }
+            }
+        }
+        found
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Instantiate<I>,
+    U: Instantiate<I>,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Checks everything `validate` does, and also that every handle
+    /// nested inside a head refers to a node of its own kind or to a var.
+    pub fn validate_heads(&self) -> Vec<Inconsistency<I>> {
+        let mut found = self.validate();
+        if !found.is_empty() {
+            return found;
+        }
+
+        let handles = core::cell::RefCell::new(Vec::new());
+        let values = |value: Value<I>| {
+            handles.borrow_mut().push((value.0, Polarity::Positive));
+            value
+        };
+        let uses = |rhs: Use<I>| {
+            handles.borrow_mut().push((rhs.0, Polarity::Negative));
+            rhs
+        };
+        for node in self.r.nodes() {
+            match &self.types[usize::from(node)] {
+                TypeNode::Value(head) => drop(head.instantiate(&values, &uses)),
+                TypeNode::Use(head) => drop(head.instantiate(&values, &uses)),
+                TypeNode::Var => continue,
+            }
+            for (handle, polarity) in handles.borrow_mut().drain(..) {
+                let misplaced = matches!(
+                    (self.types.get(usize::from(handle)), polarity),
+                    (None, _)
+                        | (Some(TypeNode::Use(_)), Polarity::Positive)
+                        | (Some(TypeNode::Value(_)), Polarity::Negative)
+                );
+                if misplaced {
+                    found.push(Inconsistency::MisplacedHandle {
+                        node,
+                        handle,
+                        polarity,
+                    });
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Head, Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn checkers_used_through_the_api_should_be_valid() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let (x_val, x_use) = t.var();
+        let sink = t.new_use(Prim::Int);
+        assert!(t.flow(int, x_use).is_ok());
+        assert!(t.flow(x_val, sink).is_ok());
+        assert!(t.validate().is_empty());
+    }
+
+    #[test]
+    fn swapped_handles_should_be_reported() {
+        let mut t = TypeChecker::new(Strict);
+        let int = t.new_val(Prim::Int);
+        let sink = t.new_use(Prim::Int);
+        // handles made from the wrong side, as a meet swapping its pairs
+        // would return.
+        assert!(t.flow(Value(sink.0), Use(int.0)).is_ok());
+        assert_eq!(
+            vec![
+                Inconsistency::EdgeFromUse {
+                    lhs: sink.0,
+                    rhs: int.0
+                },
+                Inconsistency::EdgeIntoValue {
+                    lhs: sink.0,
+                    rhs: int.0
+                },
+            ],
+            t.validate()
+        );
+    }
+
+    #[test]
+    fn handles_nested_on_the_wrong_side_should_be_reported() {
+        let mut t = TypeChecker::new(Strict);
+        let (elem, _) = t.var();
+        let list = t.new_val(Head::List(elem));
+        assert!(t.validate_heads().is_empty());
+
+        let sink = t.new_use(Head::List(elem));
+        let misplaced = t.new_val(Head::List(Value(sink.0)));
+        assert_eq!(
+            vec![Inconsistency::MisplacedHandle {
+                node: misplaced.0,
+                handle: sink.0,
+                polarity: Polarity::Positive,
+            }],
+            t.validate_heads()
+        );
+        assert!(t.flow(list, sink).is_ok());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{AbstractTypes, Child, NodeIndex, TypeChecker, Use, Value};
+
+/// Variance is the direction a child of a constructor flows in relative to
+/// the constructor itself: along with it for a covariant child, such as the
+/// result of a function, and against it for a contravariant one, such as
+/// its argument.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Variance {
+    Covariant,
+    Contravariant,
+}
+
+impl Variance {
+    /// Returns the opposite variance.
+    pub fn flip(self) -> Self {
+        match self {
+            Variance::Covariant => Variance::Contravariant,
+            Variance::Contravariant => Variance::Covariant,
+        }
+    }
+
+    /// Returns the flow a meet adds between a child of a value head and the
+    /// matching child of a use head. A covariant child flows from the
+    /// value's child into the use's, while a contravariant child flows the
+    /// other way, so that a meet declares each child's variance rather than
+    /// swapping pairs by hand.
+    ///
+    /// Panics if the children aren't the handles the variance calls for: a
+    /// value and a use when covariant, or a use and a value when
+    /// contravariant.
+    pub fn relate<I: NodeIndex>(
+        self,
+        value_child: Child<I>,
+        use_child: Child<I>,
+    ) -> (Value<I>, Use<I>) {
+        match (self, value_child, use_child) {
+            (Variance::Covariant, Child::Value(lhs), Child::Use(rhs)) => (lhs, rhs),
+            (Variance::Contravariant, Child::Use(rhs), Child::Value(lhs)) => (lhs, rhs),
+            (variance, _, _) => panic!("children don't match a {:?} position", variance),
+        }
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Adds a flow written from the use's side, as a contravariant position
+    /// such as a parameter reads, so that `flow_contra(param, arg)` flows
+    /// the argument into the parameter.
+    pub fn flow_contra(&mut self, rhs: Use<I>, lhs: Value<I>) -> Result<(), AT::Error> {
+        self.flow(lhs, rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn variances_should_orient_the_flows_between_children() {
+        let mut t = TypeChecker::new(Strict);
+        let (arg, arg_use) = t.var();
+        let (ret, ret_use) = t.var();
+        assert_eq!(
+            (arg, ret_use),
+            Variance::Contravariant.relate(Child::Use(ret_use), Child::Value(arg))
+        );
+        assert_eq!(
+            (ret, arg_use),
+            Variance::Covariant.relate(Child::Value(ret), Child::Use(arg_use))
+        );
+        assert_eq!(Variance::Covariant, Variance::Contravariant.flip());
+
+        let int = t.new_val(Prim::Int);
+        assert!(t.flow_contra(arg_use, int).is_ok());
+        assert!(t.reaches(int, arg_use));
+    }
+
+    #[test]
+    #[should_panic(expected = "children don't match a Covariant position")]
+    fn mismatched_children_should_panic() {
+        let mut t = TypeChecker::<Prim, Prim, _>::new(Strict);
+        let (arg, arg_use) = t.var();
+        Variance::Covariant.relate(Child::Use(arg_use), Child::Value(arg));
+    }
+}
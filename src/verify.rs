@@ -0,0 +1,111 @@
+//! Idempotence checking of flows, enabled in the crate's own tests and by
+//! the `verify-idempotence` feature. Once a flow has been added, adding it
+//! again must change nothing, which only holds if every meet returns the
+//! same flows each time it is called for the same heads.
+
+use crate::{AbstractTypes, EntityId, MeetContext, NodeIndex, TypeChecker, Use, Value};
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Replays a flow that was just added, asserting that it adds no new
+    /// edge and that repeating every meet it caused only returns flows that
+    /// are already in the graph.
+    pub(crate) fn verify_idempotent(&self, lhs: Value<I>, rhs: Use<I>) {
+        let (lhs, rhs) = (self.aliases.root(lhs.0), self.aliases.root(rhs.0));
+        // a graph skipping self-edges never holds the flow of a var into itself.
+        let has_edge = |lhs: EntityId<I>, rhs: EntityId<I>| {
+            self.r.has_edge(lhs, rhs) || (lhs == rhs && self.graph_config.skip_self_edges)
+        };
+        assert!(
+            has_edge(lhs, rhs),
+            "flow from {} to {} is missing after being added",
+            usize::from(lhs),
+            usize::from(rhs)
+        );
+
+        let sources: Vec<EntityId<I>> = core::iter::once(lhs).chain(self.r.upstream(lhs)).collect();
+        let sinks: Vec<EntityId<I>> = core::iter::once(rhs)
+            .chain(self.r.downstream(rhs))
+            .collect();
+        let types = &self.abstract_type_mapper;
+        let existing = |id: EntityId<I>| usize::from(id) < self.types.len();
+        for &source in &sources {
+            for &sink in &sinks {
+                let heads = self
+                    .value_heads(source)
+                    .flat_map(|lhs_head| {
+                        self.use_heads(sink)
+                            .map(move |rhs_head| (lhs_head, rhs_head))
+                    })
+                    .filter(|&(lhs_head, rhs_head)| {
+                        !types.is_bottom(lhs_head) && !types.is_top(rhs_head)
+                    });
+                for (lhs_head, rhs_head) in heads {
+                    // nodes a meet creates are new on every call, so only the
+                    // flows between existing nodes can be compared.
+                    let mut ctx = MeetContext::new(self.types.len());
+                    let flows = match types.meet_with(&mut ctx, lhs_head, rhs_head) {
+                        Ok(flows) => flows,
+                        Err(_) => panic!(
+                            "meet of {} and {} failed when repeated",
+                            usize::from(source),
+                            usize::from(sink)
+                        ),
+                    };
+                    for (value, target) in flows
+                        .into_iter()
+                        .filter(|(value, target)| existing(value.0) && existing(target.0))
+                    {
+                        let (value, target) =
+                            (self.aliases.root(value.0), self.aliases.root(target.0));
+                        assert!(
+                            has_edge(value, target),
+                            "meet of {} and {} returned flow from {} to {} when repeated",
+                            usize::from(source),
+                            usize::from(sink),
+                            usize::from(value),
+                            usize::from(target)
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Leaky returns a flow to a different node on every call.
+    #[derive(Debug)]
+    struct Leaky;
+
+    impl AbstractTypes<Vec<Use>, ()> for Leaky {
+        type Error = TypeError;
+
+        fn meet(&self, lhs: &Vec<Use>, _: &()) -> Result<Vec<(Value, Use)>, Self::Error> {
+            let call = CALLS.fetch_add(1, Ordering::Relaxed);
+            Ok(vec![(
+                Value(lhs[call % lhs.len()].0),
+                lhs[call % lhs.len()],
+            )])
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "when repeated")]
+    fn leaky_meets_should_be_caught() {
+        let mut t = TypeChecker::new(Leaky);
+        let (a, b) = (t.var(), t.var());
+        let value = t.new_val(vec![a.1, b.1]);
+        let sink = t.new_use(());
+        let _ = t.flow(value, sink);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{AbstractTypes, NodeIndex, TypeChecker, Use, Value};
+use alloc::sync::Arc;
+
+/// TypeCheckerView is a frozen, cheaply cloned copy of a checker, exposing
+/// every query of the checker through `Deref`. A view is `Send` and `Sync`
+/// whenever the checker's heads and type system are, so a language server
+/// can hand the last good result to request handlers while a new check
+/// runs elsewhere.
+#[derive(Debug)]
+pub struct TypeCheckerView<V, U, AT, I = u32>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    checker: Arc<TypeChecker<V, U, AT, I>>,
+}
+
+impl<V, U, AT, I> Clone for TypeCheckerView<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            checker: Arc::clone(&self.checker),
+        }
+    }
+}
+
+impl<V, U, AT, I> core::ops::Deref for TypeCheckerView<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    type Target = TypeChecker<V, U, AT, I>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.checker
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I>,
+{
+    /// Returns true if a value reaches a use, directly or transitively.
+    pub fn reaches(&self, lhs: Value<I>, rhs: Use<I>) -> bool {
+        self.r
+            .has_edge(self.aliases.root(lhs.0), self.aliases.root(rhs.0))
+    }
+
+    /// Freezes the checker into a view that can be shared across threads.
+    pub fn into_view(self) -> TypeCheckerView<V, U, AT, I> {
+        TypeCheckerView {
+            checker: Arc::new(self),
+        }
+    }
+}
+
+impl<V, U, AT, I> TypeChecker<V, U, AT, I>
+where
+    V: Clone,
+    U: Clone,
+    I: NodeIndex,
+    AT: AbstractTypes<V, U, I> + Clone,
+{
+    /// Returns a view of a copy of the checker, leaving the checker free to
+    /// be changed.
+    pub fn view(&self) -> TypeCheckerView<V, U, AT, I> {
+        self.clone().into_view()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_systems::{Prim, Strict};
+    use crate::*;
+
+    #[test]
+    fn views_should_be_queried_from_other_threads() {
+        let mut t = TypeChecker::new(Strict);
+        let (x_val, x_use) = t.var();
+        let int = t.new_val(Prim::Int);
+        assert!(t.flow(int, x_use).is_ok());
+
+        let (_, later) = t.var();
+        let view = t.view();
+        assert!(t.flow(x_val, later).is_ok());
+
+        let handle = std::thread::spawn(move || {
+            (
+                view.describe(x_val).to_string(),
+                view.reaches(int, x_use),
+                view.reaches(x_val, later),
+            )
+        });
+        assert_eq!(("int".to_string(), true, false), handle.join().unwrap());
+    }
+}